@@ -12,7 +12,7 @@ async fn main() {
     .subcommand(Command::new("create-admin").about("creates an admin user"))
     .subcommand(Command::new("populate-block-times").about("Populates bulletin block times"))
     .subcommand(Command::new("populate-backup-storage").about("Copies all files from current DO storage to AWS backup storage"))
-    .subcommand(Command::new("check-template-schemas").about("Typechecks all stored template schemas"))
+    .subcommand(Command::new("check-template-schemas").about("Typechecks all stored template schemas and the variables their files use"))
     .get_matches();
 
   match matches.subcommand() {
@@ -115,6 +115,19 @@ async fn check_template_schemas() -> ConstataResult<()> {
     print!(".");
     if let Err(e) = template.parsed_schema() {
       println!("Template {} has invalid schema {:?}", template.id(), e);
+      continue;
+    }
+
+    match template.check_variables().await {
+      Err(e) => println!("Template {} could not be checked {:?}", template.id(), e),
+      Ok(check) => {
+        if !check.unknown_variables.is_empty() {
+          println!("Template {} uses unknown variables: {}", template.id(), check.unknown_variables.join(", "));
+        }
+        if !check.unused_required_fields.is_empty() {
+          println!("Template {} never uses required fields: {}", template.id(), check.unused_required_fields.join(", "));
+        }
+      }
     }
   }
   Ok(())
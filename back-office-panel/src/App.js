@@ -26,11 +26,12 @@ import {SubscriptionShow, SubscriptionEdit}
 import {TermsAcceptanceList, TermsAcceptanceShow}
         from './views/terms_acceptance';
 import {TemplateList, TemplateShow, TemplateCreate, TemplateEdit} from './views/template';
+import {JobList, JobShow} from './views/job';
 import MissingTokenList from './views/missing_token';
 import TopTenList from './views/top_ten';
 import {PermIdentity, PostAdd, Assignment, AttachMoney, VpnKey, Pause, Create, Fingerprint, Image,
         Link, Receipt, AccountCircle, DynamicFeed, DomainVerification, SupervisorAccount,
-        CardGiftcard, AlternateEmail, EmojiEvents, Delete, Inbox, Business} from '@mui/icons-material';
+        CardGiftcard, AlternateEmail, EmojiEvents, Delete, Inbox, Business, WorkHistory} from '@mui/icons-material';
 import { Login } from 'ra-ui-materialui';
 import { LoginForm } from './views/login.tsx';
 import {
@@ -109,6 +110,18 @@ function App() {
             }`
           };
 
+        } else if (resource === 'RetryJob') {
+          return {
+            parseResponse: (data) => ({ data: data.data.data }),
+            variables: params.data,
+            query: gql`mutation($id:Int!){
+              data: retryJob(id: $id) {
+                id,
+                state,
+              }
+            }`
+          };
+
         } else {
           return buildQuery(introspection)(fetchType, resource, params);
         }
@@ -287,6 +300,13 @@ function App() {
           icon={Delete}
           options={{ label: 'Organization Deletions' }}
         />,
+        <Resource
+          name="Job"
+          list={JobList}
+          show={JobShow}
+          icon={WorkHistory}
+          options={{ label: 'Jobs' }}
+        />,
 
         <Resource
           name="TopTen"
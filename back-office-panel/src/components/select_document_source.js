@@ -6,6 +6,7 @@ const SelectDocumentSources = ({source, validate}) => {
     { id: 'Email', name: 'resources.Document.fields.sourcedFroms.EMAIL' },
     { id: 'Api', name: 'resources.Document.fields.sourcedFroms.API' },
     { id: 'Internal', name: 'resources.Document.fields.sourcedFroms.INTERNAL' },
+    { id: 'WebCapture', name: 'resources.Document.fields.sourcedFroms.WEB_CAPTURE' },
   ]} />
 )};
 
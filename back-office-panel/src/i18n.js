@@ -52,6 +52,7 @@ module.exports = {
             API: "Api",
             TELEGRAM: "Telegram",
             INTERNAL: "Internal",
+            WEB_CAPTURE: "Web capture",
           },
           authorId: 'Author',
           funded: 'Funded?',
@@ -520,6 +521,40 @@ module.exports = {
           completedEq: 'By Completed:',
         },
       },
+      Job: {
+        name: 'Job |||| Jobs',
+        retry: 'Retry',
+        fields: {
+          id: 'Nº',
+          kind: 'Kind',
+          kinds: {
+            RENDER_ISSUANCE: 'Render Issuance',
+            COMPLETE_ISSUANCE: 'Complete Issuance',
+            ATTEMPT_WEB_CALLBACK: 'Attempt Web Callback',
+            SEND_EMAIL_CALLBACK: 'Send Email Callback',
+            APPLY_BATCH_SIGNATURE: 'Apply Batch Signature',
+          },
+          resourceId: 'Resource',
+          state: 'State',
+          states: {
+            QUEUED: 'Queued',
+            RUNNING: 'Running',
+            DONE: 'Done',
+            FAILED: 'Failed',
+          },
+          attempts: 'Attempts',
+          maxAttempts: 'Max Attempts',
+          runAt: 'Run At',
+          lockedAt: 'Locked At',
+          lastError: 'Last Error',
+          createdAt: 'Created At',
+          finishedAt: 'Finished At',
+          idEq: 'By Nº:',
+          kindEq: 'By Kind:',
+          resourceIdEq: 'By Resource:',
+          stateEq: 'By State:',
+        },
+      },
       MissingToken: {
         name: 'Missing Tokens',
         fields: {
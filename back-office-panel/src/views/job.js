@@ -0,0 +1,107 @@
+import React from "react";
+import { List, Datagrid, TextField, ShowButton, SelectInput, Show, SimpleShowLayout,
+         NumberField, FunctionField, TopToolbar, Button, useShowController,
+         useDataProvider, useNotify, useRefresh } from 'react-admin'
+import { Replay } from '@mui/icons-material';
+import {PostPagination, defaultSort} from "../components/utils";
+import {TopToolbarDefault} from "../components/top_toolbars";
+import TranslatedTextField from "../components/translated_textfield";
+import ParsedDateTextField from "../components/parsed_date_textfield";
+import FilterTextInput from "../components/filter_textinput";
+
+const RetryJobButton = ({jobId}) => {
+  const dataProvider = useDataProvider();
+  const notify = useNotify();
+  const refresh = useRefresh();
+
+  const handleClick = async () => {
+    try {
+      await dataProvider.create('RetryJob', {data: {id: jobId}});
+    } catch {
+      notify('admin.errors.default', {type: 'warning'});
+      return;
+    }
+    notify('resources.actions.updated');
+    refresh();
+  };
+
+  return (
+    <Button label="resources.Job.retry" onClick={handleClick}>
+      <Replay/>
+    </Button>
+  );
+};
+
+function JobList() {
+  const jobFilters = [
+    <FilterTextInput source="idEq" />,
+    <FilterTextInput source="resourceIdEq" />,
+    <SelectInput source="kindEq" choices={[
+      { id: 'RENDER_ISSUANCE', name: "resources.Job.fields.kinds.RENDER_ISSUANCE" },
+      { id: 'COMPLETE_ISSUANCE', name: "resources.Job.fields.kinds.COMPLETE_ISSUANCE" },
+      { id: 'ATTEMPT_WEB_CALLBACK', name: "resources.Job.fields.kinds.ATTEMPT_WEB_CALLBACK" },
+      { id: 'SEND_EMAIL_CALLBACK', name: "resources.Job.fields.kinds.SEND_EMAIL_CALLBACK" },
+      { id: 'APPLY_BATCH_SIGNATURE', name: "resources.Job.fields.kinds.APPLY_BATCH_SIGNATURE" },
+    ]} />,
+    <SelectInput source="stateEq" choices={[
+      { id: 'QUEUED', name: "resources.Job.fields.states.QUEUED" },
+      { id: 'RUNNING', name: "resources.Job.fields.states.RUNNING" },
+      { id: 'DONE', name: "resources.Job.fields.states.DONE" },
+      { id: 'FAILED', name: "resources.Job.fields.states.FAILED" },
+    ]} />,
+  ];
+
+  return (
+    <List
+      empty={false}
+      sort={defaultSort}
+      filters={jobFilters}
+      perPage={20}
+      pagination={<PostPagination />}
+      actions={<TopToolbarDefault/>}
+    >
+      <Datagrid bulkActionButtons={false}>
+        <TextField source='id'/>
+        <TranslatedTextField source="kind" translation="resources.Job.fields.kinds"/>
+        <NumberField source='resourceId' sortable={false} />
+        <TranslatedTextField source="state" translation="resources.Job.fields.states"/>
+        <NumberField source='attempts' />
+        <ParsedDateTextField source='runAt' />
+        <ShowButton />
+        <FunctionField
+          render={record => record.state === 'FAILED' && <RetryJobButton jobId={record.id} />}
+        />
+      </Datagrid>
+    </List>
+  );
+}
+
+function JobShow(){
+  const { record } = useShowController();
+
+  const JobShowActions = () => (
+    <TopToolbar>
+      {record?.state === 'FAILED' && <RetryJobButton jobId={record.id} />}
+    </TopToolbar>
+  );
+
+  return (
+    <Show actions={<JobShowActions />}>
+      <SimpleShowLayout>
+        <NumberField source='id' />
+        <TranslatedTextField source="kind" translation="resources.Job.fields.kinds"/>
+        <NumberField source='resourceId' />
+        <TranslatedTextField source="state" translation="resources.Job.fields.states"/>
+        <NumberField source='attempts' />
+        <NumberField source='maxAttempts' />
+        <ParsedDateTextField source='runAt' />
+        <ParsedDateTextField source='lockedAt' />
+        <TextField source='lastError' />
+        <ParsedDateTextField source='createdAt' />
+        <ParsedDateTextField source='finishedAt' />
+      </SimpleShowLayout>
+    </Show>
+  );
+}
+
+export {JobList, JobShow};
@@ -45,10 +45,38 @@ commands! {
   ///   "email":"john@example.com",
   ///   "custom_text":"Large custom text"
   /// }
-  CreateIssuanceFromJson => print_json,
+  ///
+  /// Use --dry-run to check your entries against the template and render a few of them, without creating anything.
+  CreateIssuanceFromJson => |runner, query| {
+    if query.dry_run {
+      runner.print_json(&query.dry_run(&runner.client)?)?;
+    } else {
+      runner.print_json(&query.run(&runner.client)?)?;
+    }
+  },
 
   /// Start an issuance using a CSV file as initial entries.
-  CreateIssuanceFromCsv => print_json,
+  ///
+  /// Use --dry-run to check your entries against the template and render a few of them, without creating anything.
+  CreateIssuanceFromCsv => |runner, query| {
+    if query.dry_run {
+      runner.print_json(&query.dry_run(&runner.client)?)?;
+    } else {
+      runner.print_json(&query.run(&runner.client)?)?;
+    }
+  },
+
+  /// Start an issuance using the first sheet of an XLSX or ODS file as initial entries.
+  ///
+  /// Column headers are matched to template fields by name or label, use --map-column for those that don't match.
+  /// Use --sheet-name to read another sheet, and --dry-run to check your entries without creating anything.
+  CreateIssuanceFromSpreadsheet => |runner, query| {
+    if query.dry_run {
+      runner.print_json(&query.dry_run(&runner.client)?)?;
+    } else {
+      runner.print_json(&query.run(&runner.client)?)?;
+    }
+  },
 
   /// Append entries to a previously created issuance before signing it. 
   AppendEntriesToIssuance => print_json,
@@ -73,6 +101,11 @@ commands! {
   /// See the entry-state to check for the entry status before calling.
   EntryHtmlExport => print_json_or_save("Verifiable HTML for Entry {} saved to file"),
 
+  /// Exports an entry as an Open Badges 3.0 verifiable credential.
+  ///
+  /// Only available for completed diplomas and badges.
+  EntryVerifiableCredential => print_json_or_save("Verifiable credential for Entry {} saved to file"),
+
   /// Exports all verifiable HTMLs from entries matching the given criteria
   ///
   /// Use all-entries to review your query before downloading.
@@ -96,6 +129,7 @@ commands! {
   /// Sign all entries in an issuance.
   ///
   /// This will download all entries and digitally sign them locally with your secure digital signature.
+  /// With --batch, a single signature over the merkle root of all entry hashes signs the whole issuance.
   SignIssuance => |runner, query| {
     use std::io::Write;
     query.run(&runner.client, |i| {
@@ -116,6 +150,21 @@ commands! {
   /// Creates a new attestation of some files.
   CreateAttestation => print_json,
 
+  /// Creates many attestations from a CSV or JSON manifest, signing every file locally and uploading them in batches.
+  ///
+  /// The id and state of each attestation are written to a results file as batches are created.
+  /// Running it again with the same manifest skips the rows that were already created.
+  CreateAttestationsFromManifest => print_json,
+
+  /// Adds more files to an attestation that is still open.
+  AppendDocumentsToAttestation => print_json,
+
+  /// Co-signs every file of an attestation you were invited to.
+  CosignAttestation => print_json,
+
+  /// Captures a web page as a WARC file and attests it.
+  CreateWebCaptureAttestation => print_json,
+
   /// Lists all your attestations
   AllAttestations => print_json,
 
@@ -145,11 +194,24 @@ commands! {
   /// Gets your organization's account state
   AccountState => print_json,
 
-  /// Sets your web callbacks URL
+  /// Registers a URL where we will send web callbacks
+  ///
+  /// You can register several, each one subscribed to some kinds of events with --kind.
+  /// Use --all-kinds instead to get every kind of event, including the ones we add later.
+  CreateWebCallbackEndpoint => print_json,
+
+  /// Changes the URL, subscribed kinds, or enabled status of a web callback endpoint
+  ///
+  /// Disabled endpoints don't get new callbacks, and their pending ones are failed.
+  UpdateWebCallbackEndpoint => print_json,
+
+  /// Replaces the secret used to sign the Constata-Signature header of a web callback endpoint
   ///
-  /// We will send a web callback to that URL when your attestations change state.
-  /// Call with an empty URL to reset it.
-  UpdateWebCallbacksUrl => print_json,
+  /// The old secret stays valid for --overlap-hours, so you can deploy the new one without missing callbacks.
+  RotateWebCallbackEndpointSecret => print_json,
+
+  /// Lists your web callback endpoints and the kinds of events they are subscribed to.
+  AllWebCallbackEndpoints => print_json,
 
   /// Lists your web callbacks, for debugging and recovery.
   ///
@@ -158,6 +220,8 @@ commands! {
   AllWebCallbacks => print_json,
 
   /// Validate Web Callback, and outputs its contents if valid.
+  ///
+  /// Pass your endpoint's --secret and the --signature-header you got to also check the Constata-Signature HMAC.
   ValidateWebCallback => print_json,
 
   /// Run a custom graphql query authenticated with your credentials.
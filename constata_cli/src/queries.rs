@@ -16,6 +16,7 @@ pub mod gql_fields;
 pub_mods!{
   create_issuance_from_json::CreateIssuanceFromJson;
   create_issuance_from_csv::CreateIssuanceFromCsv;
+  create_issuance_from_spreadsheet::CreateIssuanceFromSpreadsheet;
   custom_graphql::CustomGraphql;
   append_entries_to_issuance::AppendEntriesToIssuance;
   account_state::AccountState;
@@ -23,9 +24,15 @@ pub_mods!{
   attestation_state::AttestationState;
   sign_issuance::SignIssuance;
   create_attestation::CreateAttestation;
+  create_attestations_from_manifest::CreateAttestationsFromManifest;
   attestation_set_published::AttestationSetPublished;
+  append_documents_to_attestation::AppendDocumentsToAttestation;
+  cosign_attestation::CosignAttestation;
+  create_web_capture_attestation::CreateWebCaptureAttestation;
   preview_sample_from_issuance::PreviewSampleFromIssuance;
-  update_web_callbacks_url::UpdateWebCallbacksUrl;
+  create_web_callback_endpoint::CreateWebCallbackEndpoint;
+  update_web_callback_endpoint::UpdateWebCallbackEndpoint;
+  rotate_web_callback_endpoint_secret::RotateWebCallbackEndpointSecret;
   validate_web_callback::ValidateWebCallback;
 }
 
@@ -68,6 +75,28 @@ query_by_id_and_save_file_template!{
   verifiable_html
 }
 
+query_by_id_and_save_file_template!{
+  entry_verifiable_credential,
+  gql_types::EntryVerifiableCredential,
+  EntryVerifiableCredential,
+  "Id of the entry your want to export as an Open Badges verifiable credential.",
+  "\
+    Write the JSON-LD credential here, it can be imported into any Open Badges 3.0 wallet. \
+    Use --json-pointer=/credential to extract the JSON and print it to stdout.
+  ",
+  "EntryVerifiableCredential",
+  &format!("\
+    id
+    entry {{
+      {}
+    }}
+    filename
+    credential
+    __typename
+  ", gql_fields::ENTRY),
+  credential
+}
+
 query_by_id_and_save_file_template!{
   attestation_html_export,
   gql_types::AttestationHtmlExport,
@@ -193,3 +222,14 @@ collection_query_template!{
   "_allWebCallbacksMeta",
   gql_fields::WEB_CALLBACK
 }
+
+collection_query_template!{
+  all_web_callback_endpoints,
+  AllWebCallbackEndpoints,
+  gql_types::WebCallbackEndpoint,
+  gql_types::WebCallbackEndpointFilter,
+  "WebCallbackEndpointFilter",
+  "allWebCallbackEndpoints",
+  "_allWebCallbackEndpointsMeta",
+  gql_fields::WEB_CALLBACK_ENDPOINT
+}
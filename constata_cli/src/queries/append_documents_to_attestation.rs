@@ -0,0 +1,31 @@
+use super::*;
+use constata_lib::prelude::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendDocumentsToAttestation {
+  /// The attestation to append documents to. It must still be open.
+  pub attestation_id: i32,
+
+  /// A list of paths to the files to add to your attestation.
+  #[arg(short, long="path", value_name="PATH", action=clap::ArgAction::Append,
+    help="Path to a file you want to append to your attestation. You can repeat this argument to add many.")]
+  pub paths: Vec<PathBuf>,
+}
+
+impl AppendDocumentsToAttestation {
+  pub fn run(self, client: &Client) -> ClientResult<Attestation> {
+    let documents: Vec<SignedPayload> = self.paths.iter()
+      .map(|f| ex::fs::read(f).map(|x| client.sign(&x) ).into() )
+      .collect::<Result<Vec<_>, ex::io::Error>>()?;
+
+    let input = AppendDocumentsToAttestationInput{ attestation_id: self.attestation_id, documents };
+    client.mutation(&QueryWithInput{ input }, "appendDocumentsToAttestation", "AppendDocumentsToAttestationInput", gql_fields::ATTESTATION)
+  }
+}
+
+#[derive(serde::Serialize)]
+struct QueryWithInput {
+  input: AppendDocumentsToAttestationInput,
+}
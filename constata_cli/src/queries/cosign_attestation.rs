@@ -0,0 +1,31 @@
+use super::*;
+use constata_lib::prelude::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct CosignAttestation {
+  /// The attestation you were invited to co-sign.
+  pub attestation_id: i32,
+
+  /// A list of paths to every file in the attestation.
+  #[arg(short, long="path", value_name="PATH", action=clap::ArgAction::Append,
+    help="Path to one of the files in the attestation. Repeat this argument once for every file, you must co-sign all of them.")]
+  pub paths: Vec<PathBuf>,
+}
+
+impl CosignAttestation {
+  pub fn run(self, client: &Client) -> ClientResult<Cosigner> {
+    let documents: Vec<SignedPayload> = self.paths.iter()
+      .map(|f| ex::fs::read(f).map(|x| client.sign(&x) ).into() )
+      .collect::<Result<Vec<_>, ex::io::Error>>()?;
+
+    let input = CosignAttestationInput{ attestation_id: self.attestation_id, documents };
+    client.mutation(&QueryWithInput{ input }, "cosignAttestation", "CosignAttestationInput", gql_fields::COSIGNER)
+  }
+}
+
+#[derive(serde::Serialize)]
+struct QueryWithInput {
+  input: CosignAttestationInput,
+}
@@ -24,6 +24,24 @@ pub struct CreateAttestation {
   #[arg(short, long, value_name="PATH", action=clap::ArgAction::Append,
     help="Email an admin access link to view, download and share this attestation to the given addresses.")]
   pub email_admin_access_url_to: Vec<String>,
+
+  /// Invite someone to co-sign every document, by email. Documents are not stamped until enough cosigners sign.
+  #[arg(long="cosigner-email", value_name="EMAIL", action=clap::ArgAction::Append,
+    help="Email address of someone who must co-sign the attestation. You can repeat this argument to add many.")]
+  pub cosigner_emails: Vec<String>,
+
+  /// Invite someone to co-sign every document, by their public key.
+  #[arg(long="cosigner-pubkey", value_name="PUBKEY", action=clap::ArgAction::Append,
+    help="Public key of someone who must co-sign the attestation. You can repeat this argument to add many.")]
+  pub cosigner_pubkeys: Vec<String>,
+
+  /// How many cosigners must sign before the documents are stamped. Defaults to all of them.
+  #[arg(long)]
+  pub cosigning_threshold: Option<i32>,
+
+  /// Cosigners who haven't signed by this date are timed out. Defaults to two weeks from now.
+  #[arg(long)]
+  pub cosigning_deadline: Option<UtcDateTime>,
 }
 
 impl CreateAttestation {
@@ -32,11 +50,19 @@ impl CreateAttestation {
       .map(|f| ex::fs::read(f).map(|x| client.sign(&x) ).into() )
       .collect::<Result<Vec<_>, ex::io::Error>>()?;
 
+    let cosigners: Vec<CosignerInput> = self.cosigner_emails.into_iter()
+      .map(|email| CosignerInput{ email: Some(email), pubkey: None })
+      .chain(self.cosigner_pubkeys.into_iter().map(|pubkey| CosignerInput{ email: None, pubkey: Some(pubkey) }))
+      .collect();
+
     QueryWithAttestationInput::from(AttestationInput{
       documents,
       open_until: self.open_until,
       markers: self.markers,
       email_admin_access_url_to: self.email_admin_access_url_to,
+      cosigners: (!cosigners.is_empty()).then(|| cosigners),
+      cosigning_threshold: self.cosigning_threshold,
+      cosigning_deadline: self.cosigning_deadline,
     }).run(client)
   }
 }
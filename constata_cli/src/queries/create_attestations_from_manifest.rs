@@ -0,0 +1,161 @@
+use super::*;
+use constata_lib::prelude::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAttestationsFromManifest {
+  /// A CSV or JSON file with one attestation per row.
+  ///
+  /// CSV manifests have the columns paths, markers and open_until, with paths separated by ';'.
+  /// JSON manifests are an array of objects like {"paths": ["contract.pdf"], "markers": "acme", "open_until": "2030-01-01T00:00:00Z"}.
+  /// Paths are relative to the manifest's directory.
+  pub manifest: PathBuf,
+
+  /// Where to write the id and state of each attestation. Defaults to the manifest path ending in .results.csv
+  ///
+  /// When this file exists, rows that already have an attestation id are skipped, so you can run the same command
+  /// again to resume after an error.
+  #[arg(long)]
+  pub results_file: Option<PathBuf>,
+
+  /// How many attestations to send on each request, at most 50.
+  #[arg(long, default_value_t = 20)]
+  pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestRow {
+  pub paths: Vec<PathBuf>,
+  #[serde(default)]
+  pub markers: Option<String>,
+  #[serde(default)]
+  pub open_until: Option<UtcDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvManifestRow {
+  paths: String,
+  #[serde(default)]
+  markers: Option<String>,
+  #[serde(default)]
+  open_until: Option<UtcDateTime>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ManifestResult {
+  pub row: usize,
+  pub paths: String,
+  pub attestation_id: Option<i32>,
+  pub state: Option<String>,
+  pub error: Option<String>,
+}
+
+impl CreateAttestationsFromManifest {
+  pub fn run(self, client: &Client) -> ClientResult<Vec<ManifestResult>> {
+    let rows = self.read_manifest()?;
+    let results_path = self.results_file.clone().unwrap_or_else(|| self.manifest.with_extension("results.csv"));
+
+    let mut results: Vec<ManifestResult> = if results_path.exists() {
+      let mut reader = check!(csv::Reader::from_path(&results_path), InvalidInput("Could not read results file"));
+      check!(reader.deserialize().collect::<Result<Vec<_>, _>>(), InvalidInput("Results file is not valid"))
+    } else {
+      vec![]
+    };
+
+    let pending: Vec<(usize, &ManifestRow)> = rows.iter().enumerate()
+      .filter(|(i, row)| !results.iter().any(|r| r.row == *i && r.paths == joined_paths(row) && r.attestation_id.is_some()))
+      .collect();
+    results.retain(|r| !pending.iter().any(|(i, _)| r.row == *i));
+
+    for batch in pending.chunks(self.batch_size.clamp(1, 50)) {
+      let mut attestations = vec![];
+      for (_, row) in batch {
+        attestations.push(self.attestation_input(client, row)?);
+      }
+
+      let query = QueryWithInput{ input: CreateAttestationsInput{ attestations } };
+      let created: ClientResult<Vec<CreatedAttestation>> =
+        client.mutation(&query, "createAttestations", "CreateAttestationsInput", gql_fields::CREATED_ATTESTATION);
+
+      /* Each attestation has its own result, rows that failed on their own are retried on the next run. */
+      match created {
+        Ok(created) => {
+          for created in created {
+            let Some((i, row)) = batch.get(created.index as usize) else { continue };
+            results.push(ManifestResult{
+              row: *i,
+              paths: joined_paths(row),
+              attestation_id: created.attestation.as_ref().map(|a| a.id),
+              state: created.attestation.map(|a| a.state),
+              error: created.error,
+            });
+          }
+          write_results(&results_path, &mut results)?;
+        },
+        Err(e) => {
+          for (i, row) in batch {
+            results.push(ManifestResult{ row: *i, paths: joined_paths(row), attestation_id: None, state: None, error: Some(e.to_string()) });
+          }
+          write_results(&results_path, &mut results)?;
+          return Err(e);
+        }
+      }
+    }
+
+    Ok(results)
+  }
+
+  fn read_manifest(&self) -> ClientResult<Vec<ManifestRow>> {
+    let is_json = self.manifest.extension().map(|e| e.eq_ignore_ascii_case("json")).unwrap_or(false);
+
+    if is_json {
+      return Ok(serde_json::from_str(&ex::fs::read_to_string(&self.manifest)?)?);
+    }
+
+    let mut reader = check!(csv::Reader::from_path(&self.manifest), InvalidInput("Could not read manifest"));
+    let rows: Vec<CsvManifestRow> = check!(reader.deserialize().collect::<Result<Vec<_>, _>>(), InvalidInput("Manifest is not valid"));
+
+    Ok(rows.into_iter().map(|r| ManifestRow{
+      paths: r.paths.split(';').map(|p| PathBuf::from(p.trim())).filter(|p| !p.as_os_str().is_empty()).collect(),
+      markers: r.markers,
+      open_until: r.open_until,
+    }).collect())
+  }
+
+  fn attestation_input(&self, client: &Client, row: &ManifestRow) -> ClientResult<AttestationInput> {
+    let base = self.manifest.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let documents: Vec<SignedPayload> = row.paths.iter()
+      .map(|f| ex::fs::read(base.join(f)).map(|x| client.sign(&x) ).into() )
+      .collect::<Result<Vec<_>, ex::io::Error>>()?;
+
+    Ok(AttestationInput{
+      documents,
+      open_until: row.open_until,
+      markers: row.markers.clone(),
+      email_admin_access_url_to: vec![],
+      cosigners: None,
+      cosigning_threshold: None,
+      cosigning_deadline: None,
+    })
+  }
+}
+
+fn joined_paths(row: &ManifestRow) -> String {
+  row.paths.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(";")
+}
+
+fn write_results(path: &PathBuf, results: &mut Vec<ManifestResult>) -> ClientResult<()> {
+  results.sort_by_key(|r| r.row);
+  let mut writer = check!(csv::Writer::from_path(path), InvalidInput("Could not write results file"));
+  for result in results.iter() {
+    check!(writer.serialize(result), Unexpected("Could not write results file"));
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+#[derive(serde::Serialize)]
+struct QueryWithInput {
+  input: CreateAttestationsInput,
+}
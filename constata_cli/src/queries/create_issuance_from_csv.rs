@@ -13,10 +13,22 @@ pub struct CreateIssuanceFromCsv {
   #[arg(long, help="A path to a CSV file you want to use for creating this issuance.")]
   #[serde(skip)]
   pub csv_file: Option<PathBuf>,
+
+  #[arg(long, help="Only check the entries against the template and render a few of them, without creating the issuance.")]
+  #[serde(skip)]
+  pub dry_run: bool,
 }
 
 impl CreateIssuanceFromCsv {
-  pub fn run(mut self, client: &Client) -> ClientResult<Issuance> {
+  pub fn run(self, client: &Client) -> ClientResult<Issuance> {
+    client.mutation(&self.with_local_files()?, "createIssuanceFromCsv", "CreateIssuanceFromCsvInput", gql_fields::ISSUANCE)
+  }
+
+  pub fn dry_run(self, client: &Client) -> ClientResult<IssuanceDryRun> {
+    client.mutation(&self.with_local_files()?, "dryRunIssuanceFromCsv", "CreateIssuanceFromCsvInput", gql_fields::ISSUANCE_DRY_RUN)
+  }
+
+  fn with_local_files(mut self) -> ClientResult<Self> {
     if let Some(ref path) = self.new_logo_image_file {
       self.input.new_logo_image = Some(ex::fs::read(path)?);
     }
@@ -25,6 +37,6 @@ impl CreateIssuanceFromCsv {
       self.input.csv = ex::fs::read_to_string(path)?;
     }
 
-    client.mutation(&self, "createIssuanceFromCsv", "CreateIssuanceFromCsvInput", gql_fields::ISSUANCE)
+    Ok(self)
   }
 }
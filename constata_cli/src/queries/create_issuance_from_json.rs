@@ -13,10 +13,22 @@ pub struct CreateIssuanceFromJson {
   #[arg(long, help="A path to file with the JSON for your entries, an alternative to --entry")]
   #[serde(skip)]
   pub json_file: Option<PathBuf>,
+
+  #[arg(long, help="Only check the entries against the template and render a few of them, without creating the issuance.")]
+  #[serde(skip)]
+  pub dry_run: bool,
 }
 
 impl CreateIssuanceFromJson {
-  pub fn run(mut self, client: &Client) -> ClientResult<Issuance> {
+  pub fn run(self, client: &Client) -> ClientResult<Issuance> {
+    client.mutation(&self.with_local_files()?, "createIssuanceFromJson", "CreateIssuanceFromJsonInput", gql_fields::ISSUANCE)
+  }
+
+  pub fn dry_run(self, client: &Client) -> ClientResult<IssuanceDryRun> {
+    client.mutation(&self.with_local_files()?, "dryRunIssuanceFromJson", "CreateIssuanceFromJsonInput", gql_fields::ISSUANCE_DRY_RUN)
+  }
+
+  fn with_local_files(mut self) -> ClientResult<Self> {
     if let Some(ref path) = self.new_logo_image_file {
       self.input.new_logo_image = Some(ex::fs::read(path)?);
     }
@@ -28,6 +40,6 @@ impl CreateIssuanceFromJson {
       );
     }
 
-    client.mutation(&self, "createIssuanceFromJson", "CreateIssuanceFromJsonInput", gql_fields::ISSUANCE)
+    Ok(self)
   }
 }
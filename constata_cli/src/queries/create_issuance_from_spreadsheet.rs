@@ -0,0 +1,40 @@
+use super::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+pub struct CreateIssuanceFromSpreadsheet {
+  #[command(flatten)]
+  pub input: CreateIssuanceFromSpreadsheetInput,
+
+  #[arg(help="A path to the XLSX or ODS file you want to use for creating this issuance.")]
+  #[serde(skip)]
+  pub spreadsheet_file: PathBuf,
+
+  #[arg(long, help="A path to a PNG or JPEG file to use as logo in your new template")]
+  #[serde(skip)]
+  pub new_logo_image_file: Option<PathBuf>,
+
+  #[arg(long, help="Only check the entries against the template and render a few of them, without creating the issuance.")]
+  #[serde(skip)]
+  pub dry_run: bool,
+}
+
+impl CreateIssuanceFromSpreadsheet {
+  pub fn run(self, client: &Client) -> ClientResult<Issuance> {
+    client.mutation(&self.with_local_files()?, "createIssuanceFromSpreadsheet", "CreateIssuanceFromSpreadsheetInput", gql_fields::ISSUANCE)
+  }
+
+  pub fn dry_run(self, client: &Client) -> ClientResult<IssuanceDryRun> {
+    client.mutation(&self.with_local_files()?, "dryRunIssuanceFromSpreadsheet", "CreateIssuanceFromSpreadsheetInput", gql_fields::ISSUANCE_DRY_RUN)
+  }
+
+  fn with_local_files(mut self) -> ClientResult<Self> {
+    self.input.spreadsheet = ex::fs::read(&self.spreadsheet_file)?;
+
+    if let Some(ref path) = self.new_logo_image_file {
+      self.input.new_logo_image = Some(ex::fs::read(path)?);
+    }
+
+    Ok(self)
+  }
+}
@@ -0,0 +1,15 @@
+use super::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebCallbackEndpoint {
+  #[command(flatten)]
+  pub input: CreateWebCallbackEndpointInput,
+}
+
+impl CreateWebCallbackEndpoint {
+  pub fn run(self, client: &Client) -> ClientResult<WebCallbackEndpoint> {
+    client.mutation(&self, "createWebCallbackEndpoint", "CreateWebCallbackEndpointInput", gql_fields::WEB_CALLBACK_ENDPOINT)
+  }
+}
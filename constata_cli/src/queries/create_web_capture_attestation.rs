@@ -0,0 +1,15 @@
+use super::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebCaptureAttestation {
+  #[command(flatten)]
+  pub input: WebCaptureAttestationInput,
+}
+
+impl CreateWebCaptureAttestation {
+  pub fn run(self, client: &Client) -> ClientResult<Attestation> {
+    client.mutation(&self, "createWebCaptureAttestation", "WebCaptureAttestationInput", gql_fields::ATTESTATION)
+  }
+}
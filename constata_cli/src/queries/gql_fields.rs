@@ -119,6 +119,34 @@ pub const ATTESTATION: &'static str = "\
         }
     }
   }
+  cosigningThreshold
+  cosigningDeadline
+  cosigners {
+    attestationId
+    email
+    pubkeyId
+    state
+    signedBy
+    signedAt
+  }
+  __typename";
+
+pub const CREATED_ATTESTATION: &'static str = formatcp!("\
+  index
+  attestation {{
+    {ATTESTATION}
+  }}
+  error
+  __typename
+");
+
+pub const COSIGNER: &'static str = "\
+  attestationId
+  email
+  pubkeyId
+  state
+  signedBy
+  signedAt
   __typename";
 
 pub const ACCOUNT_STATE: &'static str = "\
@@ -138,16 +166,52 @@ pub const ACCOUNT_STATE: &'static str = "\
   }
   pendingTycUrl
   pendingInvoiceLinkUrl
-  webCallbacksUrl
+  __typename";
+
+pub const ISSUANCE_DRY_RUN: &'static str = "\
+  isValid
+  entriesCount
+  violations {
+    row
+    field
+    message
+    __typename
+  }
+  previews {
+    row
+    sizeInBytes
+    error
+    __typename
+  }
+  duplicates {
+    field
+    value
+    rows
+    __typename
+  }
+  estimatedTokens
   __typename";
 
 pub const WEB_CALLBACK: &'static str = "\
   id
   kind
   resourceId
+  documentId
+  webCallbackEndpointId
   state
   lastAttemptId
   createdAt
   nextAttemptOn
   requestBody
   __typename";
+
+pub const WEB_CALLBACK_ENDPOINT: &'static str = "\
+  id
+  url
+  kinds
+  allKinds
+  enabled
+  createdAt
+  secret
+  previousSecretExpiresAt
+  __typename";
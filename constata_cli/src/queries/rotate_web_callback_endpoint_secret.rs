@@ -0,0 +1,15 @@
+use super::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateWebCallbackEndpointSecret {
+  #[command(flatten)]
+  pub input: RotateWebCallbackEndpointSecretInput,
+}
+
+impl RotateWebCallbackEndpointSecret {
+  pub fn run(self, client: &Client) -> ClientResult<WebCallbackEndpoint> {
+    client.mutation(&self, "rotateWebCallbackEndpointSecret", "RotateWebCallbackEndpointSecretInput", gql_fields::WEB_CALLBACK_ENDPOINT)
+  }
+}
@@ -75,10 +75,43 @@ pub struct SignIssuance {
   #[arg(short, long, help="Do not output progress information to stdout")]
   #[serde(skip)]
   pub silent: bool,
+  #[arg(short, long, help="Sign all entries at once, with a single signature over the merkle root of their hashes. \
+    Much faster for large issuances, as entries are not downloaded one by one.")]
+  #[serde(skip)]
+  pub batch: bool,
 }
 
 impl SignIssuance {
   pub fn run<F: Fn(&Iter)>(self, client: &Client, before_each: F) -> ClientResult<i32> {
+    if self.batch {
+      return Self::sign_batch(client, self.id);
+    }
     Iter::new(client, self.id)?.sign_all(before_each)
   }
+
+  /* The root is rebuilt locally from the leaves, so we never sign a root that doesn't match the listed entries. */
+  fn sign_batch(client: &Client, id: i32) -> ClientResult<i32> {
+    use constata_lib::models::merkle::MerkleTree;
+
+    let batch: gql_types::IssuanceBatchSigningRoot = client.by_id(
+      &serde_json::json!({ "id": id }),
+      "IssuanceBatchSigningRoot",
+      "id root leaves { entryId hash }"
+    )?;
+
+    let rebuilt = MerkleTree::new(batch.leaves.iter().map(|l| l.hash.clone()).collect());
+    if rebuilt.root() != Some(&batch.root) {
+      return Err(error!(Unexpected("The batch root sent by the server does not match its entries.")));
+    }
+
+    let signature = client.sign(batch.root.as_bytes()).signature.to_base64();
+    let _: gql_types::Issuance = client.mutation(
+      &serde_json::json!({ "input": { "issuanceId": id, "root": batch.root, "signature": signature } }),
+      "signIssuanceBatch",
+      "SignIssuanceBatchInput",
+      gql_fields::ISSUANCE
+    )?;
+
+    Ok(batch.leaves.len() as i32)
+  }
 }
@@ -0,0 +1,15 @@
+use super::*;
+use gql_types::*;
+
+#[derive(serde::Serialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebCallbackEndpoint {
+  #[command(flatten)]
+  pub input: UpdateWebCallbackEndpointInput,
+}
+
+impl UpdateWebCallbackEndpoint {
+  pub fn run(self, client: &Client) -> ClientResult<WebCallbackEndpoint> {
+    client.mutation(&self, "updateWebCallbackEndpoint", "UpdateWebCallbackEndpointInput", gql_fields::WEB_CALLBACK_ENDPOINT)
+  }
+}
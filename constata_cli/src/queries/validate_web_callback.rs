@@ -1,5 +1,5 @@
 use super::*;
-use constata_lib::models::web_callback::WebCallbackContent;
+use constata_lib::models::{web_callback::WebCallbackContent, web_callback_endpoint::verify_signature_header};
 use bitcoin::network::constants::Network;
 
 #[derive(serde::Serialize, clap::Args)]
@@ -10,6 +10,12 @@ pub struct ValidateWebCallback {
   pub input_file: Option<PathBuf>,
   #[arg(short, long, default_value="production", help="The environment from where you got the callback.")]
   pub environment: Environment,
+  #[arg(long, requires="signature_header", help="Your endpoint's secret, to also check the Constata-Signature header.")]
+  pub secret: Option<String>,
+  #[arg(long, requires="secret", help="The value of the Constata-Signature header you got along with the web callback.")]
+  pub signature_header: Option<String>,
+  #[arg(long, default_value_t=300, help="Reject signature headers older than this many seconds, to prevent replays.")]
+  pub tolerance_seconds: i64,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
@@ -39,10 +45,18 @@ impl ValidateWebCallback {
     let good_signature = check!(signed.signed_ok(), Unexpected("Could not validate signature"));
     let signer = check!(signed.signer_as_p2wpkh(network), Unexpected("Could not obtain signer address"));
 
-    if good_signature && signer == expected {
-      Ok(serde_json::from_slice(&signed.payload)?)
-    } else {
-      Err(error!(InvalidInput("The web callback signature was wrong, expected signer to be {} but was {}", expected, signer)))
+    if !good_signature || signer != expected {
+      return Err(error!(InvalidInput("The web callback signature was wrong, expected signer to be {} but was {}", expected, signer)));
     }
+
+    if let (Some(secret), Some(header)) = (&self.secret, &self.signature_header) {
+      let now = chrono::Utc::now().timestamp();
+      check!(
+        verify_signature_header(header, &content, secret, self.tolerance_seconds, now),
+        InvalidInput("The Constata-Signature header was wrong or too old")
+      );
+    }
+
+    Ok(serde_json::from_slice(&signed.payload)?)
   }
 }
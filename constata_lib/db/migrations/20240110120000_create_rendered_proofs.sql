@@ -0,0 +1,17 @@
+CREATE TYPE rendered_proof_format AS ENUM (
+  'html',
+  'parts_zip'
+);
+
+CREATE TABLE rendered_proofs (
+  id SERIAL PRIMARY KEY NOT NULL,
+  story_id INTEGER NOT NULL REFERENCES stories(id),
+  story_snapshot_id INTEGER NOT NULL REFERENCES story_snapshots(id),
+  cache_key VARCHAR NOT NULL,
+  lang language NOT NULL,
+  format rendered_proof_format NOT NULL,
+  size_in_bytes INTEGER NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX rendered_proofs_cache_key ON rendered_proofs (cache_key);
+CREATE INDEX rendered_proofs_story_id ON rendered_proofs (story_id);
@@ -0,0 +1,20 @@
+CREATE TABLE document_part_commitments (
+  id SERIAL PRIMARY KEY NOT NULL,
+  document_id VARCHAR NOT NULL REFERENCES documents(id),
+  hash VARCHAR NOT NULL,
+  salts TEXT NOT NULL,
+  bulletin_id INTEGER NOT NULL DEFAULT current_draft(),
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX document_part_commitments_document_id ON document_part_commitments (document_id);
+CREATE INDEX document_part_commitments_bulletin_id ON document_part_commitments (bulletin_id);
+
+CREATE TABLE part_disclosures (
+  id SERIAL PRIMARY KEY NOT NULL,
+  org_id INTEGER NOT NULL REFERENCES orgs(id),
+  document_part_id VARCHAR NOT NULL REFERENCES document_parts(id),
+  token VARCHAR NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX part_disclosures_token ON part_disclosures (token);
+CREATE INDEX part_disclosures_org_id ON part_disclosures (org_id);
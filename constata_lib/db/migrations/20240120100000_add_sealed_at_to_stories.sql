@@ -0,0 +1,3 @@
+ALTER TABLE stories ADD COLUMN sealed_at TIMESTAMPTZ;
+CREATE INDEX stories_open_until_unsealed ON stories (open_until) WHERE sealed_at IS NULL;
+ALTER TYPE web_callback_kind ADD VALUE 'attestation_sealed';
@@ -0,0 +1,19 @@
+CREATE TABLE template_versions (
+  id SERIAL PRIMARY KEY NOT NULL,
+  template_id INTEGER NOT NULL REFERENCES templates(id),
+  version_number INTEGER NOT NULL,
+  schema TEXT NOT NULL,
+  custom_message VARCHAR,
+  og_title_override VARCHAR,
+  payload_hash VARCHAR,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX template_versions_template_id_version_number ON template_versions (template_id, version_number);
+
+/* Existing templates get a first version whose payload is still the one stored for the template itself. */
+INSERT INTO template_versions (template_id, version_number, schema, custom_message, og_title_override, payload_hash, created_at)
+  SELECT id, 1, schema, custom_message, og_title_override, NULL, created_at FROM templates;
+
+ALTER TABLE issuances ADD COLUMN template_version_id INTEGER REFERENCES template_versions(id);
+UPDATE issuances SET template_version_id = v.id FROM template_versions v WHERE v.template_id = issuances.template_id;
+CREATE INDEX issuances_template_version_id ON issuances (template_version_id);
@@ -0,0 +1,15 @@
+CREATE TABLE entry_revocations (
+  id SERIAL PRIMARY KEY NOT NULL,
+  org_id INTEGER NOT NULL REFERENCES orgs(id),
+  entry_id INTEGER NOT NULL REFERENCES entries(id),
+  person_id INTEGER NOT NULL REFERENCES persons(id),
+  reason TEXT NOT NULL,
+  hash VARCHAR NOT NULL,
+  bulletin_id INTEGER NOT NULL DEFAULT current_draft(),
+  revoked_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE UNIQUE INDEX entry_revocations_entry_id ON entry_revocations (entry_id);
+CREATE INDEX entry_revocations_org_id ON entry_revocations (org_id);
+CREATE INDEX entry_revocations_bulletin_id ON entry_revocations (bulletin_id);
+
+ALTER TYPE web_callback_kind ADD VALUE 'entry_revoked';
@@ -0,0 +1,4 @@
+ALTER TABLE entries
+  ADD COLUMN reissue_of_id INTEGER REFERENCES entries(id),
+  ADD COLUMN notify BOOLEAN NOT NULL DEFAULT TRUE;
+CREATE UNIQUE INDEX entries_reissue_of_id ON entries (reissue_of_id);
@@ -0,0 +1,3 @@
+ALTER TABLE document_part_signatures
+  ADD COLUMN merkle_root VARCHAR,
+  ADD COLUMN merkle_path TEXT;
@@ -0,0 +1,29 @@
+CREATE TYPE job_kind AS ENUM (
+  'render_issuance',
+  'complete_issuance',
+  'attempt_web_callback',
+  'send_email_callback'
+);
+
+CREATE TYPE job_state AS ENUM (
+  'queued',
+  'running',
+  'done',
+  'failed'
+);
+
+CREATE TABLE jobs (
+  id SERIAL PRIMARY KEY NOT NULL,
+  kind job_kind NOT NULL,
+  resource_id INTEGER NOT NULL,
+  state job_state NOT NULL DEFAULT 'queued',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  max_attempts INTEGER NOT NULL DEFAULT 5,
+  run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  locked_at TIMESTAMPTZ,
+  last_error TEXT,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  finished_at TIMESTAMPTZ
+);
+CREATE INDEX jobs_kind_state_run_at ON jobs (kind, state, run_at);
+CREATE UNIQUE INDEX jobs_open_per_resource ON jobs (kind, resource_id) WHERE state != 'done';
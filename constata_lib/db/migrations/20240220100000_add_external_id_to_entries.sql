@@ -0,0 +1,2 @@
+ALTER TABLE entries ADD COLUMN external_id VARCHAR;
+CREATE UNIQUE INDEX entries_issuance_id_external_id ON entries (issuance_id, external_id) WHERE external_id IS NOT NULL;
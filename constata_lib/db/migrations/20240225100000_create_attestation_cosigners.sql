@@ -0,0 +1,25 @@
+ALTER TABLE attestations ADD COLUMN cosigning_threshold INTEGER;
+ALTER TABLE attestations ADD COLUMN cosigning_deadline TIMESTAMPTZ;
+ALTER TABLE stories ADD COLUMN awaiting_cosigners BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TYPE cosigner_state AS ENUM (
+  'pending',
+  'signed',
+  'timed_out'
+);
+
+CREATE TABLE attestation_cosigners (
+  id SERIAL PRIMARY KEY NOT NULL,
+  attestation_id INTEGER NOT NULL REFERENCES attestations(id),
+  email VARCHAR,
+  pubkey_id VARCHAR,
+  state cosigner_state NOT NULL DEFAULT 'pending',
+  signed_by VARCHAR REFERENCES pubkeys(id),
+  signed_at TIMESTAMPTZ,
+  notifications_sent INTEGER NOT NULL DEFAULT 0,
+  last_notified_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  CHECK (email IS NOT NULL OR pubkey_id IS NOT NULL)
+);
+CREATE INDEX attestation_cosigners_attestation_id ON attestation_cosigners (attestation_id);
+CREATE INDEX attestation_cosigners_pending ON attestation_cosigners (last_notified_at) WHERE state = 'pending';
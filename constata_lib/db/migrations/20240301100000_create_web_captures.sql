@@ -0,0 +1,17 @@
+ALTER TYPE document_source ADD VALUE 'webcapture';
+
+CREATE TABLE web_captures (
+  id SERIAL PRIMARY KEY NOT NULL,
+  org_id INTEGER NOT NULL REFERENCES orgs(id),
+  person_id INTEGER NOT NULL REFERENCES persons(id),
+  attestation_id INTEGER NOT NULL REFERENCES attestations(id),
+  url TEXT NOT NULL,
+  state VARCHAR NOT NULL DEFAULT 'pending',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt TIMESTAMPTZ NOT NULL DEFAULT now(),
+  attempts_log TEXT NOT NULL DEFAULT '',
+  document_id VARCHAR,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX web_captures_pending ON web_captures (next_attempt) WHERE state = 'pending';
+CREATE INDEX web_captures_attestation_id ON web_captures (attestation_id);
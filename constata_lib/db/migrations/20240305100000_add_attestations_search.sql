@@ -0,0 +1,12 @@
+ALTER TABLE attestations ADD COLUMN indexed_text TEXT NOT NULL DEFAULT '';
+ALTER TABLE attestations ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+  setweight(to_tsvector('simple', markers), 'A') || setweight(to_tsvector('simple', indexed_text), 'B')
+) STORED;
+CREATE INDEX attestations_search_vector ON attestations USING GIN (search_vector);
+CREATE INDEX attestations_org_id_created_at ON attestations (org_id, created_at);
+
+UPDATE attestations SET indexed_text = coalesce((
+  SELECT string_agg(dp.friendly_name, ' ') FROM documents d
+  JOIN document_parts dp ON dp.document_id = d.id
+  WHERE d.story_id = attestations.story_id
+), '');
@@ -0,0 +1,11 @@
+ALTER TYPE web_callback_kind ADD VALUE 'issuance_created';
+ALTER TYPE web_callback_kind ADD VALUE 'issuance_completed';
+ALTER TYPE web_callback_kind ADD VALUE 'entry_signed';
+ALTER TYPE web_callback_kind ADD VALUE 'entry_completed';
+ALTER TYPE web_callback_kind ADD VALUE 'entry_email_sent';
+ALTER TYPE web_callback_kind ADD VALUE 'document_parked';
+ALTER TYPE web_callback_kind ADD VALUE 'document_accepted';
+ALTER TYPE web_callback_kind ADD VALUE 'invoice_paid';
+ALTER TYPE web_callback_kind ADD VALUE 'token_balance_low';
+
+ALTER TABLE web_callbacks ADD COLUMN document_id VARCHAR;
@@ -0,0 +1,41 @@
+CREATE TABLE web_callback_endpoints (
+  id SERIAL PRIMARY KEY NOT NULL,
+  org_id INTEGER NOT NULL REFERENCES orgs(id),
+  url VARCHAR NOT NULL,
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  all_kinds BOOLEAN NOT NULL DEFAULT FALSE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX web_callback_endpoints_org_id ON web_callback_endpoints (org_id);
+
+CREATE TABLE web_callback_subscriptions (
+  id SERIAL PRIMARY KEY NOT NULL,
+  web_callback_endpoint_id INTEGER NOT NULL REFERENCES web_callback_endpoints(id),
+  kind web_callback_kind NOT NULL,
+  UNIQUE (web_callback_endpoint_id, kind)
+);
+
+ALTER TYPE web_callback_result_code ADD VALUE 'endpoint_disabled';
+
+ALTER TABLE web_callbacks ADD COLUMN web_callback_endpoint_id INTEGER REFERENCES web_callback_endpoints(id);
+ALTER TABLE web_callback_attempts ADD COLUMN web_callback_endpoint_id INTEGER REFERENCES web_callback_endpoints(id);
+
+INSERT INTO web_callback_endpoints (org_id, url)
+  SELECT id, web_callbacks_url FROM orgs WHERE web_callbacks_url IS NOT NULL;
+
+-- Existing integrations keep getting the events they got until now, and opt in to the new ones.
+INSERT INTO web_callback_subscriptions (web_callback_endpoint_id, kind)
+  SELECT e.id, k.kind FROM web_callback_endpoints e
+  CROSS JOIN (VALUES
+    ('attestation_done'::web_callback_kind),
+    ('attestation_sealed'::web_callback_kind),
+    ('entry_revoked'::web_callback_kind)
+  ) AS k(kind);
+
+UPDATE web_callbacks SET web_callback_endpoint_id = e.id
+  FROM web_callback_endpoints e WHERE e.org_id = web_callbacks.org_id;
+
+UPDATE web_callback_attempts SET web_callback_endpoint_id = e.id
+  FROM web_callback_endpoints e WHERE e.org_id = web_callback_attempts.org_id AND e.url = web_callback_attempts.url;
+
+ALTER TABLE orgs DROP COLUMN web_callbacks_url;
@@ -0,0 +1,9 @@
+CREATE EXTENSION IF NOT EXISTS pgcrypto;
+
+ALTER TABLE web_callback_endpoints ADD COLUMN secret VARCHAR;
+ALTER TABLE web_callback_endpoints ADD COLUMN previous_secret VARCHAR;
+ALTER TABLE web_callback_endpoints ADD COLUMN previous_secret_expires_at TIMESTAMPTZ;
+
+-- Same format as WebCallbackEndpointHub::make_secret, 32 random bytes from a CSPRNG.
+UPDATE web_callback_endpoints SET secret = 'whsec_' || encode(gen_random_bytes(32), 'hex');
+ALTER TABLE web_callback_endpoints ALTER COLUMN secret SET NOT NULL;
@@ -0,0 +1,5 @@
+ALTER TABLE entries ADD COLUMN completed_at TIMESTAMPTZ;
+
+UPDATE entries SET completed_at = b.block_time
+  FROM documents d JOIN bulletins b ON b.id = d.bulletin_id
+  WHERE d.id = entries.document_id AND entries.state = 'completed';
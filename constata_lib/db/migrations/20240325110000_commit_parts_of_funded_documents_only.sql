@@ -0,0 +1,15 @@
+ALTER TABLE document_part_commitments DROP CONSTRAINT document_part_commitments_document_id_fkey;
+ALTER TABLE document_part_commitments
+    ADD FOREIGN KEY(document_id)
+    REFERENCES documents(id)
+    ON DELETE CASCADE;
+
+ALTER TABLE part_disclosures DROP CONSTRAINT part_disclosures_document_part_id_fkey;
+ALTER TABLE part_disclosures
+    ADD FOREIGN KEY(document_part_id)
+    REFERENCES document_parts(id)
+    ON DELETE CASCADE;
+
+DELETE FROM document_part_commitments c USING documents d WHERE d.id = c.document_id AND NOT d.funded;
+
+ALTER TABLE document_part_commitments ALTER COLUMN bulletin_id DROP DEFAULT;
@@ -0,0 +1,2 @@
+ALTER TABLE story_snapshots ADD COLUMN documents_count INTEGER;
+ALTER TABLE story_snapshots ADD COLUMN sealed BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,17 @@
+-- Documents lock their story row while being inserted, so a story can't be sealed
+-- between checking it's still open and adding the document to it.
+CREATE FUNCTION "reject_documents_in_sealed_stories"() RETURNS trigger
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    IF (SELECT sealed_at IS NOT NULL FROM stories WHERE id = NEW.story_id FOR SHARE) THEN
+        RAISE EXCEPTION 'story_is_sealed';
+    END IF;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER documents_reject_sealed_stories
+    BEFORE INSERT ON documents
+    FOR EACH ROW EXECUTE FUNCTION reject_documents_in_sealed_stories();
@@ -0,0 +1,4 @@
+ALTER TYPE job_kind ADD VALUE 'apply_batch_signature';
+
+ALTER TABLE issuances ADD COLUMN batch_signing TEXT;
+ALTER TABLE issuances ADD COLUMN batch_signature TEXT;
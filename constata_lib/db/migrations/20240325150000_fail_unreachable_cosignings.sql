@@ -0,0 +1,3 @@
+ALTER TYPE web_callback_kind ADD VALUE 'attestation_cosigning_failed';
+
+ALTER TABLE attestations ADD COLUMN cosigning_failed_at TIMESTAMPTZ;
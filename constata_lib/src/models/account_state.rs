@@ -116,7 +116,10 @@ impl AccountState {
 impl AccountState {
   pub async fn fund_all_documents(self) -> ConstataResult<Vec<String>> {
     let unfunded = self.state.db.fetch_all(sqlx::query!(
-      r#"SELECT id, cost::decimal as "cost!" FROM documents WHERE org_id = $1 AND NOT funded ORDER BY gift_id NULLS LAST, created_at"#,
+      r#"SELECT id, cost::decimal as "cost!" FROM documents
+        WHERE org_id = $1 AND NOT funded
+        AND story_id NOT IN (SELECT id FROM stories WHERE org_id = $1 AND awaiting_cosigners)
+        ORDER BY gift_id NULLS LAST, created_at"#,
       self.org_id
     )).await?;
 
@@ -152,6 +155,17 @@ impl AccountState {
       tokens = tokens - doc.cost;
     }
 
+    for id in &funded_ids {
+      let document = self.state.document().find(id).await?;
+      self.state.document_part_commitment().create_for(&document).await?;
+      self.state.web_callback().schedule_document(WebCallbackKind::DocumentAccepted, &document).await?;
+    }
+
+    let threshold = WebCallbackHub::LOW_TOKEN_BALANCE;
+    if self.token_balance >= threshold && tokens < threshold {
+      self.state.web_callback().schedule_token_balance_low(&org).await?;
+    }
+
     Ok(funded_ids)
   }
 }
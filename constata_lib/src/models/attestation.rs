@@ -1,5 +1,6 @@
 use crate::{
   ConstataResult,
+  Error,
   signed_payload::SignedPayload,
   models::{
     InsertEmailCallback,
@@ -10,9 +11,12 @@ use crate::{
     OrgDeletion,
     UtcDateTime,
     Site,
+    attestation_cosigner::*,
+    WebCallbackKind,
   },
 };
 use juniper::GraphQLObject;
+use chrono::Utc;
 
 model!{
   state: Site,
@@ -32,12 +36,24 @@ model!{
     created_at: UtcDateTime,
     #[sqlx_model_hints(int4, default)]
     deletion_id: Option<i32>,
+    #[sqlx_model_hints(int4, default)]
+    cosigning_threshold: Option<i32>,
+    #[sqlx_model_hints(timestamptz, default)]
+    cosigning_deadline: Option<UtcDateTime>,
+    #[sqlx_model_hints(timestamptz, default)]
+    cosigning_failed_at: Option<UtcDateTime>,
+  },
+  queries {
+    full_text_search("org_id = $1 AND deletion_id IS NULL AND search_vector @@ websearch_to_tsquery('simple', $2)", org_id: i32, text: String),
   },
   belongs_to {
     Org(org_id),
     Person(person_id),
     Story(story_id),
     OrgDeletion(deletion_id),
+  },
+  has_many {
+    AttestationCosigner(attestation_id),
   }
 }
 
@@ -50,13 +66,34 @@ impl AttestationHub {
     maybe_lang: Option<i18n::Lang>,
     email_admin_access_url_to: Vec<String>,
   ) -> ConstataResult<Attestation> {
+    self.create_cosigned(person, signed_payloads, open_until, markers, maybe_lang, email_admin_access_url_to, None).await
+  }
+
+  /* When there are co-signers, the story is held before any document is created, so none of them gets funded
+   * until enough co-signers have signed. See AttestationCosigner. */
+  pub async fn create_cosigned(&self,
+    person: &Person,
+    signed_payloads: &[SignedPayload],
+    open_until: Option<UtcDateTime>,
+    markers: Option<String>,
+    maybe_lang: Option<i18n::Lang>,
+    email_admin_access_url_to: Vec<String>,
+    cosigning: Option<Cosigning>,
+  ) -> ConstataResult<Attestation> {
+    let threshold_and_deadline = cosigning.as_ref().map(|c| c.validate()).transpose()?;
+
     let lang = maybe_lang.unwrap_or(person.attrs.lang);
-    let story = self.state.story().create(
+    let mut story = self.state.story().create(
       person.attrs.org_id,
       open_until,
       markers.clone().unwrap_or_else(|| String::new()),
       lang
     ).await?;
+
+    if cosigning.is_some() {
+      story = story.update().awaiting_cosigners(true).save().await?;
+    }
+
     let mut documents = vec![];
     for payload in signed_payloads {
       documents.push(self.state.document().create_from_signed_payload(&story, payload, None).await?);
@@ -75,20 +112,223 @@ impl AttestationHub {
       }
     }
     
-    Ok(self.insert(InsertAttestation{
+    let attestation = self.insert(InsertAttestation{
       person_id: person.attrs.id,
       org_id: person.attrs.org_id,
       story_id: story.attrs.id,
       markers: markers.unwrap_or_else(|| String::new()),
-    }).save().await?)
+    }).save().await?;
+
+    attestation.refresh_search_index().await?;
+
+    let (Some(cosigning), Some((threshold, deadline))) = (cosigning, threshold_and_deadline) else {
+      return Ok(attestation);
+    };
+
+    for invitee in &cosigning.invitees {
+      self.state.attestation_cosigner().create(attestation.attrs.id, invitee).await?;
+    }
+
+    Ok(attestation.update()
+      .cosigning_threshold(Some(threshold))
+      .cosigning_deadline(Some(deadline))
+      .save().await?)
   }
 }
 
 impl Attestation {
+  /* Documents can be added to the story until its open_until date. They are charged and stamped like the
+   * first ones, and the cached proof is invalidated so the next one covers every document in order. */
+  pub async fn append_documents(&self, signed_payloads: &[SignedPayload]) -> ConstataResult<Attestation> {
+    let story = self.story().await?;
+
+    if story.is_sealed() {
+      return Err(Error::validation("story", "story_is_sealed"));
+    }
+
+    if self.attrs.cosigning_threshold.is_some() {
+      return Err(Error::validation("cosigners", "cannot_append_to_cosigned_attestation"));
+    }
+
+    match story.attrs.open_until {
+      None => return Err(Error::validation("open_until", "attestation_cannot_be_appended")),
+      Some(date) if date < Utc::now() => return Err(Error::validation("open_until", "attestation_is_closed")),
+      _ => {}
+    }
+
+    for payload in signed_payloads {
+      if !payload.signed_ok()? {
+        return Err(Error::validation("signed_payload", "wrong_signature"));
+      }
+    }
+
+    for payload in signed_payloads {
+      self.state.document().create_from_signed_payload(&story, payload, None).await?;
+    }
+
+    self.refresh_search_index().await?;
+
+    Ok(self.reloaded().await?)
+  }
+
+  /* A co-signer signs every document in the attestation with the same pubkey. Once enough of them have signed,
+   * the documents are funded and stamped along with all their signatures. */
+  pub async fn cosign(&self, signed_payloads: &[SignedPayload]) -> ConstataResult<AttestationCosigner> {
+    let story = self.story().await?;
+    if !story.attrs.awaiting_cosigners {
+      return Err(Error::validation("attestation", "not_awaiting_cosigners"));
+    }
+
+    let Some(signer) = signed_payloads.first().map(|p| p.signer.to_string()) else {
+      return Err(Error::validation("documents", "must_sign_every_document"));
+    };
+
+    for payload in signed_payloads {
+      if !payload.signed_ok()? {
+        return Err(Error::validation("signed_payload", "wrong_signature"));
+      }
+      if payload.signer.to_string() != signer {
+        return Err(Error::validation("signed_payload/signer", "all_documents_must_have_the_same_signer"));
+      }
+    }
+
+    let pubkey = self.state.pubkey().find_optional(&signer).await?
+      .ok_or_else(|| Error::validation("signed_payload/signer", "signer_is_unknown"))?;
+
+    if self.attestation_cosigner_scope().signed_by_eq(&signer).optional().await?.is_some() {
+      return Err(Error::validation("signed_payload/signer", "already_cosigned"));
+    }
+
+    let mut cosigner = None;
+    for pending in self.attestation_cosigner_scope().state_eq(&CosignerState::Pending).all().await? {
+      if pending.is_invited(&pubkey).await? {
+        cosigner = Some(pending);
+        break;
+      }
+    }
+    let cosigner = cosigner
+      .ok_or_else(|| Error::validation("signed_payload/signer", "signer_is_not_a_pending_cosigner"))?;
+
+    let mut signed_parts = vec![];
+    for doc in story.documents().await? {
+      let part = doc.base_document_part().await?;
+      let payload = signed_payloads.iter().find(|p| p.payload_hash() == part.attrs.hash)
+        .ok_or_else(|| Error::validation("documents", "must_sign_every_document"))?;
+      signed_parts.push((part, payload));
+    }
+
+    for (part, payload) in signed_parts {
+      part.add_signature(payload).await?;
+    }
+
+    let cosigner = cosigner.mark_signed(&pubkey).await?;
+    self.release_if_cosigned().await?;
+    Ok(cosigner)
+  }
+
+  async fn release_if_cosigned(&self) -> ConstataResult<()> {
+    let signed = self.attestation_cosigner_scope().state_eq(&CosignerState::Signed).count().await?;
+    if signed < self.attrs.cosigning_threshold.unwrap_or(0) as i64 {
+      return Ok(());
+    }
+
+    let story = self.story().await?.update().awaiting_cosigners(false).save().await?;
+    self.state.rendered_proof().invalidate_story(story.attrs.id).await?;
+    self.org().await?.account_state().await?.fund_all_documents().await?;
+
+    for doc in story.documents().await? {
+      if doc.is_parked() {
+        self.state.web_callback().schedule_document(WebCallbackKind::DocumentParked, &doc).await?;
+      }
+    }
+    Ok(())
+  }
+
+  /* Once co-signers time out, the ones still pending may not be enough to reach the threshold. The documents are
+   * never stamped then, so the attestation is marked as failed and the author gets a web callback. */
+  pub async fn fail_cosigning_if_unreachable(self) -> ConstataResult<Attestation> {
+    if self.attrs.cosigning_failed_at.is_some() || !self.story().await?.attrs.awaiting_cosigners {
+      return Ok(self);
+    }
+
+    let signed = self.attestation_cosigner_scope().state_eq(&CosignerState::Signed).count().await?;
+    let pending = self.attestation_cosigner_scope().state_eq(&CosignerState::Pending).count().await?;
+    let reachable = signed + pending;
+    if reachable >= self.attrs.cosigning_threshold.unwrap_or(0) as i64 {
+      return Ok(self);
+    }
+
+    let failed = self.update().cosigning_failed_at(Some(Utc::now())).save().await?;
+    failed.state.web_callback().schedule_attestation_cosigning_failed(&failed).await?;
+    Ok(failed)
+  }
+
   pub async fn on_done(&self) -> ConstataResult<()> {
     self.state.web_callback().schedule_attestation_done(self).await?;
     Ok(())
   }
+
+  /* Markers are indexed straight from their column. The file names and any text we can read from the
+   * documents are gathered here, and have to be refreshed whenever documents are added. */
+  pub async fn refresh_search_index(&self) -> ConstataResult<()> {
+    let mut indexed = vec![];
+    for doc in self.story().await?.documents().await? {
+      for part in doc.document_part_vec().await? {
+        indexed.push(part.attrs.friendly_name.clone());
+        if let Some(text) = searchable_text(&part.attrs.content_type, &part.contents().await?) {
+          indexed.push(text);
+        }
+      }
+    }
+
+    let mut indexed_text = indexed.join(" ");
+    if let Some((cut, _)) = indexed_text.char_indices().nth(AttestationHub::MAX_INDEXED_CHARS) {
+      indexed_text.truncate(cut);
+    }
+
+    self.state.db.execute(sqlx::query!(
+      "UPDATE attestations SET indexed_text = $2 WHERE id = $1",
+      self.attrs.id,
+      indexed_text
+    )).await?;
+
+    Ok(())
+  }
+}
+
+impl AttestationHub {
+  /* Postgres won't build a tsvector larger than 1MB, so very long documents are only partially indexed. */
+  pub const MAX_INDEXED_CHARS: usize = 200_000;
+
+  /* Uses websearch syntax, so users can write "quoted phrases", OR, and -excluded words. */
+  pub async fn search_ids(&self, org_id: i32, text: &str) -> sqlx::Result<Vec<i32>> {
+    Ok(self.full_text_search(org_id, text.to_string()).all().await?.into_iter().map(|a| a.attrs.id).collect())
+  }
+}
+
+/* Only plain text, markup and data formats are indexed. HTML and web captures have their tags removed. */
+fn searchable_text(content_type: &str, bytes: &[u8]) -> Option<String> {
+  lazy_static::lazy_static! {
+    static ref SCRIPTS: regex::Regex = regex::Regex::new(r"(?is)<(script|style)[^>]*>.*?</(script|style)>").unwrap();
+    static ref TAGS: regex::Regex = regex::Regex::new(r"(?s)<[^>]*>").unwrap();
+    static ref SPACES: regex::Regex = regex::Regex::new(r"\s+").unwrap();
+  }
+
+  let mime = content_type.split(';').next().unwrap_or("").trim();
+  let is_text = mime.starts_with("text/") ||
+    ["application/json", "application/xml", "application/xhtml+xml", "application/warc"].contains(&mime);
+  if !is_text {
+    return None;
+  }
+
+  let text = String::from_utf8_lossy(bytes);
+  let text = if mime.contains("html") || mime.contains("xml") || mime == "application/warc" {
+    TAGS.replace_all(&SCRIPTS.replace_all(&text, " "), " ").to_string()
+  } else {
+    text.to_string()
+  };
+
+  Some(SPACES.replace_all(&text, " ").trim().to_string())
 }
 
 pub mod for_api {
@@ -122,7 +362,35 @@ pub mod for_api {
     pub admin_access_url: Option<String>,
     pub public_certificate_url: Option<String>,
     pub created_at: UtcDateTime,
-    pub documents: Vec<AttestationDocument>
+    pub documents: Vec<AttestationDocument>,
+    pub cosigning_threshold: Option<i32>,
+    pub cosigning_deadline: Option<UtcDateTime>,
+    pub cosigners: Vec<Cosigner>,
+  }
+
+  #[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+  #[serde(rename_all = "camelCase")]
+  #[graphql(description = "Someone invited to co-sign an attestation, by email or by pubkey. State is 'PENDING', 'SIGNED' or 'TIMED_OUT'.")]
+  pub struct Cosigner {
+    attestation_id: i32,
+    email: Option<String>,
+    pubkey_id: Option<String>,
+    state: CosignerState,
+    signed_by: Option<String>,
+    signed_at: Option<UtcDateTime>,
+  }
+
+  impl Cosigner {
+    pub fn from_model(c: AttestationCosigner) -> Cosigner {
+      Cosigner{
+        attestation_id: c.attrs.attestation_id,
+        email: c.attrs.email,
+        pubkey_id: c.attrs.pubkey_id,
+        state: c.attrs.state,
+        signed_by: c.attrs.signed_by,
+        signed_at: c.attrs.signed_at,
+      }
+    }
   }
 
   #[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
@@ -215,7 +483,9 @@ pub mod for_api {
       });
     }
 
-    let state = if done_documents > 0 {
+    let state = if story.attrs.awaiting_cosigners {
+      if d.attrs.cosigning_failed_at.is_some() { "cosigning_failed" } else { "awaiting_cosigners" }
+    } else if done_documents > 0 {
       if parked_documents == 0 && processing_documents == 0 {
         "done"
       } else if parked_documents > 0 {
@@ -253,6 +523,11 @@ pub mod for_api {
 
     let last_doc_date = story.documents().await?.last().map(|d| d.attrs.created_at.clone());
 
+    let cosigners = d.attestation_cosigner_scope().order_by(AttestationCosignerOrderBy::Id).all().await?
+      .into_iter()
+      .map(Cosigner::from_model)
+      .collect();
+
     Ok(Attestation {
       id: d.attrs.id,
       person_id: d.attrs.person_id,
@@ -275,7 +550,10 @@ pub mod for_api {
       tokens_paid: tokens_paid.to_f64().unwrap_or(0.0),
       tokens_owed: tokens_owed.to_f64().unwrap_or(0.0),
       last_doc_date,
-      documents
+      documents,
+      cosigning_threshold: d.attrs.cosigning_threshold,
+      cosigning_deadline: d.attrs.cosigning_deadline,
+      cosigners,
     })
   }
 }
@@ -290,4 +568,52 @@ describe! {
     let att = site.attestation().create(&alice.person().await, &payloads, None, None, None, vec![]).await?;
     assert_eq!(att.org_id(), alice.org().await.id());
   }
+
+  dbtest!{ appends_documents_while_the_story_is_open (site, c)
+    let alice = c.alice().await;
+    let person = alice.person().await;
+    let open_until = Some(Utc::now() + chrono::Duration::days(1));
+    let att = site.attestation().create(&person, &[alice.signed_payload(b"hello world")], open_until, None, None, vec![]).await?;
+    let first_snapshots = att.story().await?.story_snapshots().await?.len();
+
+    att.append_documents(&[alice.signed_payload(b"goodbye world")]).await?;
+    let story = att.story().await?;
+    assert_eq!(story.documents().await?.len(), 2);
+    assert_eq!(story.story_snapshots().await?.len(), first_snapshots + 1);
+
+    story.update().open_until(Some(Utc::now() - chrono::Duration::days(1))).save().await?;
+    assert_that!(
+      &att.append_documents(&[alice.signed_payload(b"too late")]).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("attestation_is_closed".to_string()) } }
+    );
+
+    let closed = site.attestation().create(&person, &[alice.signed_payload(b"hello")], None, None, None, vec![]).await?;
+    assert_that!(
+      &closed.append_documents(&[alice.signed_payload(b"bye")]).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("attestation_cannot_be_appended".to_string()) } }
+    );
+  }
+
+  dbtest!{ searches_attestations_by_markers_and_document_text (site, c)
+    let alice = c.alice().await;
+    let bob = c.bob().await;
+    let person = alice.person().await;
+    let org_id = person.attrs.org_id;
+    let open_until = Some(Utc::now() + chrono::Duration::days(1));
+
+    let contract = site.attestation()
+      .create(&person, &[alice.signed_payload(b"lease agreement for the apartment")], open_until, Some("tenants 2024".to_string()), None, vec![]).await?;
+    let invoice = site.attestation()
+      .create(&person, &[alice.signed_payload(b"<html><body><p>Invoice for consulting</p></body></html>")], None, None, None, vec![]).await?;
+    site.attestation()
+      .create(&bob.person().await, &[bob.signed_payload(b"another lease agreement")], None, None, None, vec![]).await?;
+
+    assert_eq!(site.attestation().search_ids(org_id, "tenants").await?, vec![contract.attrs.id]);
+    assert_eq!(site.attestation().search_ids(org_id, "lease").await?, vec![contract.attrs.id]);
+    assert_eq!(site.attestation().search_ids(org_id, "consulting -lease").await?, vec![invoice.attrs.id]);
+    assert!(site.attestation().search_ids(org_id, "body").await?.is_empty());
+
+    contract.append_documents(&[alice.signed_payload(b"inventory of furniture")]).await?;
+    assert_eq!(site.attestation().search_ids(org_id, "\"inventory of furniture\"").await?, vec![contract.attrs.id]);
+  }
 }
@@ -0,0 +1,300 @@
+/*
+ * People invited to co-sign an attestation, by email or by pubkey. While fewer than the attestation's
+ * cosigning_threshold have signed, its story is awaiting_cosigners and its documents are not funded, so
+ * they don't get stamped. Each co-signer signs every document with their own pubkey, adding a
+ * DocumentPartSignature that ends up in the proof next to the author's.
+ * Pending co-signers get an invitation and a few reminders by email, and time out at the cosigning_deadline.
+ * If the rest can't reach the threshold then, the cosigning fails and the author gets a web callback.
+ */
+use super::*;
+use juniper::GraphQLEnum;
+
+model!{
+  state: Site,
+  table: attestation_cosigners,
+  struct AttestationCosigner {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    attestation_id: i32,
+    #[sqlx_model_hints(varchar)]
+    email: Option<String>,
+    #[sqlx_model_hints(varchar)]
+    pubkey_id: Option<String>,
+    #[sqlx_model_hints(cosigner_state, default)]
+    state: CosignerState,
+    #[sqlx_model_hints(varchar, default)]
+    signed_by: Option<String>,
+    #[sqlx_model_hints(timestamptz, default)]
+    signed_at: Option<UtcDateTime>,
+    #[sqlx_model_hints(int4, default)]
+    notifications_sent: i32,
+    #[sqlx_model_hints(timestamptz, default)]
+    last_notified_at: Option<UtcDateTime>,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+  },
+  queries {
+    due_for_notification("state = 'pending' AND notifications_sent < $1
+      AND (last_notified_at IS NULL OR last_notified_at < now() - interval '3 days')", max_notifications: i32),
+    past_deadline("state = 'pending' AND attestation_id IN (
+      SELECT id FROM attestations WHERE cosigning_deadline < now()
+    )"),
+  },
+  belongs_to {
+    Attestation(attestation_id),
+  }
+}
+
+#[derive(sqlx::Type, Copy, Clone, Debug, Deserialize, PartialEq, Serialize, GraphQLEnum)]
+#[sqlx(type_name = "cosigner_state", rename_all = "snake_case")]
+#[serde(rename_all= "SCREAMING_SNAKE_CASE")]
+pub enum CosignerState {
+  Pending,
+  Signed,
+  TimedOut,
+}
+
+impl sqlx::postgres::PgHasArrayType for CosignerState {
+  fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+    sqlx::postgres::PgTypeInfo::with_name("_cosigner_state")
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum CosignerInvitee {
+  Email(String),
+  Pubkey(String),
+}
+
+/* Who should co-sign an attestation, and how many of them are enough. The threshold defaults to all of them,
+ * and the deadline to two weeks from now. */
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Cosigning {
+  pub invitees: Vec<CosignerInvitee>,
+  pub threshold: Option<i32>,
+  pub deadline: Option<UtcDateTime>,
+}
+
+impl Cosigning {
+  pub fn validate(&self) -> ConstataResult<(i32, UtcDateTime)> {
+    if self.invitees.is_empty() {
+      return Err(Error::validation("cosigners", "must_invite_at_least_one_cosigner"));
+    }
+
+    let threshold = self.threshold.unwrap_or(self.invitees.len() as i32);
+    if threshold < 1 || threshold as usize > self.invitees.len() {
+      return Err(Error::validation("cosigning_threshold", "must_be_between_one_and_the_number_of_cosigners"));
+    }
+
+    let deadline = self.deadline.unwrap_or_else(|| Utc::now() + Duration::days(14));
+    if deadline < Utc::now() {
+      return Err(Error::validation("cosigning_deadline", "must_be_in_the_future"));
+    }
+
+    Ok((threshold, deadline))
+  }
+}
+
+impl AttestationCosignerHub {
+  pub const MAX_NOTIFICATIONS: i32 = 4;
+
+  pub async fn create(&self, attestation_id: i32, invitee: &CosignerInvitee) -> sqlx::Result<AttestationCosigner> {
+    let (email, pubkey_id) = match invitee {
+      CosignerInvitee::Email(address) => (Some(address.trim().to_lowercase()), None),
+      CosignerInvitee::Pubkey(id) => (None, Some(id.clone())),
+    };
+    self.insert(InsertAttestationCosigner{ attestation_id, email, pubkey_id }).save().await
+  }
+
+  pub async fn not_notified(&self) -> sqlx::Result<Vec<AttestationCosigner>> {
+    self.due_for_notification(Self::MAX_NOTIFICATIONS).all().await
+  }
+
+  /* Co-signers that didn't sign in time are left out. If the remaining ones can't reach the threshold
+   * the attestation's cosigning fails, and its documents are eventually deleted like any other parked document. */
+  pub async fn time_out_all_past_deadline(&self) -> ConstataResult<()> {
+    let mut attestation_ids = std::collections::HashSet::new();
+    for cosigner in self.past_deadline().all().await? {
+      attestation_ids.insert(cosigner.attrs.attestation_id);
+      cosigner.update().state(CosignerState::TimedOut).save().await?;
+    }
+
+    for id in attestation_ids {
+      self.state.attestation().find(&id).await?.fail_cosigning_if_unreachable().await?;
+    }
+    Ok(())
+  }
+}
+
+impl AttestationCosigner {
+  pub fn is_pending(&self) -> bool {
+    self.attrs.state == CosignerState::Pending
+  }
+
+  /* An invitation by pubkey is for that exact pubkey, an invitation by email is for anyone who verified that address. */
+  pub async fn is_invited(&self, pubkey: &Pubkey) -> ConstataResult<bool> {
+    if let Some(id) = self.pubkey_id() {
+      return Ok(id == pubkey.id());
+    }
+
+    let Some(address) = self.email() else { return Ok(false) };
+    Ok(pubkey.person().await?.email_address_scope()
+      .address_eq(address)
+      .verified_at_is_set(true)
+      .optional().await?
+      .is_some())
+  }
+
+  pub async fn mark_signed(self, pubkey: &Pubkey) -> sqlx::Result<AttestationCosigner> {
+    self.update()
+      .state(CosignerState::Signed)
+      .signed_by(Some(pubkey.attrs.id.clone()))
+      .signed_at(Some(Utc::now()))
+      .save().await
+  }
+
+  /* Co-signers invited by pubkey are emailed at their person's last known address, if any. */
+  pub async fn address(&self) -> ConstataResult<Option<String>> {
+    if let Some(email) = self.email() {
+      return Ok(Some(email.clone()));
+    }
+
+    let Some(id) = self.pubkey_id() else { return Ok(None) };
+    let Some(pubkey) = self.state.pubkey().find_optional(id).await? else { return Ok(None) };
+    Ok(pubkey.person().await?.email_address().await?.map(|e| e.attrs.address))
+  }
+
+  pub fn is_reminder(&self) -> bool {
+    self.attrs.notifications_sent > 0
+  }
+
+  pub async fn render_mailer_html(&self) -> ConstataResult<String> {
+    EmailCosignerInvitation::new(self).await?.render_html()
+  }
+
+  pub async fn mark_notified(self) -> sqlx::Result<AttestationCosigner> {
+    let sent = self.attrs.notifications_sent + 1;
+    self.update().notifications_sent(sent).last_notified_at(Some(Utc::now())).save().await
+  }
+}
+
+describe! {
+  dbtest!{ holds_documents_until_enough_cosigners_sign (site, c)
+    let alice = c.alice().await;
+    let bob = c.bob().await;
+    let robert = c.robert().await;
+    robert.verify_email("robert@example.com").await;
+
+    let payloads = vec![alice.signed_payload(b"hello world"), alice.signed_payload(b"goodbye world")];
+    let cosigning = Cosigning{
+      invitees: vec![
+        CosignerInvitee::Pubkey(bob.public_key().to_string()),
+        CosignerInvitee::Email("Robert@example.com".to_string()),
+        CosignerInvitee::Email("nobody@example.com".to_string()),
+      ],
+      threshold: Some(2),
+      deadline: None,
+    };
+    let att = site.attestation().create_cosigned(&alice.person().await, &payloads, None, None, None, vec![], Some(cosigning)).await?;
+
+    let is_held = |att: Attestation| async move {
+      let story = att.story().await.unwrap();
+      let docs = story.documents().await.unwrap();
+      story.attrs.awaiting_cosigners && docs.iter().all(|d| d.is_parked())
+    };
+    assert!(is_held(att.clone()).await);
+    assert_eq!(site.attestation_cosigner().not_notified().await?.len(), 3);
+
+    assert_that!(
+      &att.cosign(&[bob.signed_payload(b"hello world")]).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("must_sign_every_document".to_string()) } }
+    );
+    assert_that!(
+      &att.cosign(&[alice.signed_payload(b"hello world"), alice.signed_payload(b"goodbye world")]).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("signer_is_not_a_pending_cosigner".to_string()) } }
+    );
+
+    att.cosign(&[bob.signed_payload(b"goodbye world"), bob.signed_payload(b"hello world")]).await?;
+    assert!(is_held(att.clone()).await);
+
+    att.cosign(&[robert.signed_payload(b"hello world"), robert.signed_payload(b"goodbye world")]).await?;
+    let story = att.story().await?;
+    assert!(!story.attrs.awaiting_cosigners);
+
+    for doc in story.documents().await? {
+      assert!(doc.is_accepted());
+      let signatures = doc.base_document_part().await?.document_part_signature_vec().await?;
+      assert_eq!(signatures.len(), 3);
+      assert!(signatures.iter().all(|s| s.attrs.bulletin_id.is_some()));
+    }
+
+    let states: Vec<CosignerState> = att.attestation_cosigner_scope().order_by(AttestationCosignerOrderBy::Id).all().await?.into_iter().map(|c| c.attrs.state).collect();
+    assert_eq!(states, vec![CosignerState::Signed, CosignerState::Signed, CosignerState::Pending]);
+  }
+
+  dbtest!{ reminds_and_times_out_pending_cosigners (site, c)
+    let alice = c.alice().await;
+    let bob = c.bob().await;
+    let cosigning = Cosigning{
+      invitees: vec![CosignerInvitee::Pubkey(bob.public_key().to_string())],
+      threshold: None,
+      deadline: Some(Utc::now() + Duration::days(1)),
+    };
+    let att = site.attestation()
+      .create_cosigned(&alice.person().await, &[alice.signed_payload(b"hello")], None, None, None, vec![], Some(cosigning)).await?;
+
+    let cosigner = site.attestation_cosigner().not_notified().await?.pop().unwrap();
+    assert!(!cosigner.is_reminder());
+    cosigner.mark_notified().await?;
+    assert!(site.attestation_cosigner().not_notified().await?.is_empty());
+
+    let cosigner = att.attestation_cosigner_scope().one().await?;
+    cosigner.update().last_notified_at(Some(Utc::now() - Duration::days(4))).save().await?;
+    assert!(site.attestation_cosigner().not_notified().await?.pop().unwrap().is_reminder());
+
+    att.clone().update().cosigning_deadline(Some(Utc::now() - Duration::minutes(1))).save().await?;
+    site.attestation_cosigner().time_out_all_past_deadline().await?;
+    assert_eq!(att.attestation_cosigner_scope().one().await?.attrs.state, CosignerState::TimedOut);
+    assert!(site.attestation_cosigner().not_notified().await?.is_empty());
+
+    let failed = att.reloaded().await?;
+    assert!(failed.attrs.cosigning_failed_at.is_some());
+    assert_eq!(super::attestation::for_api::from_model(failed).await?.state, "cosigning_failed");
+
+    assert_that!(
+      &att.cosign(&[bob.signed_payload(b"hello")]).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("signer_is_not_a_pending_cosigner".to_string()) } }
+    );
+  }
+
+  dbtest!{ keeps_documents_awaiting_cosigners_until_cosigning_fails (site, c)
+    use chrono::TimeZone;
+    let alice = c.alice().await;
+    alice.make_email("alice@example.com").await;
+    let bob = c.bob().await;
+    site.web_callback_endpoint()
+      .create(&alice.org().await, "http://127.0.0.1:1234/callbacks_url", &[WebCallbackKind::DocumentParked], false, true).await?;
+
+    let cosigning = Cosigning{
+      invitees: vec![CosignerInvitee::Pubkey(bob.public_key().to_string())],
+      threshold: None,
+      deadline: Some(Utc::now() + Duration::days(1)),
+    };
+    let att = site.attestation()
+      .create_cosigned(&alice.person().await, &[alice.signed_payload(b"hello")], None, None, None, vec![], Some(cosigning)).await?;
+    let doc = att.story().await?.documents().await?.pop().unwrap();
+    let doc = doc.update().created_at(Utc::now() - Duration::days(60)).save().await?;
+    assert_eq!(site.web_callback().select().kind_eq(&WebCallbackKind::DocumentParked).count().await?, 0);
+
+    site.document().delete_old_parked().await?;
+    assert!(doc.reloaded().await.is_ok());
+    site.parked_reminder().create_new_reminders(Utc.with_ymd_and_hms(2024, 3, 18, 13, 0, 0).unwrap()).await?;
+    assert!(site.parked_reminder().not_sent().await?.is_empty());
+
+    att.clone().update().cosigning_deadline(Some(Utc::now() - Duration::minutes(1))).save().await?;
+    site.attestation_cosigner().time_out_all_past_deadline().await?;
+    site.document().delete_old_parked().await?;
+    assert!(doc.reloaded().await.is_err());
+  }
+}
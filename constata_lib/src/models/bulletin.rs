@@ -103,6 +103,10 @@ impl Bulletin {
        UNION
        (SELECT DISTINCT hash as "hash!" FROM story_snapshots WHERE bulletin_id = $1)
        UNION
+       (SELECT DISTINCT hash as "hash!" FROM document_part_commitments WHERE bulletin_id = $1)
+       UNION
+       (SELECT DISTINCT hash as "hash!" FROM entry_revocations WHERE bulletin_id = $1)
+       UNION
        (SELECT DISTINCT evidence_hash as "hash!" FROM pubkey_domain_endorsements WHERE bulletin_id = $1)
        UNION
        (SELECT DISTINCT evidence_hash as "hash!" FROM email_addresses WHERE bulletin_id = $1)
@@ -344,6 +348,8 @@ impl Submitted {
       .block_time(Some(block_time))
       .save().await?;
 
+    updated.state.rendered_proof().invalidate_bulletin(updated.attrs.id).await?;
+
     for doc in updated.document_vec().await? {
       if let Some(att) = doc.story().await?.attestation().await? {
         att.on_done().await?;
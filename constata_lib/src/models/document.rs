@@ -37,7 +37,11 @@ model!{
     sourced_from: DocumentSource,
   },
   queries {
-    all_old_parked("now() - created_at > $1 AND NOT funded AND bulletin_id IS NULL", delete_interval: Duration),
+    all_old_parked("now() - created_at > $1 AND NOT funded AND bulletin_id IS NULL
+      AND story_id NOT IN (
+        SELECT s.id FROM stories s INNER JOIN attestations a ON a.story_id = s.id
+        WHERE s.awaiting_cosigners AND a.cosigning_failed_at IS NULL
+      )", delete_interval: Duration),
   },
   belongs_to {
     Org(org_id),
@@ -51,6 +55,7 @@ model!{
     EmailCallback(document_id),
     DownloadProofLink(document_id),
     Entry(document_id),
+    DocumentPartCommitment(document_id),
   }
 }
 
@@ -405,9 +410,33 @@ impl DocumentHub {
     Ok(doc)
   }
 
+  pub async fn create_from_batch_signed_payload(&self, story: &Story, payload: &[u8], signed_root: &SignedPayload, path: &MerklePath)
+   -> ConstataResult<Document> {
+    if !signed_root.signed_ok()? {
+      return Err(Error::validation("signed_payload", "wrong_signature"));
+    }
+
+    let person_id = self.state.pubkey().find(&signed_root.signer.to_string())
+      .await
+      .map_err(|_| Error::validation("signed_payload/signer", "signer_is_unknown"))?
+      .attrs
+      .person_id;
+
+    let doc = self.state.document().create_and_index(&story, payload, None, person_id, None, DocumentSource::Api, false).await?;
+    doc.base_document_part().await?.add_batch_signature(signed_root, path).await?;
+
+    Ok(doc)
+  }
+
   pub async fn create_and_index
   (&self, story: &Story, payload: &[u8], filename: Option<&str>, person_id: PersonId, mime_override: MimeOverride, sourced_from: DocumentSource, always_gift: bool)
    -> ConstataResult<Document> {
+    /* The documents_reject_sealed_stories trigger locks the story row on insert, this is just for a friendlier error. */
+    let current_story = self.state.story().find(story.id()).await?;
+    if current_story.is_sealed() {
+      return Err(Error::validation("story", "story_is_sealed"));
+    }
+
     let org = story.org().await?;
     use std::time::{SystemTime, UNIX_EPOCH};
     let time = SystemTime::now().duration_since(UNIX_EPOCH).expect("Time went backwards").as_nanos();
@@ -444,7 +473,12 @@ impl DocumentHub {
 
     org.account_state().await?.fund_all_documents().await?;
 
-    Ok(doc.reloaded().await?)
+    let doc = doc.reloaded().await?;
+    /* Documents held for co-signers are not waiting for tokens, so there's nothing to tell about them yet. */
+    if doc.is_parked() && !current_story.attrs.awaiting_cosigners {
+      self.state.web_callback().schedule_document(WebCallbackKind::DocumentParked, &doc).await?;
+    }
+    Ok(doc)
   }
 
   pub async fn delete_old_parked(&self) -> ConstataResult<()> {
@@ -5,6 +5,7 @@ use crate::{
     hasher::hexdigest,
     Document,
     document_part_signature::*,
+    merkle::{MerkleTree, MerklePath},
     storable::*,
   },
   signed_payload::SignedPayload,
@@ -73,7 +74,22 @@ impl DocumentPartHub {
 
 impl DocumentPart {
   pub async fn add_signature(&self, signed_payload: &SignedPayload) -> sqlx::Result<DocumentPartSignature> {
+    self.insert_signature(signed_payload, None).await
+  }
+
+  /* The signed payload is a merkle root, the path proves this part's hash is one of its leaves. */
+  pub async fn add_batch_signature(&self, signed_root: &SignedPayload, path: &MerklePath) -> ConstataResult<DocumentPartSignature> {
+    let root = String::from_utf8(signed_root.payload.clone())?;
+    if MerkleTree::root_from_path(&self.attrs.hash, path) != root {
+      return Err(Error::validation("merkle_path", "does_not_lead_to_signed_root"));
+    }
+
+    Ok(self.insert_signature(signed_root, Some((root, serde_json::to_string(path)?))).await?)
+  }
+
+  async fn insert_signature(&self, signed_payload: &SignedPayload, merkle: Option<(String, String)>) -> sqlx::Result<DocumentPartSignature> {
     let serialized_signature = signed_payload.signature.serialize();
+    let (merkle_root, merkle_path) = merkle.unzip();
 
     self.state.document_part_signature().insert(
       InsertDocumentPartSignature{
@@ -81,6 +97,8 @@ impl DocumentPart {
         pubkey_id: signed_payload.signer.to_string(),
         signature: serialized_signature.to_vec(),
         signature_hash: hexdigest(&serialized_signature),
+        merkle_root,
+        merkle_path,
       }
     ).save_and_trigger_updates().await
   }
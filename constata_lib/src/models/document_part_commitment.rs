@@ -0,0 +1,124 @@
+/*
+ * A DocumentPartCommitment is a merkle root over salted hashes of all parts of a document.
+ * It gets stamped like any other hash, so a single part can later be proven to belong to its document
+ * by revealing just that part, its salt and its merkle path, while the siblings reveal nothing.
+ * Commitments are made when a document is funded, in the same bulletin as the document.
+ */
+use super::*;
+use super::merkle::{MerkleTree, MerklePath};
+
+model!{
+  state: Site,
+  table: document_part_commitments,
+  struct DocumentPartCommitment {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(varchar)]
+    document_id: String,
+    #[sqlx_model_hints(varchar)]
+    hash: String,
+    #[sqlx_model_hints(text)]
+    salts: String,
+    #[sqlx_model_hints(int4)]
+    bulletin_id: i32,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+  },
+  belongs_to {
+    Document(document_id),
+    Bulletin(bulletin_id),
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PartSalt {
+  pub part_id: String,
+  pub salt: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct PartInclusion {
+  pub commitment: String,
+  pub bulletin_id: i32,
+  pub salt: String,
+  pub leaf: String,
+  pub path: MerklePath,
+}
+
+impl DocumentPartCommitmentHub {
+  pub async fn create_for(&self, document: &Document) -> ConstataResult<DocumentPartCommitment> {
+    let bulletin_id = document.attrs.bulletin_id
+      .ok_or_else(|| Error::validation("document", "only_funded_documents_get_part_commitments"))?;
+    let parts = document.document_part_scope().order_by(DocumentPartOrderBy::Id).all().await?;
+
+    let salts: Vec<PartSalt> = parts.iter()
+      .map(|p| PartSalt{ part_id: p.attrs.id.clone(), salt: hex::encode(rand::random::<[u8; 16]>()) })
+      .collect();
+
+    let leaves = parts.iter().zip(&salts).map(|(p, s)| DocumentPartCommitment::leaf(&s.salt, &p.attrs.hash) ).collect();
+    let hash = MerkleTree::new(leaves).root().cloned()
+      .ok_or_else(|| Error::validation("document", "has_no_parts_to_commit"))?;
+
+    Ok(self.insert(InsertDocumentPartCommitment{
+      document_id: document.attrs.id.clone(),
+      hash,
+      salts: serde_json::to_string(&salts)?,
+      bulletin_id,
+    }).save().await?)
+  }
+}
+
+impl DocumentPartCommitment {
+  pub fn leaf(salt: &str, part_hash: &str) -> String {
+    hasher::hexdigest(format!("{salt}:{part_hash}").as_bytes())
+  }
+
+  pub fn parsed_salts(&self) -> ConstataResult<Vec<PartSalt>> {
+    Ok(serde_json::from_str(self.salts())?)
+  }
+
+  pub async fn inclusion(&self, part: &DocumentPart) -> ConstataResult<PartInclusion> {
+    let salts = self.parsed_salts()?;
+    let parts = self.document().await?.document_part_scope().order_by(DocumentPartOrderBy::Id).all().await?;
+
+    let leaves: Vec<String> = parts.iter().zip(&salts)
+      .map(|(p, s)| DocumentPartCommitment::leaf(&s.salt, &p.attrs.hash) )
+      .collect();
+
+    let index = salts.iter().position(|s| s.part_id == part.attrs.id)
+      .ok_or_else(|| Error::validation("document_part", "is_not_part_of_this_commitment"))?;
+
+    let path = MerkleTree::new(leaves.clone()).path(index)
+      .ok_or_else(|| Error::validation("document_part", "is_not_part_of_this_commitment"))?;
+
+    Ok(PartInclusion{
+      commitment: self.attrs.hash.clone(),
+      bulletin_id: self.attrs.bulletin_id,
+      salt: salts[index].salt.clone(),
+      leaf: leaves[index].clone(),
+      path,
+    })
+  }
+}
+
+describe! {
+  dbtest!{ commits_to_every_part_of_a_document (site, c)
+    let alice = c.alice().await.add_funds().await;
+    let story = alice.make_story().await;
+    let doc = alice.make_signed_document(&story, &read("document.zip"), None).await;
+
+    let commitment = doc.document_part_commitment_scope().one().await?;
+    let parts = doc.document_part_vec().await?;
+    assert!(parts.len() > 1);
+    assert_eq!(commitment.parsed_salts()?.len(), parts.len());
+
+    for part in &parts {
+      let inclusion = commitment.inclusion(part).await?;
+      assert_eq!(inclusion.leaf, DocumentPartCommitment::leaf(&inclusion.salt, part.hash()));
+      assert_eq!(&merkle::MerkleTree::root_from_path(&inclusion.leaf, &inclusion.path), commitment.hash());
+    }
+
+    let payload = site.bulletin().find(commitment.bulletin_id()).await?.payload().await?;
+    assert!(payload.contains(commitment.hash()));
+  }
+}
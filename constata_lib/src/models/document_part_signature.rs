@@ -23,6 +23,10 @@ model!{
     signature_hash: String,
     #[sqlx_model_hints(int4, default)]
     bulletin_id: Option<i32>,
+    #[sqlx_model_hints(varchar)]
+    merkle_root: Option<String>,
+    #[sqlx_model_hints(text)]
+    merkle_path: Option<String>,
   },
   belongs_to {
     Bulletin(bulletin_id),
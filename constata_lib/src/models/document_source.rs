@@ -8,6 +8,7 @@ pub enum DocumentSource {
   Api,
   Telegram, // Legacy document source from when we had a telegram robot.
   Internal,
+  WebCapture,
 }
 
 impl sqlx::postgres::PgHasArrayType for DocumentSource {
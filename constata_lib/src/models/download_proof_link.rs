@@ -65,8 +65,7 @@ impl DownloadProofLink {
   pub async fn html_proof(&self, key: &PrivateKey, lang: i18n::Lang) -> ConstataResult<String> {
     self.document().await?
       .story().await?
-      .proof(self.state.settings.network, &key).await?
-      .render_html(lang)
+      .cached_html_proof(&key, lang).await
   }
 
   pub async fn safe_env_url(&self) -> ConstataResult<String> {
@@ -121,6 +120,13 @@ impl DownloadProofLink {
     })
   }
 
+  pub async fn has_verifiable_credential(&self) -> ConstataResult<bool> {
+    match self.entry_optional().await? {
+      Some(e) => e.has_verifiable_credential().await,
+      None => Ok(false),
+    }
+  }
+
   pub async fn share_on_social_networks_call_to_action(&self, l: &i18n::Lang) -> ConstataResult<String> {
     let share_on_social_networks_call_to_action = match self.document().await?.entry_optional().await? {
       Some(entry) => {
@@ -176,10 +182,12 @@ impl InsertDownloadProofLink {
     let access_token = org.state.access_token()
       .create(&person, AccessTokenKind::DownloadProofLink, Some(duration_days)).await?;
 
+    let entry_token = document.entry_optional().await?.and_then(|e| e.attrs.public_token);
+
     Ok(Self{
       document_id: document.attrs.id.clone(),
       access_token_id: *access_token.id(),
-      public_token: MagicLink::make_random_token(),
+      public_token: entry_token.unwrap_or_else(MagicLink::make_random_token),
     })
   }
 }
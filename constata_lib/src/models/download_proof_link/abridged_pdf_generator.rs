@@ -106,7 +106,7 @@ impl AbridgedPdfGenerator {
           ),
         };
 
-        let schema = entry.issuance().await?.template().await?.parsed_schema()?;
+        let schema = entry.issuance().await?.pinned_template().await?.parsed_schema()?;
         let params = entry.parsed_params()?;
 
         let mut fields = vec![];
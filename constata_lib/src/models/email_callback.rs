@@ -53,7 +53,12 @@ impl EmailCallback {
       return Err(Error::validation("sent_at", "cannot_mark_as_sent"));
     }
 
-    Ok(self.update().sent_at(Some(Utc::now())).save().await?)
+    let sent = self.update().sent_at(Some(Utc::now())).save().await?;
+    let entry = sent.state.entry().select().email_callback_id_eq(&sent.attrs.id).optional().await?;
+    if let Some(entry) = entry {
+      sent.state.web_callback().schedule_entry(WebCallbackKind::EntryEmailSent, &entry).await?;
+    }
+    Ok(sent)
   }
 
   pub async fn render_mailer_html(&self) -> ConstataResult<String> {
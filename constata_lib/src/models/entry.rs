@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use duplicate::duplicate_item;
 
 use bitcoin::util::misc::MessageSignature;
+use super::merkle::MerklePath;
 
 model!{
   state: Site,
@@ -34,6 +35,16 @@ model!{
     email_callback_id: Option<i32>,
     #[sqlx_model_hints(int4, default)]
     deletion_id: Option<i32>,
+    #[sqlx_model_hints(varchar, default)]
+    public_token: Option<String>,
+    #[sqlx_model_hints(int4, default)]
+    reissue_of_id: Option<i32>,
+    #[sqlx_model_hints(boolean, default)]
+    notify: bool,
+    #[sqlx_model_hints(varchar, default)]
+    external_id: Option<String>,
+    #[sqlx_model_hints(timestamptz, default)]
+    completed_at: Option<UtcDateTime>,
   },
   belongs_to {
     Person(person_id),
@@ -63,7 +74,7 @@ impl Entry {
   }
 
   pub async fn title(&self) -> ConstataResult<Option<String>> {
-    if let Some(title_override) = self.issuance().await?.template().await?.attrs.og_title_override {
+    if let Some(title_override) = self.issuance().await?.pinned_template().await?.attrs.og_title_override {
       return Ok(Some(title_override));
     }
     
@@ -74,13 +85,23 @@ impl Entry {
 
   pub async fn params_and_custom_message(&self) -> ConstataResult<(HashMap<String, String>, Option<String>)> {
     let params = self.parsed_params()?;
-    let custom_message = self.issuance().await?.template().await?.attrs.custom_message
+    let custom_message = self.issuance().await?.pinned_template().await?.attrs.custom_message
       .map(|msg| i18n::Tera::one_off(&msg, &i18n::Context::from_serialize(&params)?, true) )
       .transpose()?;
 
     Ok((params, custom_message))
   }
 
+  /* Entries that were not signed yet go back to received, to be rendered again with their new params. */
+  pub async fn replace_params(self, params: &HashMap<String,String>) -> ConstataResult<Received> {
+    self.update()
+      .params(serde_json::to_string(params)?)
+      .state("received".to_string())
+      .size_in_bytes(None)
+      .save().await?
+      .in_received()
+  }
+
   pub fn parsed_params(&self) -> ConstataResult<HashMap<String,String>> {
     Ok(serde_json::from_str(self.params())?)
   }
@@ -120,8 +141,7 @@ impl Entry {
     let Some(doc) = self.document().await? else { return Ok(None) };
 
     doc.story().await?
-      .proof(self.state.settings.network, &key).await?
-      .render_html(lang)
+      .cached_html_proof(&key, lang).await
       .map(|x| Some(x) )
   }
 }
@@ -204,12 +224,44 @@ impl Flow {
 
 impl Received {
   pub async fn create(self, template_files: &[(String, Vec<u8>)]) -> ConstataResult<Created> {
+    let inner = self.into_inner();
+    let schema = inner.issuance().await?.pinned_template().await?.parsed_schema()?;
+    let lang = inner.person().await?.attrs.lang;
+    let params = inner.parsed_params()?;
+
+    /* The token is chosen before the certificate exists, so printed PDFs can link to it with a QR code. */
+    let public_token = inner.public_token().clone().unwrap_or_else(MagicLink::make_random_token);
+    let public_url = format!("{}/certificate/{}", &inner.state.settings.url, public_token);
+
+    let helpers = TemplateHelpers::new(&inner.org().await?, lang, &public_url, &params, template_files)?;
+    let mut files = template_files.to_vec();
+    if let Some(claims) = VerifiableCredential::claims_for(&inner, &public_url).await? {
+      files.push((CREDENTIAL_FILE.to_string(), VerifiableCredential::canonical_json(&claims)?.into_bytes()));
+    }
+    let destination_buffer = Entry::render_payload(&schema, &params, &files, &helpers)?;
+
+    inner.storage_put(&destination_buffer).await?;
+    inner.update()
+      .state("created".to_string())
+      .size_in_bytes(Some(destination_buffer.len() as i32))
+      .public_token(Some(public_token))
+      .save().await?
+      .in_created()
+  }
+}
+
+impl Entry {
+  /* Expands every tera file in the template with the entry params and template helpers, and zips them along with the other template files. */
+  pub fn render_payload(
+    schema: &TemplateSchema,
+    params: &HashMap<String,String>,
+    template_files: &[(String, Vec<u8>)],
+    helpers: &TemplateHelpers,
+  ) -> ConstataResult<Vec<u8>> {
     use std::io::Write;
     use zip::write::FileOptions;
 
-    let inner = self.into_inner();
-    let context = i18n::tera::Context::from_serialize(inner.parsed_params()?)?;
-
+    let context = i18n::tera::Context::from_serialize(localize_params(schema, params, helpers.lang))?;
     let mut destination_buffer = vec![];
 
     {
@@ -217,8 +269,13 @@ impl Received {
       for (raw_name, bytes) in template_files {
         let name = raw_name.split('/').last().unwrap_or(raw_name);
         let (final_name, final_bytes) = if let Some(tera_name) = Template::is_tera(name) {
-          let expanded = i18n::Tera::one_off(std::str::from_utf8(bytes)?, &context, true)?.into_bytes();
-          (tera_name.to_owned(), expanded)
+          let expanded = helpers.render(tera_name, std::str::from_utf8(bytes)?, &context)?;
+          if PdfLayout::is_pdf_output(tera_name) {
+            let pdf = PdfLayout::parse(&expanded)?.render(tera_name, template_files, &helpers.public_url)?;
+            (tera_name.to_owned(), pdf)
+          } else {
+            (tera_name.to_owned(), expanded.into_bytes())
+          }
         } else {
           (name.to_owned(), bytes.to_owned())
         };
@@ -230,17 +287,21 @@ impl Received {
       destination.finish()?;
     }
 
-    inner.storage_put(&destination_buffer).await?;
-    inner.update()
-      .state("created".to_string())
-      .size_in_bytes(Some(destination_buffer.len() as i32))
-      .save().await?
-      .in_created()
+    Ok(destination_buffer)
   }
 }
 
 impl Created {
   pub async fn apply_signature(self, signature: MessageSignature) -> ConstataResult<Signed> {
+    self.sign(signature, None).await
+  }
+
+  /* The signature is over the merkle root of a whole batch of entries, see issuance::Created::apply_batch_signature. */
+  pub async fn apply_batch_signature(self, root: &str, path: &MerklePath, signature: MessageSignature) -> ConstataResult<Signed> {
+    self.sign(signature, Some((root, path))).await
+  }
+
+  async fn sign(self, signature: MessageSignature, merkle: Option<(&str, &MerklePath)>) -> ConstataResult<Signed> {
     let transactional = self.as_inner().state.entry().transactional().await?;
     let this = transactional.find(self.id()).await?;
     let state = &this.state;
@@ -257,16 +318,19 @@ impl Created {
       .ok_or_else(|| Error::validation("person", "has no pubkey registered. Impossible."))?
       .address();
 
-    let signed_payload = SignedPayload {
-      payload: this.payload().await?,
-      signer,
-      signature
+    let document = match merkle {
+      None => {
+        let signed_payload = SignedPayload { payload: this.payload().await?, signer, signature };
+        state.document().create_from_signed_payload(&story, &signed_payload, None).await?
+      },
+      Some((root, path)) => {
+        let signed_root = SignedPayload { payload: root.as_bytes().to_vec(), signer, signature };
+        state.document().create_from_batch_signed_payload(&story, &this.payload().await?, &signed_root, path).await?
+      },
     };
 
-    let document = state.document().create_from_signed_payload(&story, &signed_payload, None).await?;
-
     let email_callback_id = match params.get("email") {
-      Some(address) if address != "" => {
+      Some(address) if address != "" && *this.notify() => {
         if person.can_send_email().await? {
           Some(state.email_callback().insert(InsertEmailCallback{
             address: address.to_owned(),
@@ -285,12 +349,12 @@ impl Created {
       .state("signed".to_string())
       .document_id(Some(document.attrs.id))
       .email_callback_id(email_callback_id)
-      .save().await?
-      .in_signed();
+      .save().await?;
 
+    signed.state.web_callback().schedule_entry(WebCallbackKind::EntrySigned, &signed).await?;
     transactional.commit().await?;
 
-    signed
+    signed.in_signed()
   }
 }
 
@@ -326,7 +390,139 @@ impl Signed {
 
     download_proof_link.publish().await?;
 
-    self.into_inner().update().state("completed".to_string()).save().await?;
+    let completed = self.into_inner().update()
+      .state("completed".to_string())
+      .completed_at(Some(Utc::now()))
+      .save().await?;
+    completed.state.web_callback().schedule_entry(WebCallbackKind::EntryCompleted, &completed).await?;
     Ok(true)
   }
 }
+
+/*
+ * A completed entry with a mistake, like a misspelled name, can be reissued with corrected params.
+ * The new entry goes through the usual flow in the same issuance, which moves back to 'created' until it's signed.
+ * The original stays as it is, but its public certificate links to the new one once it's completed.
+ */
+impl Completed {
+  pub async fn reissue(self, params: HashMap<String,String>, notify: bool) -> ConstataResult<Created> {
+    let original = self.into_inner();
+    let issuance = original.issuance().await?.in_completed()
+      .map_err(|_| Error::validation("issuance", "must_be_completed_to_reissue"))?;
+
+    if original.superseded_by().await?.is_some() {
+      return Err(Error::validation("entry", "already_reissued"));
+    }
+
+    let template = issuance.as_inner().pinned_template().await?;
+    if let Some(v) = validate_rows(&template.parsed_schema()?, &[params.clone()]).first() {
+      return Err(Error::validation(&v.field, &v.message));
+    }
+
+    let received = original.state.entry().insert(InsertEntry{
+      person_id: original.attrs.person_id,
+      org_id: original.attrs.org_id,
+      issuance_id: original.attrs.issuance_id,
+      row_number: 1 + issuance.as_inner().entry_scope().count().await? as i32,
+      state: "received".to_string(),
+      params: serde_json::to_string(&params)?,
+    }).save().await?
+      .update()
+      .reissue_of_id(Some(original.attrs.id))
+      .notify(notify)
+      .save().await?
+      .in_received()?;
+
+    let template_files = Template::read_name_and_bytes_from_payload(&template.payload().await?).await?;
+    let new_id = *received.id();
+    let created = match received.create(&template_files).await {
+      Ok(created) => created,
+      Err(e) => {
+        /* Unlinked from the original, so it can still be reissued. */
+        original.state.entry().find(&new_id).await?.update()
+          .state("failed".to_string())
+          .errors(Some(e.to_string()))
+          .reissue_of_id(None)
+          .save().await?;
+        return Err(e);
+      }
+    };
+
+    issuance.into_inner().update().state("created".to_string()).save().await?;
+
+    Ok(created)
+  }
+}
+
+impl Entry {
+  pub async fn reissue_of(&self) -> sqlx::Result<Option<Entry>> {
+    match self.reissue_of_id() {
+      Some(id) => Ok(Some(self.state.entry().find(id).await?)),
+      None => Ok(None),
+    }
+  }
+
+  /* Failed reissues don't count, the entry can be reissued again. */
+  pub async fn superseded_by(&self) -> sqlx::Result<Option<Entry>> {
+    Ok(self.state.entry().select().reissue_of_id_eq(&self.attrs.id).all().await?.into_iter().find(|e| !e.is_failed()))
+  }
+
+  /* Where the corrected certificate can be seen, only once the new entry is completed and its certificate published. */
+  pub async fn superseded_by_url(&self) -> ConstataResult<Option<String>> {
+    let Some(newer) = self.superseded_by().await? else { return Ok(None) };
+    if !newer.is_completed() {
+      return Ok(None);
+    }
+    newer.public_certificate_url().await
+  }
+}
+
+describe! {
+  regtest!{ reissues_a_completed_entry (site, c, mut chain)
+    let alice = c.alice().await;
+    let entry = alice.make_entry_and_sign_it().await;
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+    site.issuance().try_complete().await?;
+
+    let issuance = entry.issuance().await?;
+    let mut params = entry.parsed_params()?;
+    let original = entry.reloaded().await?.in_completed()?;
+    assert!(original.clone().reissue(params.clone(), false).await.is_err(), "issuance is not completed yet");
+
+    for e in issuance.entry_vec().await? {
+      if let Some(callback) = e.email_callback().await? {
+        callback.mark_sent().await?;
+      }
+    }
+    site.issuance().try_complete().await?;
+    assert!(issuance.reloaded().await?.is_completed());
+
+    params.remove("course");
+    let err = original.clone().reissue(params.clone(), false).await.unwrap_err();
+    assert_that!(&err.to_string(), rematch("course"));
+
+    params.insert("course".to_string(), "Derecho Épico".to_string());
+    params.insert("name".to_string(), "Lisa Simpson".to_string());
+    params.insert("email".to_string(), "lisa@example.com".to_string());
+    let created = original.clone().reissue(params, false).await?;
+    assert!(original.clone().reissue(created.as_inner().parsed_params()?, false).await.is_err());
+    assert_eq!(created.as_inner().reissue_of().await?.unwrap().attrs.id, *original.id());
+    assert!(original.as_inner().superseded_by_url().await?.is_none());
+
+    let mut signature = None;
+    let created_issuance = issuance.reloaded().await?.in_created()?;
+    while let Some(next) = created_issuance.signing_iterator(signature).await? {
+      signature = Some(alice.sign_issuance_entry(next).await);
+    }
+
+    let signed = created.as_inner().reloaded().await?.in_signed()?;
+    assert!(signed.as_inner().email_callback_id().is_none());
+
+    chain.simulate_stamping().await;
+    site.issuance().try_complete().await?;
+    assert!(issuance.reloaded().await?.is_completed());
+    assert!(original.as_inner().superseded_by_url().await?.is_some());
+    assert!(!original.as_inner().reloaded().await?.is_failed());
+  }
+}
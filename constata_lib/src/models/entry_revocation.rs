@@ -0,0 +1,161 @@
+/*
+ * Completed entries can be revoked by their org, for example when a diploma was issued by mistake.
+ * The entry and its documents are kept as they are, the revocation is a separate record whose hash
+ * is stamped in a bulletin, so anyone can verify when it happened and that it wasn't backdated.
+ */
+use super::*;
+use chrono::SubsecRound;
+
+model!{
+  state: Site,
+  table: entry_revocations,
+  struct EntryRevocation {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    org_id: i32,
+    #[sqlx_model_hints(int4)]
+    entry_id: i32,
+    #[sqlx_model_hints(int4)]
+    person_id: PersonId,
+    #[sqlx_model_hints(text)]
+    reason: String,
+    #[sqlx_model_hints(varchar)]
+    hash: String,
+    #[sqlx_model_hints(int4, default)]
+    bulletin_id: i32,
+    #[sqlx_model_hints(timestamptz)]
+    revoked_at: UtcDateTime,
+  },
+  belongs_to {
+    Org(org_id),
+    Entry(entry_id),
+    Person(person_id),
+    Bulletin(bulletin_id),
+  }
+}
+
+/* What proofs, public certificates, status queries and web callbacks say about a revocation. */
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct RevocationStatus {
+  pub entry_id: i32,
+  pub issuance_id: i32,
+  pub document_id: String,
+  pub reason: String,
+  pub revoked_at: UtcDateTime,
+  pub hash: String,
+  pub preimage: String,
+  pub bulletin_id: i32,
+  pub is_stamped: bool,
+}
+
+impl EntryRevocation {
+  pub fn preimage(document_id: &str, reason: &str, revoked_at: &UtcDateTime) -> String {
+    format!("constata-revocation:{document_id}:{}:{reason}", revoked_at.to_rfc3339())
+  }
+
+  pub async fn status(&self) -> ConstataResult<RevocationStatus> {
+    let entry = self.entry().await?;
+    let document_id = entry.attrs.document_id.clone()
+      .ok_or_else(|| Error::Internal(format!("Revoked entry {} has no document", entry.attrs.id)))?;
+
+    Ok(RevocationStatus {
+      entry_id: entry.attrs.id,
+      issuance_id: entry.attrs.issuance_id,
+      preimage: Self::preimage(&document_id, &self.attrs.reason, &self.attrs.revoked_at),
+      document_id,
+      reason: self.attrs.reason.clone(),
+      revoked_at: self.attrs.revoked_at,
+      hash: self.attrs.hash.clone(),
+      bulletin_id: self.attrs.bulletin_id,
+      is_stamped: self.bulletin().await?.is_published(),
+    })
+  }
+}
+
+impl entry::Completed {
+  pub async fn revoke(self, person: &Person, reason: String) -> ConstataResult<EntryRevocation> {
+    let entry = self.as_inner();
+
+    if person.attrs.org_id != entry.attrs.org_id {
+      return Err(Error::validation("entry", "not_found"));
+    }
+
+    if reason.trim().is_empty() {
+      return Err(Error::validation("reason", "cannot_be_empty"));
+    }
+
+    if entry.revocation().await?.is_some() {
+      return Err(Error::validation("entry", "already_revoked"));
+    }
+
+    let document = self.document().await?;
+
+    /* The revocation date is part of the stamped hash, so it's chosen here instead of by the database.
+     * Postgres keeps only microseconds, so it's truncated to what we can read back when building the preimage again. */
+    let revoked_at = Utc::now().trunc_subsecs(0);
+    let hash = hexdigest(EntryRevocation::preimage(&document.attrs.id, &reason, &revoked_at).as_bytes());
+
+    let revocation = entry.state.entry_revocation().insert(InsertEntryRevocation{
+      org_id: entry.attrs.org_id,
+      entry_id: entry.attrs.id,
+      person_id: person.attrs.id,
+      reason,
+      hash,
+      revoked_at,
+    }).save().await?;
+
+    entry.state.rendered_proof().invalidate_story(document.attrs.story_id).await?;
+    entry.state.web_callback().schedule_entry_revoked(&revocation).await?;
+
+    Ok(revocation)
+  }
+}
+
+impl Entry {
+  pub async fn revocation(&self) -> sqlx::Result<Option<EntryRevocation>> {
+    self.state.entry_revocation().select().entry_id_eq(&self.attrs.id).optional().await
+  }
+}
+
+impl Document {
+  pub async fn revocation(&self) -> sqlx::Result<Option<EntryRevocation>> {
+    match self.entry_optional().await? {
+      Some(entry) => entry.revocation().await,
+      None => Ok(None),
+    }
+  }
+}
+
+describe! {
+  regtest!{ revokes_completed_entries (site, c, mut chain)
+    let alice = c.alice().await;
+    let entry = alice.make_entry_and_sign_it().await;
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+    site.issuance().try_complete().await?;
+
+    let completed = entry.reloaded().await?.in_completed()?;
+    let document = completed.document().await?;
+    let key = TestBlockchain::default_private_key().await?;
+    assert_that!(&document.story().await?.proof(site.settings.network, &key).await?.render_html(i18n::Lang::En)?, not(rematch("by its issuer since")));
+
+    let bob = c.bob().await;
+    assert_that!(&completed.clone().revoke(&bob.person().await, "mistake".to_string()).await.unwrap_err().to_string(), rematch("not_found"));
+    assert_that!(&completed.clone().revoke(&alice.person().await, " ".to_string()).await.unwrap_err().to_string(), rematch("cannot_be_empty"));
+
+    let revocation = completed.clone().revoke(&alice.person().await, "Issued by mistake".to_string()).await?;
+    let revoked = entry.reloaded().await?;
+    assert!(!revoked.has_verifiable_credential().await?);
+    assert_that!(&revoked.verifiable_credential(&key).await.unwrap_err().to_string(), rematch("entry_is_revoked"));
+    assert_that!(&completed.revoke(&alice.person().await, "Again".to_string()).await.unwrap_err().to_string(), rematch("already_revoked"));
+
+    let status = revocation.reloaded().await?.status().await?;
+    assert_eq!(status.hash, hexdigest(status.preimage.as_bytes()));
+    assert!(!status.is_stamped);
+
+    chain.simulate_stamping().await;
+    assert!(revocation.status().await?.is_stamped);
+    assert_that!(&document.story().await?.proof(site.settings.network, &key).await?.render_html(i18n::Lang::En)?, rematch("by its issuer since"));
+  }
+}
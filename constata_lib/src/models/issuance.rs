@@ -5,6 +5,8 @@ use num_traits::ToPrimitive;
 use duplicate::duplicate_item;
 use bitcoin::util::misc::MessageSignature;
 use std::collections::HashMap;
+use super::merkle::MerkleTree;
+use crate::signed_payload::SignedPayload;
 
 model!{
   state: Site,
@@ -18,6 +20,8 @@ model!{
     org_id: i32,
     #[sqlx_model_hints(int4)]
     template_id: i32,
+    #[sqlx_model_hints(int4)]
+    template_version_id: Option<i32>,
     #[sqlx_model_hints(varchar)]
     state: String,
     #[sqlx_model_hints(varchar)]
@@ -28,12 +32,17 @@ model!{
     created_at: UtcDateTime,
     #[sqlx_model_hints(int4, default)]
     deletion_id: Option<i32>,
+    #[sqlx_model_hints(text, default)]
+    batch_signing: Option<String>,
+    #[sqlx_model_hints(text, default)]
+    batch_signature: Option<String>,
   },
   has_many {
     Entry(issuance_id),
   },
   belongs_to {
     Template(template_id),
+    TemplateVersion(template_version_id),
     Person(person_id),
     Org(org_id),
     OrgDeletion(deletion_id),
@@ -89,7 +98,7 @@ impl Issuance {
     use csv::Writer;
 
     let mut wtr = Writer::from_writer(vec![]);
-    let schema = self.template().await?.parsed_schema()?;
+    let schema = self.pinned_template().await?.parsed_schema()?;
 
     let mut headers = vec![
       "constata_state",
@@ -97,6 +106,7 @@ impl Issuance {
       "constata_admin_access_url",
       "constata_issuance_id",
       "constata_id",
+      EXTERNAL_ID_KEY,
     ];
     headers.extend(schema.iter().map(|i| i.name.as_str() ));
 
@@ -108,7 +118,8 @@ impl Issuance {
         entry.notification_status().await?.to_string(),
         entry.admin_access_url().await?.unwrap_or("".to_string()),
         entry.attrs.issuance_id.to_string(),
-        entry.attrs.id.to_string()
+        entry.attrs.id.to_string(),
+        entry.attrs.external_id.clone().unwrap_or_default(),
       ];
 
       let mut params: HashMap<String,String> = entry.parsed_params()?;
@@ -203,46 +214,96 @@ impl Flow {
     }
   }
 
+  /* Once a batch signature is applied the signed entries are fixed, new or replaced ones would be left out of it. */
   pub async fn for_adding_entries(&self) -> ConstataResult<Option<Received>> {
     Ok(match self.to_owned() {
       Flow::Received(a) => Some(a),
-      Flow::Created(a) => Some(a.back_to_received().await?),
+      Flow::Created(a) => {
+        if a.as_inner().attrs.batch_signature.is_some() {
+          return Err(Error::validation("issuance", "batch_signature_already_applied"));
+        }
+        Some(a.back_to_received().await?)
+      },
       _ => None
     })
   }
 }
 
+/* Rows may have this key to identify entries by an id of your own, like a student id.
+ * Appending a row with a key already in the issuance replaces that entry's params, unless it was signed. */
+pub const EXTERNAL_ID_KEY: &str = "constata_external_id";
+
 impl Received {
   pub async fn append_entries(&self, rows: &[HashMap<String,String>]) -> ConstataResult<Vec<entry::Received>> {
     let inner = self.as_inner();
-    let template_schema = inner.template().await?.parsed_schema()?;
+    let template_schema = inner.pinned_template().await?.parsed_schema()?;
+
+    let violations = validate_rows(&template_schema, rows);
+    if let Some(first) = violations.first() {
+      let details: Vec<String> = violations.iter()
+        .map(|v| format!("Entry #{} field {}: {}", v.row, v.field, v.message))
+        .collect();
+      return Err(Error::validation(
+        &first.field,
+        &format!("{}. See your selected template's schema for more information.", details.join(", "))
+      ));
+    }
 
-    let base_index = inner.entry_scope().count().await? as i32;
-    let mut received = vec![];
-    for (i, p) in rows.iter().enumerate() {
-      if let Some(field) = Self::maybe_missing_field(&template_schema, p) {
-        return Err(Error::validation( field, &format!("Entry #{} is missing field {}. See your selected template's schema for more information.", i, field)));
+    let mut keyed = vec![];
+    for row in rows {
+      let mut params = row.clone();
+      let key = params.remove(EXTERNAL_ID_KEY).map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
+      if key.is_some() && keyed.iter().any(|(k, _)| k == &key) {
+        return Err(Error::validation(EXTERNAL_ID_KEY, &format!("{} is repeated", key.unwrap_or_default())));
       }
-
-      received.push(inner.state.entry().insert(InsertEntry{
-        person_id: *inner.person_id(),
-        org_id: *inner.org_id(),
-        issuance_id: *inner.id(),
-        row_number: 1 + base_index + i as i32,
-        state: "received".to_string(),
-        params: serde_json::to_string(&p)?,
-      }).save().await?.in_received()?);
+      keyed.push((key, params));
     }
-    Ok(received)
-  }
 
-  pub fn maybe_missing_field<'a>(schema: &'a TemplateSchema, params: &'a HashMap<String,String>) -> Option<&'a str> {
-    for field in schema {
-      if !field.optional && !params.contains_key(&field.name) {
-        return Some(&field.name)
+    /* Every key is checked before writing anything, so a rejected batch leaves the issuance as it was. */
+    let mut existing = vec![];
+    for (key, _) in &keyed {
+      let entry = match key {
+        Some(k) => inner.entry_scope().external_id_eq(k).optional().await?,
+        None => None,
+      };
+
+      if let Some(e) = &entry {
+        let k = key.clone().unwrap_or_default();
+        if e.is_failed() {
+          return Err(Error::validation(EXTERNAL_ID_KEY, &format!("{k} belongs to a failed entry and cannot be updated")));
+        }
+        if !(e.is_received() || e.is_created()) {
+          return Err(Error::validation(EXTERNAL_ID_KEY, &format!("{k} is already signed and cannot be updated")));
+        }
       }
+      existing.push(entry);
+    }
+
+    let mut row_number = inner.entry_scope().count().await? as i32;
+    let mut received = vec![];
+    for ((key, params), existing) in keyed.into_iter().zip(existing) {
+      let entry = match existing {
+        Some(entry) => entry.replace_params(&params).await?,
+        None => {
+          row_number += 1;
+          inner.state.entry().insert(InsertEntry{
+            person_id: *inner.person_id(),
+            org_id: *inner.org_id(),
+            issuance_id: *inner.id(),
+            row_number,
+            state: "received".to_string(),
+            params: serde_json::to_string(&params)?,
+          }).save().await?
+            .update()
+            .external_id(key)
+            .save().await?
+            .in_received()?
+        }
+      };
+
+      received.push(entry);
     }
-    None
+    Ok(received)
   }
 
   pub async fn create(&self) -> ConstataResult<Created> {
@@ -257,14 +318,16 @@ impl Received {
 
   pub async fn create_helper(&self) -> ConstataResult<Created> {
     let inner = self.as_inner();
-    let template_payload = inner.template().await?.payload().await?;
+    let template_payload = inner.pinned_template().await?.payload().await?;
     let template_files = Template::read_name_and_bytes_from_payload(&template_payload).await?;
 
     for entry in inner.entry_scope().state_eq("received".to_string()).all().await? {
       entry.in_received()?.create(&template_files).await?;
     }
 
-    inner.to_owned().update().state("created".to_string()).save().await?.in_created()
+    let created = inner.to_owned().update().state("created".to_string()).save().await?;
+    inner.state.web_callback().schedule_issuance(WebCallbackKind::IssuanceCreated, &created).await?;
+    created.in_created()
   }
 }
 
@@ -285,6 +348,18 @@ impl EntrySignature {
   }
 }
 
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct BatchLeaf {
+  pub entry_id: i32,
+  pub hash: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct BatchSigningRoot {
+  pub root: String,
+  pub leaves: Vec<BatchLeaf>,
+}
+
 impl Created {
   pub async fn back_to_received(self) -> ConstataResult<Received> {
     self.into_inner().update().state("received".to_string()).save().await?.in_received()
@@ -328,6 +403,80 @@ impl Created {
     Ok(next)
   }
 
+  /* Instead of signing each entry, the issuer may sign the merkle root over the hashes of all created entries. */
+  pub async fn batch_signing_root(&self) -> ConstataResult<BatchSigningRoot> {
+    let mut leaves = vec![];
+    for entry in self.as_inner().entry_scope().state_eq("created".to_string()).order_by(EntryOrderBy::Id).all().await? {
+      leaves.push(BatchLeaf{ entry_id: entry.attrs.id, hash: hexdigest(&entry.payload().await?) });
+    }
+
+    let root = MerkleTree::new(leaves.iter().map(|l| l.hash.clone()).collect()).root().cloned()
+      .ok_or_else(|| Error::validation("issuance", "has_no_entries_to_sign"))?;
+
+    Ok(BatchSigningRoot{ root, leaves })
+  }
+
+  /* The signature is checked right away, but large batches take a while to sign, so entries are signed by a job. */
+  pub async fn apply_batch_signature(self, root: &str, signature: MessageSignature) -> ConstataResult<Created> {
+    let inner = self.as_inner();
+    if inner.attrs.batch_signature.is_some() {
+      return Err(Error::validation("issuance", "batch_signature_already_applied"));
+    }
+
+    let batch = self.batch_signing_root().await?;
+
+    if batch.root != root {
+      return Err(Error::validation("root", "does_not_match_entries"));
+    }
+
+    let signer = inner.person().await?.pubkey().await?
+      .ok_or_else(|| Error::validation("person", "has no pubkey registered. Impossible."))?
+      .address();
+
+    if !SignedPayload{ payload: root.as_bytes().to_vec(), signer, signature }.signed_ok()? {
+      return Err(Error::validation("signature", "wrong_signature"));
+    }
+
+    let issuance = self.into_inner().update()
+      .batch_signing(Some(serde_json::to_string(&batch)?))
+      .batch_signature(Some(signature.to_base64()))
+      .save().await?;
+    issuance.state.job().enqueue(JobKind::ApplyBatchSignature, issuance.attrs.id).await?;
+    issuance.in_created()
+  }
+
+  /* Entries signed by a previous attempt are skipped, so the job can pick up where it left off.
+   * Any other entry that's not waiting for its signature means the batch no longer matches the issuance. */
+  pub async fn sign_batch_entries(self) -> ConstataResult<Signed> {
+    let inner = self.as_inner();
+    let (Some(batch), Some(signature)) = (inner.attrs.batch_signing.as_deref(), inner.attrs.batch_signature.as_deref()) else {
+      return Err(Error::validation("issuance", "has_no_batch_signature"));
+    };
+    let batch: BatchSigningRoot = serde_json::from_str(batch)?;
+    let signature = MessageSignature::from_base64(signature)?;
+
+    let tree = MerkleTree::new(batch.leaves.iter().map(|l| l.hash.clone()).collect());
+    for (i, leaf) in batch.leaves.iter().enumerate() {
+      let entry = inner.state.entry().find(&leaf.entry_id).await?;
+      if entry.is_signed() || entry.is_completed() {
+        continue;
+      }
+      let created = entry.in_created()
+        .map_err(|_| Error::validation("entries", &format!("entry {} is {} and cannot be batch signed", leaf.entry_id, entry.attrs.state)))?;
+      let path = tree.path(i).expect("every leaf has a path");
+      created.apply_batch_signature(&batch.root, &path, signature).await?;
+    }
+
+    /* Failed entries are never signed, every other one must have been part of the batch. */
+    for entry in inner.entry_scope().all().await? {
+      if !(entry.is_signed() || entry.is_completed() || entry.is_failed()) {
+        return Err(Error::validation("entries", &format!("entry {} was not signed by the batch", entry.attrs.id)));
+      }
+    }
+
+    self.into_inner().update().state("signed".to_string()).save().await?.in_signed()
+  }
+
   pub async fn discard(&self) -> ConstataResult<Failed> {
     self.to_owned().into_inner().set_all_failed("user_discarded").await
   }
@@ -347,7 +496,8 @@ impl Signed {
     };
     
     if all_complete {
-      self.clone().into_inner().update().state("completed".to_string()).save().await?;
+      let completed = self.clone().into_inner().update().state("completed".to_string()).save().await?;
+      completed.state.web_callback().schedule_issuance(WebCallbackKind::IssuanceCompleted, &completed).await?;
     }
 
     Ok(())
@@ -595,6 +745,49 @@ describe!{
     assert_eq!(issuance.entry_vec().await?[3].attrs.row_number, 4);
   }
 
+  dbtest!{ updates_entries_appended_again_with_the_same_external_id (site, c)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let issuance = alice.make_issuance(template.attrs.id, read("issuance.csv")).await?;
+
+    let row = |key: &str, name: &str| -> HashMap<String,String> {
+      [("constata_external_id", key), ("name", name), ("course", "Derecho Épico"), ("date", "22 marzo 2022"), ("email", "simpsons@cc.com")]
+        .into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    };
+    let by_key = |key: &str| issuance.entry_scope().external_id_eq(&key.to_string());
+
+    issuance.in_received()?.append_entries(&[row("A1", "Lisa"), row("B2", "Bart")]).await?;
+    assert_eq!(issuance.entry_scope().count().await?, 4);
+
+    site.issuance().create_all_received().await?;
+    let received = issuance.reloaded().await?.flow().for_adding_entries().await?.unwrap();
+    received.append_entries(&[row("A1", "Lisa Simpson"), row(" ", "Maggie")]).await?;
+    assert_eq!(issuance.entry_scope().count().await?, 5);
+
+    let lisa = by_key("A1").one().await?;
+    assert!(lisa.is_received());
+    assert_eq!(lisa.attrs.row_number, 3);
+    assert_eq!(lisa.parsed_params()?.get("name"), Some(&"Lisa Simpson".to_string()));
+    assert!(lisa.parsed_params()?.get("constata_external_id").is_none());
+    assert!(by_key("B2").one().await?.is_created());
+
+    assert!(received.append_entries(&[row("C3", "Homer"), row("C3", "Homer")]).await.is_err());
+
+    by_key("B2").one().await?.update().state("signed".to_string()).save().await?;
+    assert!(received.append_entries(&[row("B2", "Bart Simpson")]).await.is_err());
+    assert_eq!(issuance.entry_scope().count().await?, 5);
+
+    let err = received.append_entries(&[row("D4", "Homer"), row("B2", "Bart Simpson")]).await.unwrap_err();
+    assert_that!(&err.to_string(), rematch("already signed"));
+    assert!(by_key("D4").optional().await?.is_none());
+
+    by_key("B2").one().await?.update().state("failed".to_string()).save().await?;
+    let err = received.append_entries(&[row("B2", "Bart Simpson")]).await.unwrap_err();
+    assert_that!(&err.to_string(), rematch("failed entry"));
+
+    assert!(issuance.export_csv().await?.contains(",3,A1,Lisa Simpson,"));
+  }
+
   dbtest!{ fails_to_build_from_json_with_wrong_fields(_site, c)
     let a = c.alice().await;
     let person = a.person().await;
@@ -795,6 +988,78 @@ describe!{
     assert_eq!(templates_files.len(), 3)
   }
 
+  regtest!{ signs_whole_issuance_with_a_merkle_root (site, c, mut chain)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let issuance = alice.make_issuance(*template.id(), read("issuance.csv")).await?;
+    site.issuance().create_all_received().await?;
+
+    let created = issuance.reloaded().await?.in_created()?;
+    let batch = created.batch_signing_root().await?;
+    assert_eq!(batch.leaves.len(), issuance.entry_vec().await?.len());
+
+    let wrong = SignedPayload::sign_with_key(b"something else", &alice.key);
+    assert!(created.clone().apply_batch_signature(&batch.root, wrong).await.is_err());
+    assert!(created.clone().apply_batch_signature("not the root", wrong).await.is_err());
+
+    let signature = SignedPayload::sign_with_key(batch.root.as_bytes(), &alice.key);
+    let created = created.apply_batch_signature(&batch.root, signature).await?;
+    assert!(created.clone().apply_batch_signature(&batch.root, signature).await.is_err());
+    assert!(issuance.reloaded().await?.is_created());
+    assert!(issuance.reloaded().await?.flow().for_adding_entries().await.is_err());
+
+    site.job().work(JobKind::ApplyBatchSignature).await?;
+    assert!(issuance.reloaded().await?.is_signed());
+
+    for entry in issuance.entry_vec().await? {
+      let doc = entry.in_signed()?.document().await?;
+      let sig = &doc.base_document_part().await?.document_part_signature_vec().await?[0];
+      assert_eq!(sig.attrs.merkle_root.as_ref(), Some(&batch.root));
+      assert!(sig.attrs.merkle_path.is_some());
+    }
+
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+    site.issuance().try_complete().await?;
+
+    let key = TestBlockchain::default_private_key().await?;
+    let entry = &issuance.entry_vec().await?[1];
+    let html = entry.in_completed()?.document().await?.story().await?.proof(Network::Regtest, &key).await?.render_html(i18n::Lang::En)?;
+    assert_that!(&html, rematch(&batch.root));
+  }
+
+  dbtest!{ renders_pdf_outputs_declared_by_template (site, c)
+    use std::io::{Write, Read};
+
+    let mut template_file = vec![];
+    {
+      let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut template_file));
+      zip.start_file("diploma.html.tera", zip::write::FileOptions::default())?;
+      zip.write_all(b"<h1>{{ name }}</h1><p>{{ course }} {{ date }} {{ email }}</p>")?;
+      zip.start_file("diploma.pdf.tera", zip::write::FileOptions::default())?;
+      zip.write_all(br#"{ "width": 297, "height": 210, "elements": [
+        { "type": "text", "text": "{{ name }}", "x": 20, "y": 40, "size": 28, "font": "title" },
+        { "type": "text", "text": "{{ course }} {{ date }} {{ email }}", "x": 20, "y": 80 },
+        { "type": "qr", "x": 250, "y": 160, "size": 35 }
+      ]}"#)?;
+      zip.finish()?;
+    }
+
+    let alice = c.alice().await;
+    let template = alice.make_template(template_file).await;
+    let issuance = alice.make_issuance(*template.id(), read("issuance.csv")).await?;
+    site.issuance().create_all_received().await?;
+
+    let entry = &issuance.entry_vec().await?[0];
+    assert!(entry.attrs.public_token.is_some());
+
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(entry.payload().await?))?;
+    let mut pdf = vec![];
+    zip.by_name("diploma.pdf")?.read_to_end(&mut pdf)?;
+    assert!(pdf.starts_with(b"%PDF"));
+    assert!(zip.by_name("diploma.html").is_ok());
+  }
+
   async fn set_up_csv_issuance_with_custom_template(alice: &SignerClient, issuance_path: &str) -> ConstataResult<Issuance> {
     let template_file = read("template.zip");
     let template = alice.make_template(template_file).await;
@@ -0,0 +1,141 @@
+/*
+ * Checks the rows for a new issuance before creating it. Rows are validated against the template schema,
+ * the first few entries are rendered in memory, the tokens needed are estimated, and repeated emails or
+ * recipient identifiers are reported. Nothing is saved, not even a new template.
+ */
+use super::*;
+use byte_unit::n_mb_bytes;
+use num_traits::ToPrimitive;
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct DryRunReport {
+  pub entries_count: usize,
+  pub violations: Vec<SchemaViolation>,
+  pub previews: Vec<DryRunPreview>,
+  pub duplicates: Vec<DryRunDuplicate>,
+  pub estimated_tokens: i32,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct DryRunPreview {
+  pub row: usize,
+  pub size_in_bytes: usize,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct DryRunDuplicate {
+  pub field: String,
+  pub value: String,
+  pub rows: Vec<usize>,
+}
+
+impl DryRunReport {
+  pub async fn build(person: &Person, template: WizardTemplate, rows: &[HashMap<String,String>], previews: usize) -> ConstataResult<Self> {
+    let (schema, template_files) = template.schema_and_files(person).await?;
+    let violations = validate_rows(&schema, rows);
+    let public_url = format!("{}/certificate/dry-run", person.state.settings.url);
+    let org = person.org().await?;
+
+    let previews: Vec<DryRunPreview> = rows.iter().enumerate().take(previews).map(|(row, params)| {
+      let rendered = TemplateHelpers::new(&org, person.attrs.lang, &public_url, params, &template_files)
+        .and_then(|helpers| Entry::render_payload(&schema, params, &template_files, &helpers));
+      match rendered {
+        Ok(payload) => DryRunPreview{ row, size_in_bytes: payload.len(), error: None },
+        Err(e) => DryRunPreview{ row, size_in_bytes: 0, error: Some(e.to_string()) },
+      }
+    }).collect();
+
+    Ok(Self {
+      entries_count: rows.len(),
+      estimated_tokens: Self::estimate_tokens(&previews, rows.len()),
+      duplicates: Self::find_duplicates(&schema, rows),
+      violations,
+      previews,
+    })
+  }
+
+  pub fn is_valid(&self) -> bool {
+    self.violations.is_empty() && self.previews.iter().all(|p| p.error.is_none())
+  }
+
+  /* Counted like issuance::Created::tokens_needed, entries that were not rendered are assumed to be as large as the average preview. */
+  fn estimate_tokens(previews: &[DryRunPreview], entries_count: usize) -> i32 {
+    let one_mb = Decimal::from(n_mb_bytes!(1));
+    let tokens_for = |size: usize| (Decimal::from(size) / one_mb).ceil().to_i32().unwrap_or(0);
+
+    let rendered: Vec<usize> = previews.iter().filter(|p| p.error.is_none()).map(|p| p.size_in_bytes).collect();
+    if rendered.is_empty() {
+      return 0;
+    }
+
+    let average = rendered.iter().sum::<usize>() / rendered.len();
+    let remaining = entries_count.saturating_sub(rendered.len()) as i32;
+    rendered.iter().map(|s| tokens_for(*s)).sum::<i32>() + tokens_for(average) * remaining
+  }
+
+  fn find_duplicates(schema: &TemplateSchema, rows: &[HashMap<String,String>]) -> Vec<DryRunDuplicate> {
+    let mut fields: Vec<&str> = schema.iter()
+      .filter(|f| f.field_type == TemplateFieldType::Email)
+      .map(|f| f.name.as_str())
+      .collect();
+    for name in ["email", "recipient_identification", EXTERNAL_ID_KEY] {
+      if !fields.contains(&name) {
+        fields.push(name);
+      }
+    }
+
+    let mut duplicates = vec![];
+    for field in fields {
+      let mut seen: Vec<(String, Vec<usize>)> = vec![];
+      for (row, params) in rows.iter().enumerate() {
+        let Some(value) = params.get(field).map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty()) else { continue };
+        match seen.iter_mut().find(|(v, _)| *v == value) {
+          Some((_, found)) => found.push(row),
+          None => seen.push((value, vec![row])),
+        }
+      }
+
+      for (value, rows) in seen.into_iter().filter(|(_, rows)| rows.len() > 1) {
+        duplicates.push(DryRunDuplicate{ field: field.to_string(), value, rows });
+      }
+    }
+
+    duplicates
+  }
+}
+
+describe! {
+  dbtest!{ reports_issues_without_saving_anything (site, c)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let person = alice.person().await;
+
+    let row = |name: &str, email: &str| -> HashMap<String,String> {
+      [("name", name), ("email", email), ("recipient_identification", "A1"), ("course", "Derecho Épico"), ("date", "22 marzo 2022")]
+        .into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    };
+
+    let rows = vec![row("Stan", "stan@cc.com"), row("Kyle", "STAN@cc.com "), row("Kenny", "kenny@cc.com")];
+    let report = DryRunReport::build(&person, WizardTemplate::Existing{ template_id: template.attrs.id }, &rows, 2).await?;
+
+    assert_eq!(report.entries_count, 3);
+    assert_eq!(report.previews.len(), 2);
+    assert!(report.previews.iter().all(|p| p.error.is_none() && p.size_in_bytes > 0));
+    assert_eq!(report.estimated_tokens, 3);
+    assert_eq!(report.duplicates, vec![
+      DryRunDuplicate{ field: "email".to_string(), value: "stan@cc.com".to_string(), rows: vec![0, 1] },
+      DryRunDuplicate{ field: "recipient_identification".to_string(), value: "a1".to_string(), rows: vec![0, 1, 2] },
+    ]);
+
+    assert_eq!(site.issuance().select().count().await?, 0);
+    assert_eq!(site.entry().select().count().await?, 0);
+
+    let template_count = site.template().select().count().await?;
+    let new_template = WizardTemplate::New{ kind: TemplateKind::Diploma, name: "New".to_string(), logo: ImageOrText::Text("Constata".to_string()) };
+    let report = DryRunReport::build(&person, new_template, &[HashMap::new()], 5).await?;
+    assert!(!report.is_valid());
+    assert_eq!(site.template().select().count().await?, template_count);
+  }
+}
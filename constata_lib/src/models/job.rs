@@ -0,0 +1,377 @@
+/*
+ * Background work for issuances, web callbacks and emails goes through a queue of jobs in postgres.
+ * Workers claim queued jobs with FOR UPDATE SKIP LOCKED, so several of them can run at once without
+ * picking the same job twice. Failed jobs are retried with an exponential backoff, and after too many
+ * attempts they stay failed until an admin retries them.
+ * A running job holds a lease, renewed every minute while its worker is alive. Jobs whose lease ran out
+ * were abandoned by a crashed or restarted worker.
+ */
+use super::*;
+use juniper::GraphQLEnum;
+
+model!{
+  state: Site,
+  table: jobs,
+  struct Job {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(job_kind)]
+    kind: JobKind,
+    #[sqlx_model_hints(int4)]
+    resource_id: i32,
+    #[sqlx_model_hints(job_state, default)]
+    state: JobState,
+    #[sqlx_model_hints(int4, default)]
+    attempts: i32,
+    #[sqlx_model_hints(int4, default)]
+    max_attempts: i32,
+    #[sqlx_model_hints(timestamptz, default)]
+    run_at: UtcDateTime,
+    #[sqlx_model_hints(timestamptz, default)]
+    locked_at: Option<UtcDateTime>,
+    #[sqlx_model_hints(text, default)]
+    last_error: Option<String>,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+    #[sqlx_model_hints(timestamptz, default)]
+    finished_at: Option<UtcDateTime>,
+  },
+  queries {
+    stale("state = 'running' AND locked_at < now() - interval '5 minutes'"),
+  }
+}
+
+#[derive(sqlx::Type, Copy, Clone, Debug, Deserialize, PartialEq, Serialize, GraphQLEnum)]
+#[sqlx(type_name = "job_kind", rename_all = "snake_case")]
+#[serde(rename_all= "SCREAMING_SNAKE_CASE")]
+pub enum JobKind {
+  RenderIssuance,
+  CompleteIssuance,
+  AttemptWebCallback,
+  SendEmailCallback,
+  ApplyBatchSignature,
+}
+
+impl sqlx::postgres::PgHasArrayType for JobKind {
+  fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+    sqlx::postgres::PgTypeInfo::with_name("_job_kind")
+  }
+}
+
+impl JobKind {
+  /* How many jobs of this kind may be running at the same time, across all workers. */
+  pub fn max_running(&self) -> i64 {
+    match self {
+      JobKind::RenderIssuance => 2,
+      JobKind::CompleteIssuance => 4,
+      JobKind::AttemptWebCallback => 10,
+      JobKind::SendEmailCallback => 5,
+      JobKind::ApplyBatchSignature => 2,
+    }
+  }
+}
+
+#[derive(sqlx::Type, Copy, Clone, Debug, Deserialize, PartialEq, Serialize, GraphQLEnum)]
+#[sqlx(type_name = "job_state", rename_all = "lowercase")]
+#[serde(rename_all= "SCREAMING_SNAKE_CASE")]
+pub enum JobState {
+  Queued,
+  Running,
+  Done,
+  Failed,
+}
+
+impl sqlx::postgres::PgHasArrayType for JobState {
+  fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+    sqlx::postgres::PgTypeInfo::with_name("_job_state")
+  }
+}
+
+/* A job may finish its work, or find it's too early and ask to run again later, like an
+ * issuance waiting for its documents to be stamped. Running again later is not a failed attempt. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobOutcome {
+  Done,
+  RunAgainAt(UtcDateTime),
+}
+
+/* Added to the kind to get the advisory lock taken while claiming jobs of that kind. */
+const CLAIM_LOCK_KEY: i64 = 7_300_000;
+
+impl JobHub {
+  pub async fn enqueue(&self, kind: JobKind, resource_id: i32) -> ConstataResult<Job> {
+    let existing = self.select().use_struct(SelectJob{
+      kind_eq: Some(kind),
+      resource_id_eq: Some(resource_id),
+      state_in: Some(vec![JobState::Queued, JobState::Running, JobState::Failed]),
+      ..Default::default()
+    }).optional().await?;
+
+    if let Some(job) = existing {
+      return Ok(job);
+    }
+
+    Ok(self.insert(InsertJob{ kind, resource_id }).save().await?)
+  }
+
+  /* Finds work that has no job yet. Resources whose job failed for good are left alone. */
+  pub async fn enqueue_pending(&self) -> ConstataResult<()> {
+    for issuance in self.state.issuance().select().state_eq(&"received".to_string()).all().await? {
+      self.enqueue(JobKind::RenderIssuance, issuance.attrs.id).await?;
+    }
+
+    for issuance in self.state.issuance().select().state_eq(&"created".to_string()).batch_signature_is_set(true).all().await? {
+      self.enqueue(JobKind::ApplyBatchSignature, issuance.attrs.id).await?;
+    }
+
+    for issuance in self.state.issuance().select().state_eq(&"signed".to_string()).all().await? {
+      self.enqueue(JobKind::CompleteIssuance, issuance.attrs.id).await?;
+    }
+
+    for callback in self.state.web_callback().select().state_eq(&WebCallbackState::Pending).all().await? {
+      self.enqueue(JobKind::AttemptWebCallback, callback.attrs.id).await?;
+    }
+
+    for callback in self.state.email_callback().ready_to_send().all().await? {
+      self.enqueue(JobKind::SendEmailCallback, callback.attrs.id).await?;
+    }
+
+    Ok(())
+  }
+
+  /* Claims of the same kind take turns, otherwise two workers could both count the same running jobs and go over max_running. */
+  pub async fn claim(&self, kind: JobKind) -> ConstataResult<Vec<Job>> {
+    let tx = self.transactional().await?;
+    tx.state.db.execute(sqlx::query!(
+      "SELECT 1 AS locked FROM pg_advisory_xact_lock($1)",
+      CLAIM_LOCK_KEY + kind as i64
+    )).await?;
+
+    let ids: Vec<i32> = tx.state.db.fetch_all_scalar(sqlx::query_scalar!(
+      r#"UPDATE jobs SET state = 'running', locked_at = now()
+        WHERE id IN (
+          SELECT id FROM jobs
+          WHERE kind = $1 AND state = 'queued' AND run_at <= now()
+          ORDER BY run_at
+          LIMIT GREATEST(0, $2 - (SELECT count(*) FROM jobs WHERE kind = $1 AND state = 'running'))
+          FOR UPDATE SKIP LOCKED
+        ) RETURNING id"#,
+      kind as JobKind,
+      kind.max_running(),
+    )).await?;
+    tx.commit().await?;
+
+    Ok(self.select().use_struct(SelectJob{ id_in: Some(ids), ..Default::default() }).all().await?)
+  }
+
+  pub async fn work(&self, kind: JobKind) -> ConstataResult<()> {
+    if kind == JobKind::SendEmailCallback {
+      return Err(Error::validation("kind", "email_callback_jobs_are_performed_by_the_email_bot"));
+    }
+
+    for job in self.claim(kind).await? {
+      let lease = job.keep_leased();
+      let outcome = job.perform().await;
+      lease.abort();
+      job.finish(outcome).await?;
+    }
+    Ok(())
+  }
+
+  /* Jobs whose lease ran out count as a failed attempt. */
+  pub async fn release_stale(&self) -> ConstataResult<()> {
+    for job in self.stale().all().await? {
+      job.finish(Err::<JobOutcome, _>("timed_out")).await?;
+    }
+    Ok(())
+  }
+}
+
+impl Job {
+  pub async fn perform(&self) -> ConstataResult<JobOutcome> {
+    let site = &self.state;
+    let id = self.resource_id();
+
+    match self.kind() {
+      JobKind::RenderIssuance => {
+        if let Ok(received) = site.issuance().find(id).await?.in_received() {
+          received.create_helper().await?;
+        }
+        Ok(JobOutcome::Done)
+      },
+      JobKind::CompleteIssuance => {
+        let Ok(signed) = site.issuance().find(id).await?.in_signed() else {
+          return Ok(JobOutcome::Done);
+        };
+        signed.try_complete().await?;
+
+        if signed.as_inner().reloaded().await?.is_signed() {
+          Ok(JobOutcome::RunAgainAt(Utc::now() + Duration::minutes(1)))
+        } else {
+          Ok(JobOutcome::Done)
+        }
+      },
+      JobKind::AttemptWebCallback => {
+        let callback = site.web_callback().find(id).await?;
+        let Ok(pending) = callback.in_pending() else {
+          return Ok(JobOutcome::Done);
+        };
+
+        if *callback.next_attempt_on() <= Utc::now() {
+          pending.attempt().await?;
+        }
+
+        let callback = callback.reloaded().await?;
+        if callback.is_pending() {
+          Ok(JobOutcome::RunAgainAt(*callback.next_attempt_on()))
+        } else {
+          Ok(JobOutcome::Done)
+        }
+      },
+      JobKind::ApplyBatchSignature => {
+        if let Ok(created) = site.issuance().find(id).await?.in_created() {
+          created.sign_batch_entries().await?;
+        }
+        Ok(JobOutcome::Done)
+      },
+      /* The email bot claims these itself, see JobHub::work. If one gets here, it's left queued for the bot. */
+      JobKind::SendEmailCallback => Ok(JobOutcome::RunAgainAt(Utc::now())),
+    }
+  }
+
+  /* Renews the lease every minute until the returned task is aborted, once the job is performed. */
+  pub fn keep_leased(&self) -> tokio::task::JoinHandle<()> {
+    let job = self.clone();
+    tokio::spawn(async move {
+      loop {
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        let renewed = job.state.db.execute(sqlx::query!(
+          "UPDATE jobs SET locked_at = now() WHERE id = $1 AND state = 'running'",
+          job.attrs.id
+        )).await;
+
+        if renewed.is_err() {
+          break;
+        }
+      }
+    })
+  }
+
+  pub async fn finish<E: std::fmt::Debug>(self, outcome: Result<JobOutcome, E>) -> ConstataResult<Job> {
+    let update = self.clone().update().locked_at(None);
+
+    let saved = match outcome {
+      Ok(JobOutcome::Done) => update.state(JobState::Done).finished_at(Some(Utc::now())).save().await?,
+      Ok(JobOutcome::RunAgainAt(run_at)) => update.state(JobState::Queued).run_at(run_at).save().await?,
+      Err(e) => {
+        let attempts = self.attrs.attempts + 1;
+        let mut last_error = format!("{e:?}");
+        last_error.truncate(5000);
+        let update = update.attempts(attempts).last_error(Some(last_error));
+
+        if attempts >= self.attrs.max_attempts {
+          self.give_up().await?;
+          update.state(JobState::Failed).finished_at(Some(Utc::now())).save().await?
+        } else {
+          update.state(JobState::Queued)
+            .run_at(Utc::now() + Duration::seconds(30_i64 * 2_i64.pow(attempts as u32 - 1)))
+            .save().await?
+        }
+      }
+    };
+
+    Ok(saved)
+  }
+
+  /* Once a job fails for good, the resource it was working on is marked as failed too, if it has such state. */
+  async fn give_up(&self) -> ConstataResult<()> {
+    if self.attrs.kind == JobKind::RenderIssuance {
+      let issuance = self.state.issuance().find(self.resource_id()).await?;
+      if issuance.is_received() {
+        issuance.set_all_failed("creation_failed").await?;
+      }
+    }
+    Ok(())
+  }
+
+  pub async fn retry(self) -> ConstataResult<Job> {
+    if self.attrs.state != JobState::Failed {
+      return Err(Error::validation("state", "only_failed_jobs_can_be_retried"));
+    }
+
+    Ok(self.update()
+      .state(JobState::Queued)
+      .attempts(0)
+      .run_at(Utc::now())
+      .finished_at(None)
+      .save().await?)
+  }
+}
+
+describe! {
+  dbtest!{ retries_failed_jobs_with_backoff_until_giving_up (site, c)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let issuance = alice.make_issuance(template.attrs.id, read("issuance.csv")).await?;
+
+    site.job().enqueue_pending().await?;
+    site.job().enqueue_pending().await?;
+    assert_eq!(site.job().select().count().await?, 1);
+
+    let job = site.job().claim(JobKind::RenderIssuance).await?.pop().unwrap();
+    assert_eq!(job.attrs.state, JobState::Running);
+    assert!(site.job().claim(JobKind::RenderIssuance).await?.is_empty());
+
+    let mut job = job.finish(Err::<JobOutcome, _>("boom")).await?;
+    assert_eq!(job.attrs.state, JobState::Queued);
+    assert_eq!(job.attrs.attempts, 1);
+    assert!(job.attrs.run_at > Utc::now());
+    assert!(site.job().claim(JobKind::RenderIssuance).await?.is_empty());
+
+    for _ in 1..5 {
+      job = job.finish(Err::<JobOutcome, _>("boom")).await?;
+    }
+    assert_eq!(job.attrs.state, JobState::Failed);
+    assert_eq!(job.attrs.last_error.as_deref(), Some("\"boom\""));
+    assert!(issuance.reloaded().await?.is_failed());
+
+    site.job().enqueue_pending().await?;
+    assert_eq!(site.job().select().count().await?, 1);
+
+    let job = job.retry().await?;
+    assert_eq!(job.attrs.state, JobState::Queued);
+    assert!(job.clone().retry().await.is_err());
+  }
+
+  dbtest!{ releases_running_jobs_only_once_their_lease_runs_out (site, c)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let issuance = alice.make_issuance(template.attrs.id, read("issuance.csv")).await?;
+
+    let job = site.job().enqueue(JobKind::RenderIssuance, issuance.attrs.id).await?;
+    site.job().claim(JobKind::RenderIssuance).await?;
+
+    site.job().release_stale().await?;
+    assert_eq!(job.reloaded().await?.attrs.state, JobState::Running);
+
+    job.reloaded().await?.update().locked_at(Some(Utc::now() - Duration::minutes(6))).save().await?;
+    site.job().release_stale().await?;
+    let job = job.reloaded().await?;
+    assert_eq!(job.attrs.state, JobState::Queued);
+    assert_eq!(job.attrs.attempts, 1);
+
+    assert!(site.job().work(JobKind::SendEmailCallback).await.is_err());
+  }
+
+  dbtest!{ renders_received_issuances_from_the_queue (site, c)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let issuance = alice.make_issuance(template.attrs.id, read("issuance.csv")).await?;
+
+    site.job().enqueue(JobKind::RenderIssuance, issuance.attrs.id).await?;
+    site.job().work(JobKind::RenderIssuance).await?;
+
+    assert!(issuance.reloaded().await?.is_created());
+    assert_eq!(site.job().select().one().await?.attrs.state, JobState::Done);
+  }
+}
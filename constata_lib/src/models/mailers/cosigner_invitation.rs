@@ -0,0 +1,63 @@
+use crate::{
+  prelude::*,
+  models::AttestationCosigner,
+};
+
+#[derive(Debug, Serialize)]
+pub struct EmailCosignerInvitation {
+  pub attestation_id: i32,
+  pub on_behalf_of: String,
+  pub document_names: Vec<String>,
+  pub deadline: Option<String>,
+  pub is_reminder: bool,
+  pub lang: i18n::Lang,
+}
+
+impl EmailCosignerInvitation {
+  pub async fn new(cosigner: &AttestationCosigner) -> ConstataResult<Self> {
+    let attestation = cosigner.attestation().await?;
+    let story = attestation.story().await?;
+
+    let mut document_names = vec![];
+    for doc in story.documents().await? {
+      document_names.push(doc.friendly_name().await?);
+    }
+
+    Ok(EmailCosignerInvitation{
+      attestation_id: attestation.attrs.id,
+      on_behalf_of: attestation.org().await?.name_for_on_behalf_of().await?,
+      document_names,
+      deadline: attestation.attrs.cosigning_deadline.map(|d| d.format("%F %R UTC").to_string()),
+      is_reminder: cosigner.is_reminder(),
+      lang: story.attrs.lang,
+    })
+  }
+
+  pub fn render_html(&self) -> ConstataResult<String> {
+    Ok(crate::RENDERER.i18n_and_serialize("emails/in_layout", self.lang, "cosigner_invitation.html", &self)?.to_utf8()?)
+  }
+}
+
+describe! {
+  use crate::models::{Cosigning, CosignerInvitee};
+
+  dbtest!{ renders_cosigner_invitations (site, c)
+    let alice = c.alice().await;
+    let bob = c.bob().await;
+    let cosigning = Cosigning{
+      invitees: vec![CosignerInvitee::Pubkey(bob.public_key().to_string())],
+      threshold: None,
+      deadline: None,
+    };
+    site.attestation()
+      .create_cosigned(&alice.person().await, &[alice.signed_payload(b"hello")], None, None, None, vec![], Some(cosigning)).await?;
+
+    let cosigner = site.attestation_cosigner().not_notified().await?.pop().unwrap();
+    let content = cosigner.render_mailer_html().await?;
+    assert_that!(&content, rematch("te invitó a firmar"));
+
+    let reminder = cosigner.mark_notified().await?;
+    let content = reminder.render_mailer_html().await?;
+    assert_that!(&content, rematch("Te recordamos"));
+  }
+}
@@ -4,6 +4,9 @@ pub use entry_done::*;
 pub mod email_parked_documents;
 pub use email_parked_documents::*;
 
+pub mod cosigner_invitation;
+pub use cosigner_invitation::*;
+
 #[derive(Debug, Clone)]
 pub struct AttachmentStruct {
   pub name: String,
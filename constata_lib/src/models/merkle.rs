@@ -0,0 +1,132 @@
+/*
+ * A plain sha256 merkle tree over hex encoded leaves.
+ * Each leaf is hashed behind a 0x00 byte, and each node is the hexdigest of a 0x01 byte followed by its children's hex strings,
+ * so an inner node can never be passed off as a leaf.
+ * A node without a sibling is promoted to the next level as is.
+ * Inclusion paths list the sibling at each level, bottom up, so anyone can rebuild the root from a leaf.
+ */
+use super::hasher::hexdigest;
+use serde::{Serialize, Deserialize};
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MerkleSide {
+  Left,
+  Right,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MerkleStep {
+  pub side: MerkleSide,
+  pub hash: String,
+}
+
+pub type MerklePath = Vec<MerkleStep>;
+
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+  leaves: Vec<String>,
+  levels: Vec<Vec<String>>,
+}
+
+impl MerkleTree {
+  pub fn new(leaves: Vec<String>) -> Self {
+    let mut levels = vec![leaves.iter().map(|l| Self::hash_leaf(l)).collect::<Vec<_>>()];
+
+    while levels.last().map(|l| l.len() > 1).unwrap_or(false) {
+      let next = levels.last().expect("at least one level").chunks(2).map(|pair| match pair {
+        [left, right] => Self::join(left, right),
+        [single] => single.clone(),
+        _ => unreachable!(),
+      }).collect();
+      levels.push(next);
+    }
+
+    Self { leaves, levels }
+  }
+
+  pub fn hash_leaf(leaf: &str) -> String {
+    hexdigest(&[&[LEAF_PREFIX], leaf.as_bytes()].concat())
+  }
+
+  pub fn join(left: &str, right: &str) -> String {
+    hexdigest(&[&[NODE_PREFIX], left.as_bytes(), right.as_bytes()].concat())
+  }
+
+  pub fn root(&self) -> Option<&String> {
+    self.levels.last().and_then(|l| l.first())
+  }
+
+  pub fn leaves(&self) -> &[String] {
+    &self.leaves
+  }
+
+  pub fn path(&self, index: usize) -> Option<MerklePath> {
+    if index >= self.leaves().len() {
+      return None;
+    }
+
+    let mut path = vec![];
+    let mut position = index;
+
+    for level in &self.levels[..self.levels.len() - 1] {
+      let sibling = if position % 2 == 0 {
+        level.get(position + 1).map(|hash| MerkleStep{ side: MerkleSide::Right, hash: hash.clone() })
+      } else {
+        Some(MerkleStep{ side: MerkleSide::Left, hash: level[position - 1].clone() })
+      };
+
+      if let Some(step) = sibling {
+        path.push(step);
+      }
+      position /= 2;
+    }
+
+    Some(path)
+  }
+
+  pub fn root_from_path(leaf: &str, path: &[MerkleStep]) -> String {
+    path.iter().fold(Self::hash_leaf(leaf), |acc, step| match step.side {
+      MerkleSide::Left => Self::join(&step.hash, &acc),
+      MerkleSide::Right => Self::join(&acc, &step.hash),
+    })
+  }
+}
+
+describe! {
+  test!{ builds_roots_and_paths_for_any_amount_of_leaves
+    for size in 1..=9 {
+      let leaves: Vec<String> = (0..size).map(|i| hexdigest(format!("leaf {i}").as_bytes()) ).collect();
+      let tree = MerkleTree::new(leaves.clone());
+      let root = tree.root().unwrap().clone();
+
+      for (i, leaf) in leaves.iter().enumerate() {
+        let path = tree.path(i).unwrap();
+        assert_eq!(MerkleTree::root_from_path(leaf, &path), root);
+        assert!(MerkleTree::root_from_path(&leaves[(i + 1) % size], &path) != root || size == 1);
+      }
+      assert!(tree.path(size).is_none());
+    }
+  }
+
+  test!{ single_leaf_root_is_its_hash
+    let tree = MerkleTree::new(vec!["abc".to_string()]);
+    assert_eq!(tree.root().unwrap(), &MerkleTree::hash_leaf("abc"));
+    assert_eq!(MerkleTree::hash_leaf("abc"), hexdigest(b"\x00abc"));
+    assert!(tree.path(0).unwrap().is_empty());
+  }
+
+  test!{ inner_nodes_cannot_pass_for_leaves
+    let leaves: Vec<String> = (0..4).map(|i| hexdigest(format!("leaf {i}").as_bytes()) ).collect();
+    let tree = MerkleTree::new(leaves.clone());
+    let root = tree.root().unwrap().clone();
+
+    let inner = MerkleTree::join(&MerkleTree::hash_leaf(&leaves[0]), &MerkleTree::hash_leaf(&leaves[1]));
+    let right = MerkleTree::join(&MerkleTree::hash_leaf(&leaves[2]), &MerkleTree::hash_leaf(&leaves[3]));
+    assert_eq!(MerkleTree::join(&inner, &right), root);
+    assert!(MerkleTree::root_from_path(&inner, &[MerkleStep{ side: MerkleSide::Right, hash: right }]) != root);
+  }
+}
@@ -42,13 +42,30 @@ pub_mods! {
   access_token;
   storable;
   template_schema;
+  template_variables;
+  template_helpers;
+  pdf_layout;
+  template_version;
+  entry_revocation;
   bump;
   storage;
   template;
   issuance;
+  issuance_dry_run;
   template_kind;
   wizard;
+  spreadsheet;
   attestation;
+  attestation_cosigner;
+  web_capture;
+  web_callback_payload;
+  web_callback_endpoint;
+  verifiable_credential;
+  rendered_proof;
+  merkle;
+  document_part_commitment;
+  part_disclosure;
+  job;
 }
 
 pub_mods! {
@@ -20,8 +20,6 @@ model!{
     deletion_id: Option<i32>,
     #[sqlx_model_hints(timestamptz, default)]
     created_at: UtcDateTime,
-    #[sqlx_model_hints(varchar, default)]
-    web_callbacks_url: Option<String>,
   },
   has_many {
     Person(org_id),
@@ -42,6 +40,7 @@ model!{
     Entry(org_id),
     Attestation(org_id),
     WebCallback(org_id),
+    WebCallbackEndpoint(org_id),
   },
   belongs_to {
     OrgDeletion(deletion_id),
@@ -67,6 +66,7 @@ model!{
       LEFT JOIN parked_reminders pr ON d.org_id = pr.org_id
       WHERE d.bulletin_id IS NULL
         AND NOT d.funded
+        AND d.story_id NOT IN (SELECT id FROM stories WHERE awaiting_cosigners)
         AND d.created_at < ($1::TIMESTAMPTZ - INTERVAL '2 DAYS')
         AND (pr.created_at IS NULL OR pr.created_at < ($1::TIMESTAMPTZ - INTERVAL '30 DAYS'))
       GROUP BY d.org_id)
@@ -108,7 +108,10 @@ impl OrgDeletion {
 
     for r in self.issuance_vec().await? { r.storage_put(b"").await?  }
 
-    for t in self.template_vec().await? { t.storage_put(b"").await?  }
+    for t in self.template_vec().await? {
+      t.storage_put(b"").await?;
+      for v in t.versions().await?.into_iter().filter(|v| v.attrs.payload_hash.is_some()) { v.storage_put(b"").await? }
+    }
 
     for e in self.entry_vec().await? { e.storage_put(b"").await?  }
 
@@ -202,6 +205,7 @@ describe!{
     };
     for m in org.template_vec().await? {
       assert_that!(!m.storage_fetch().await?.is_empty());
+      assert_that!(!m.latest_version().await?.storage_fetch().await?.is_empty());
     };
     for m in org.entry_vec().await? {
       assert_that!(!m.storage_fetch().await?.is_empty());
@@ -216,6 +220,9 @@ describe!{
     };
     for m in org.template_vec().await? {
       assert_that!(m.storage_fetch().await?.is_empty());
+      for v in m.versions().await? {
+        assert_that!(v.storage_fetch().await?.is_empty());
+      }
     };
     for m in org.entry_vec().await? {
       assert_that!(m.storage_fetch().await?.is_empty());
@@ -0,0 +1,91 @@
+/*
+ * A PartDisclosure is a share link for a single part of a document.
+ * Whoever has the link gets a redacted proof with only that part, see Proof::new_redacted.
+ */
+use super::*;
+use bitcoin::PrivateKey;
+
+model!{
+  state: Site,
+  table: part_disclosures,
+  struct PartDisclosure {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    org_id: i32,
+    #[sqlx_model_hints(varchar)]
+    document_part_id: String,
+    #[sqlx_model_hints(varchar)]
+    token: String,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+  },
+  belongs_to {
+    Org(org_id),
+    DocumentPart(document_part_id),
+  }
+}
+
+impl PartDisclosureHub {
+  pub async fn create(&self, story: &Story, part_hash: &str) -> ConstataResult<PartDisclosure> {
+    let mut found = None;
+    for doc in story.documents().await? {
+      if let Some(part) = doc.document_part_scope().hash_eq(&part_hash.to_string()).optional().await? {
+        found = Some(part);
+        break;
+      }
+    }
+
+    let part = found.ok_or_else(|| Error::validation("part_hash", "no_part_with_this_hash_in_story"))?;
+
+    /* Documents that are still parked, or were stamped before parts were committed, can't prove a single part. */
+    if part.document().await?.document_part_commitment_scope().optional().await?.is_none() {
+      return Err(Error::validation("part_hash", "document_has_no_part_commitment"));
+    }
+
+    Ok(self.insert(InsertPartDisclosure{
+      org_id: story.attrs.org_id,
+      document_part_id: part.attrs.id,
+      token: MagicLink::make_random_token(),
+    }).save().await?)
+  }
+}
+
+impl PartDisclosure {
+  pub fn url(&self) -> String {
+    format!("{}/disclosure/{}", &self.state.settings.url, self.token())
+  }
+
+  pub async fn html_proof(&self, key: &PrivateKey, lang: i18n::Lang) -> ConstataResult<String> {
+    Proof::new_redacted(&self.document_part().await?, self.state.settings.network, key).await?
+      .render_html(lang)
+  }
+}
+
+describe! {
+  regtest!{ discloses_a_single_part_of_a_document (site, c, mut chain)
+    let alice = c.alice().await.add_funds().await;
+    let story = alice.story_with_signed_doc(&read("document.zip"), None, "").await;
+    let key = TestBlockchain::default_private_key().await?;
+    let parts = story.documents().await?[0].document_part_vec().await?;
+    let disclosed = parts.iter().find(|p| !p.attrs.is_base).unwrap();
+    let hidden = parts.iter().find(|p| p.attrs.id != disclosed.attrs.id && !p.attrs.is_base).unwrap();
+
+    assert_that!(
+      &site.part_disclosure().create(&story, "not_a_hash").await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("no_part_with_this_hash_in_story".to_string()) } }
+    );
+
+    let disclosure = site.part_disclosure().create(&story, disclosed.hash()).await?;
+    assert_that!(&disclosure.url(), rematch("/disclosure/"));
+    assert!(disclosure.html_proof(&key, i18n::Lang::En).await.is_err());
+
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+
+    let html = disclosure.html_proof(&key, i18n::Lang::En).await?;
+    assert_that!(&html, rematch(disclosed.friendly_name()));
+    assert_that!(&html, rematch("only discloses <strong>one part</strong>"));
+    assert!(!html.contains(hidden.friendly_name()));
+  }
+}
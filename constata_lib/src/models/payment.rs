@@ -34,6 +34,9 @@ impl InsertPaymentHub {
         id,
         payment.id(),
       )).await?;
+
+      let invoice = payment.state.invoice().find(&id).await?;
+      payment.state.web_callback().schedule_invoice_paid(&invoice).await?;
     }
 
     payment.state.org()
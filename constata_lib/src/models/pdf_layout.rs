@@ -0,0 +1,239 @@
+/*
+ * Templates may include files ending in .pdf.tera. Once expanded with the entry params they must be
+ * a JSON layout like the one below, and are drawn into a printable PDF included in the entry's zip,
+ * so it's signed and stamped along with the HTML files.
+ *
+ *   { "width": 297, "height": 210, "elements": [
+ *     { "type": "text", "text": "Jane Doe", "x": 20, "y": 40, "size": 28, "font": "title" },
+ *     { "type": "image", "file": "logo.png", "x": 20, "y": 10, "width": 40 },
+ *     { "type": "qr", "x": 250, "y": 160, "size": 35 }
+ *   ]}
+ *
+ * Positions are in millimeters from the top left corner. Images are other files from the template zip.
+ * The QR code points to the entry's public certificate.
+ */
+use super::*;
+use i18n::renderer::RendererFs;
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+use qrcode_generator::QrCodeEcc;
+use printpdf::{
+  Mm, Pt, PdfDocument, PdfLayerReference, IndirectFontRef, Color, Rgb,
+  Image, ImageTransform, Svg, SvgTransform,
+  image_crate::codecs::{png::PngDecoder, jpeg::JpegDecoder},
+};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PdfLayout {
+  pub width: f64,
+  pub height: f64,
+  pub elements: Vec<PdfElement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PdfElement {
+  Text {
+    text: String,
+    x: f64,
+    y: f64,
+    #[serde(default = "PdfElement::default_text_size")]
+    size: f64,
+    #[serde(default)]
+    font: PdfFont,
+    /* Wraps lines at this many characters. */
+    wrap: Option<usize>,
+  },
+  Image {
+    file: String,
+    x: f64,
+    y: f64,
+    width: f64,
+  },
+  Qr {
+    x: f64,
+    y: f64,
+    size: f64,
+  },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfFont {
+  Title,
+  #[default]
+  Body,
+}
+
+impl PdfElement {
+  fn default_text_size() -> f64 { 12.0 }
+}
+
+impl PdfLayout {
+  pub fn is_pdf_output(tera_name: &str) -> bool {
+    tera_name.ends_with(".pdf")
+  }
+
+  pub fn parse(expanded: &str) -> ConstataResult<Self> {
+    serde_json::from_str(expanded).map_err(|_| Error::validation("template", "invalid_pdf_layout"))
+  }
+
+  pub fn render(&self, title: &str, template_files: &[(String, Vec<u8>)], public_url: &str) -> ConstataResult<Vec<u8>> {
+    let (doc, page_ref, layer_ref) = PdfDocument::new(title, Mm(self.width), Mm(self.height), "Main");
+    let layer = doc.get_page(page_ref).get_layer(layer_ref);
+    let title_font = doc.add_external_font(&*crate::RENDERER.fs.read(Path::new("fonts/Manrope-ExtraBold.ttf"))?)?;
+    let body_font = doc.add_external_font(&*crate::RENDERER.fs.read(Path::new("fonts/InterTight-Light.ttf"))?)?;
+
+    for element in &self.elements {
+      match element {
+        PdfElement::Text { text, x, y, size, font, wrap } => {
+          let font_ref = match font { PdfFont::Title => &title_font, PdfFont::Body => &body_font };
+          self.draw_text(&layer, font_ref, text, *x, *y, *size, *wrap);
+        },
+        PdfElement::Image { file, x, y, width } => {
+          let bytes = template_files.iter()
+            .find(|(name, _)| name.split('/').last() == Some(file.as_str()))
+            .map(|(_, bytes)| bytes)
+            .ok_or_else(|| Error::validation("template", &format!("pdf_layout_image_not_found: {file}")))?;
+          self.draw_image(&layer, file, bytes, *x, *y, *width)?;
+        },
+        PdfElement::Qr { x, y, size } => {
+          let qr = qrcode_generator::to_svg_to_string(public_url, QrCodeEcc::Medium, 300, None::<&str>)?;
+          self.draw_svg(&layer, &qr, *x, *y, *size)?;
+        },
+      }
+    }
+
+    let mut writer = BufWriter::new(vec![]);
+    doc.save(&mut writer)?;
+    Ok(writer.into_inner()?)
+  }
+
+  fn draw_text(&self, layer: &PdfLayerReference, font: &IndirectFontRef, text: &str, x: f64, y: f64, size: f64, wrap: Option<usize>) {
+    let lines: Vec<String> = match wrap {
+      Some(width) => textwrap::wrap(text, width).iter().map(|l| l.to_string()).collect(),
+      None => vec![text.to_string()],
+    };
+
+    layer.begin_text_section();
+    layer.set_fill_color(Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)));
+    layer.set_font(font, size);
+    layer.set_line_height(size * 1.2);
+    layer.set_text_cursor(Mm(x), Mm(self.height - y - Mm::from(Pt(size)).0));
+    for (i, line) in lines.iter().enumerate() {
+      if i > 0 {
+        layer.add_line_break();
+      }
+      layer.write_text(line, font);
+    }
+    layer.end_text_section();
+  }
+
+  fn draw_image(&self, layer: &PdfLayerReference, name: &str, bytes: &[u8], x: f64, y: f64, width: f64) -> ConstataResult<()> {
+    let lowercase = name.to_lowercase();
+
+    if lowercase.ends_with(".svg") {
+      return self.draw_svg(layer, std::str::from_utf8(bytes)?, x, y, width);
+    }
+
+    let invalid = || Error::validation("template", &format!("pdf_layout_invalid_image: {name}"));
+
+    let image = if lowercase.ends_with(".png") {
+      PngDecoder::new(Cursor::new(bytes)).and_then(Image::try_from).map_err(|_| invalid())?
+    } else if lowercase.ends_with(".jpg") || lowercase.ends_with(".jpeg") {
+      JpegDecoder::new(Cursor::new(bytes)).and_then(Image::try_from).map_err(|_| invalid())?
+    } else {
+      return Err(Error::validation("template", &format!("pdf_layout_unsupported_image: {name}")));
+    };
+
+    /* Images are placed at 300 dpi, then scaled to the requested width. */
+    let natural_width = Mm::from(image.image.width.into_pt(300.0));
+    let natural_height = Mm::from(image.image.height.into_pt(300.0));
+    let scale = width / natural_width.0;
+
+    image.add_to_layer(layer.clone(), ImageTransform {
+      translate_x: Some(Mm(x)),
+      translate_y: Some(Mm(self.height - y - natural_height.0 * scale)),
+      scale_x: Some(scale),
+      scale_y: Some(scale),
+      dpi: Some(300.0),
+      ..Default::default()
+    });
+
+    Ok(())
+  }
+
+  fn draw_svg(&self, layer: &PdfLayerReference, svg: &str, x: f64, y: f64, width: f64) -> ConstataResult<()> {
+    let parsed = Svg::parse(svg)?;
+    let natural_width = Mm::from(parsed.width.into_pt(300.0));
+    let natural_height = Mm::from(parsed.height.into_pt(300.0));
+    let scale = width / natural_width.0;
+
+    parsed.into_xobject(layer).add_to_layer(layer, SvgTransform {
+      translate_x: Some(Mm(x).into()),
+      translate_y: Some(Mm(self.height - y - natural_height.0 * scale).into()),
+      scale_x: Some(scale),
+      scale_y: Some(scale),
+      dpi: Some(300.0),
+      ..Default::default()
+    });
+
+    Ok(())
+  }
+}
+
+describe! {
+  test!{ renders_a_pdf_layout
+    let layout = PdfLayout::parse(r#"{
+      "width": 297, "height": 210,
+      "elements": [
+        { "type": "text", "text": "Lisa Simpson", "x": 20, "y": 40, "size": 28, "font": "title" },
+        { "type": "text", "text": "Derecho Épico, una materia con un nombre largo", "x": 20, "y": 80, "wrap": 20 },
+        { "type": "image", "file": "logo.svg", "x": 20, "y": 10, "width": 30 },
+        { "type": "qr", "x": 250, "y": 160, "size": 35 }
+      ]
+    }"#)?;
+
+    let files = vec![(
+      "certos_template/logo.svg".to_string(),
+      br#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100"><rect width="100" height="100" fill="red"/></svg>"#.to_vec()
+    )];
+
+    let pdf = layout.render("Diploma", &files, "https://example.com/certificate/abc")?;
+    assert!(pdf.starts_with(b"%PDF"));
+
+    let missing = PdfLayout::parse(r#"{ "width": 10, "height": 10, "elements": [{ "type": "image", "file": "nope.png", "x": 0, "y": 0, "width": 1 }] }"#)?;
+    assert!(missing.render("Diploma", &files, "https://example.com").is_err());
+    assert!(PdfLayout::parse("not json").is_err());
+  }
+
+  test!{ expands_params_in_a_pdf_layout_as_plain_text
+    use i18n::{Lang, tera::Context};
+
+    let helpers = TemplateHelpers {
+      lang: Lang::En,
+      public_url: "https://example.com/certificate/abc".to_string(),
+      params_hash: "f00".to_string(),
+      issuer_name: None,
+      issuer_logo: None,
+      template_files: vec![],
+    };
+    let context = Context::from_serialize(serde_json::json!({
+      "name": "Conan O'Brien",
+      "course": r#"Quoting "C:\Temp" & <friends>"#,
+      "date": "1999-03-03",
+    }))?;
+    let source = r#"{ "width": 297, "height": 210, "elements": [
+      { "type": "text", "text": "{{ name }}", "x": 20, "y": 40 },
+      { "type": "text", "text": "{{ course }} {{ date | localized_date(style='short') }}", "x": 20, "y": 80 }
+    ]}"#;
+
+    let layout = PdfLayout::parse(&helpers.render("diploma.pdf", source, &context)?)?;
+    let texts: Vec<&str> = layout.elements.iter().filter_map(|e| match e {
+      PdfElement::Text { text, .. } => Some(text.as_str()),
+      _ => None,
+    }).collect();
+    assert_eq!(texts, vec!["Conan O'Brien", r#"Quoting "C:\Temp" & <friends> 03/03/1999"#]);
+    assert!(layout.render("Diploma", &[], &helpers.public_url)?.starts_with(b"%PDF"));
+  }
+}
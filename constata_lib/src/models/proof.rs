@@ -9,6 +9,10 @@ use crate::{
     PersonId,
     Person,
     Endorsement,
+    DocumentPart as DbDocumentPart,
+    document_part_commitment::PartInclusion,
+    story_snapshot::TimelineEntry,
+    entry_revocation::RevocationStatus,
   },
 };
 use bitcoin::{ PrivateKey, network::constants::Network};
@@ -28,6 +32,10 @@ pub struct Proof<'a> {
   explorers: Vec<String>,
   secure_origin: String,
   will_be_updated: bool,
+  is_final: bool,
+  disclosure: Option<PartInclusion>,
+  timeline: Vec<TimelineEntry>,
+  revocation: Option<RevocationStatus>,
   #[serde(skip_serializing)]
   key: &'a PrivateKey,
 }
@@ -155,6 +163,41 @@ impl<'a> Proof<'a> {
 
     let public_certificate_url = Self::make_public_certificate_url(&story).await?;
 
+    /* Only snapshots stamped in a published bulletin, with a known preimage, can be verified. */
+    let mut timeline = vec![];
+    for entry in story.timeline().await? {
+      if entry.preimage.is_none() {
+        continue;
+      }
+
+      let Ok(object) = story.state.bulletin().find(&entry.bulletin_id).await?.in_published() else { continue };
+
+      if !bulletins.iter().any(|b| *b.object.id() == entry.bulletin_id) {
+        let contents = object.payload().await?;
+        bulletins.push(BulletinContents { object, contents });
+      }
+
+      timeline.push(entry);
+    }
+
+    let is_final = story.is_sealed() && !will_be_updated && timeline.iter().any(|e| e.is_sealed);
+
+    /* Revocations are shown as soon as they're made, their bulletin is included once published. */
+    let mut revocation = None;
+    for doc in story.documents().await? {
+      let Some(found) = doc.revocation().await? else { continue };
+
+      if let Ok(object) = found.bulletin().await?.in_published() {
+        if !bulletins.iter().any(|b| b.object.id() == object.id()) {
+          let contents = object.payload().await?;
+          bulletins.push(BulletinContents { object, contents });
+        }
+      }
+
+      revocation = Some(found.status().await?);
+      break;
+    }
+
     Ok(Proof {
       bulletins,
       key,
@@ -164,7 +207,55 @@ impl<'a> Proof<'a> {
       persons_missing_kyc,
       secure_origin,
       will_be_updated,
+      is_final,
       public_certificate_url,
+      disclosure: None,
+      timeline,
+      revocation,
+    })
+  }
+
+  /* A proof for a single part, that leaves out every other part of its document and every other document.
+   * The part is tied to its document through the document's part commitment. */
+  pub async fn new_redacted(part: &DbDocumentPart, network: Network, key: &'a PrivateKey) -> ConstataResult<Proof<'a>> {
+    let db_document = part.document().await?;
+    let commitment = db_document.document_part_commitment_scope().optional().await?
+      .ok_or_else(|| Error::validation("document_part", "document_has_no_part_commitment"))?;
+
+    let commitment_bulletin = commitment.bulletin().await?.in_published()
+      .map_err(|_| Error::WaitForBulletin(commitment.attrs.bulletin_id))?;
+
+    let full = Self::new(&db_document.story().await?, network, key).await?;
+
+    let doc = full.documents.iter().find(|d| d.id == db_document.attrs.id)
+      .ok_or_else(|| Error::WaitForBulletin(db_document.attrs.bulletin_id.unwrap_or(commitment.attrs.bulletin_id)))?;
+
+    let disclosed = doc.parts.iter().find(|p| p.object.id == part.attrs.id).cloned()
+      .ok_or_else(|| Error::validation("document_part", "not_found"))?;
+
+    let mut bulletin_ids = vec![doc.bulletin_id];
+    bulletin_ids.extend(disclosed.object.signatures.iter().filter_map(|s| s.bulletin_id));
+
+    let mut bulletins: Vec<BulletinContents> = full.bulletins.into_iter()
+      .filter(|b| bulletin_ids.contains(b.object.id()))
+      .collect();
+
+    if !bulletins.iter().any(|b| *b.object.id() == commitment.attrs.bulletin_id) {
+      let contents = commitment_bulletin.payload().await?;
+      bulletins.push(BulletinContents{ object: commitment_bulletin, contents });
+    }
+
+    let documents = vec![DocumentContents{ parts: vec![disclosed], ..doc.clone() }];
+
+    Ok(Proof {
+      bulletins,
+      documents,
+      public_certificate_url: None,
+      will_be_updated: false,
+      is_final: false,
+      disclosure: Some(commitment.inclusion(part).await?),
+      timeline: vec![],
+      ..full
     })
   }
 
@@ -336,6 +427,9 @@ describe! {
 
     let content_story = make_proof().await?.render_html(i18n::Lang::Es).expect("Content to be generated");
     assert_that!(&content_story, rematch("Contiene <strong>2 documentos"));
+    assert_that!(&content_story, rematch("Línea de tiempo"));
+    assert_that!(&content_story, rematch("los primeros 2 documentos"));
+    assert_eq!(complete.timeline.len(), 2);
 
     // And then it can generate a zip file with everything in it
     let mut zipfile = zip::ZipArchive::new(proof.generate_each_part_html_and_zip(i18n::Lang::Es).unwrap()).unwrap();
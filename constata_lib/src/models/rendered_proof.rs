@@ -0,0 +1,216 @@
+/*
+ * Rendering a proof fetches every document part from storage and renders big Tera templates.
+ * Rendered proofs are cached in storage, keyed by the story's current snapshot hash, the state of
+ * every bulletin involved, any revocation of its entry, and whether the public certificate is published, for each language and format.
+ * Any change to those yields a new key, stale renders and their stored files are deleted when snapshots or bulletins move on.
+ */
+use super::*;
+use bitcoin::PrivateKey;
+use i18n::Lang;
+use std::io::Read;
+
+#[derive(sqlx::Type, Copy, Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[sqlx(type_name = "rendered_proof_format", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RenderedProofFormat {
+  Html,
+  PartsZip,
+}
+
+model!{
+  state: Site,
+  table: rendered_proofs,
+  struct RenderedProof {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    story_id: i32,
+    #[sqlx_model_hints(int4)]
+    story_snapshot_id: i32,
+    #[sqlx_model_hints(varchar)]
+    cache_key: String,
+    #[sqlx_model_hints(language)]
+    lang: Lang,
+    #[sqlx_model_hints(rendered_proof_format)]
+    format: RenderedProofFormat,
+    #[sqlx_model_hints(int4)]
+    size_in_bytes: i32,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+  },
+  belongs_to {
+    Story(story_id),
+    StorySnapshot(story_snapshot_id),
+  }
+}
+
+derive_storable!(RenderedProof, "rp");
+
+impl RenderedProofHub {
+  pub async fn invalidate_story(&self, story_id: i32) -> ConstataResult<()> {
+    self.delete_all(self.select().story_id_eq(&story_id).all().await?).await
+  }
+
+  pub async fn invalidate_bulletin(&self, bulletin_id: i32) -> ConstataResult<()> {
+    let story_ids = self.state.db.fetch_all_scalar(sqlx::query_scalar!(
+      r#"SELECT DISTINCT story_id as "story_id!" FROM documents WHERE bulletin_id = $1"#,
+      bulletin_id
+    )).await?;
+
+    for story_id in story_ids {
+      self.invalidate_story(story_id).await?;
+    }
+    Ok(())
+  }
+
+  async fn delete_all(&self, rendered: Vec<RenderedProof>) -> ConstataResult<()> {
+    for r in rendered {
+      r.storage_delete().await?;
+      self.state.db.execute(sqlx::query!("DELETE FROM rendered_proofs WHERE id = $1", r.attrs.id)).await?;
+    }
+    Ok(())
+  }
+
+  /* Renders the default proof for stories with published documents and nothing cached yet. */
+  pub async fn warm_up(&self, key: &PrivateKey, limit: i64) -> ConstataResult<()> {
+    let story_ids = self.state.db.fetch_all_scalar(sqlx::query_scalar!(
+      r#"SELECT DISTINCT d.story_id as "story_id!" FROM documents d
+        INNER JOIN bulletins b ON b.id = d.bulletin_id
+        WHERE b.state = 'published' AND d.deletion_id IS NULL
+        AND NOT EXISTS (SELECT 1 FROM rendered_proofs rp WHERE rp.story_id = d.story_id)
+        ORDER BY "story_id!" DESC LIMIT $1
+      "#,
+      limit
+    )).await?;
+
+    for id in story_ids {
+      let story = self.state.story().find(&id).await?;
+      let lang = story.attrs.lang;
+      story.cached_proof(key, lang, RenderedProofFormat::Html).await?;
+    }
+
+    Ok(())
+  }
+}
+
+impl Story {
+  /* Returns None when the story has no snapshot for its current contents, those are never cached. */
+  pub async fn proof_cache_key(&self, lang: Lang, format: RenderedProofFormat) -> ConstataResult<Option<(StorySnapshot, String)>> {
+    let Some(snapshot) = self.state.story_snapshot().select().hash_eq(&self.hash().await?).optional().await? else {
+      return Ok(None);
+    };
+
+    let mut preimage = format!("{}-{}-{}", snapshot.attrs.hash, lang.code(), serde_json::to_string(&format)?);
+
+    let documents = self.documents().await?;
+    for doc in &documents {
+      let state = match doc.bulletin().await? {
+        Some(b) => b.attrs.state,
+        None => "parked".to_string(),
+      };
+      preimage.push_str(&format!("-{}:{}", doc.attrs.id, state));
+
+      if let Some(revocation) = doc.revocation().await? {
+        preimage.push_str(&format!("-revoked:{}:{}", revocation.attrs.hash, revocation.bulletin().await?.attrs.state));
+      }
+    }
+
+    let is_public = match documents.first() {
+      Some(doc) => doc.active_download_proof_link().await?.map(|l| l.published_at().is_some()).unwrap_or(false),
+      None => false,
+    };
+    preimage.push_str(&format!("-{is_public}"));
+
+    Ok(Some((snapshot, hasher::hexdigest(preimage.as_bytes()))))
+  }
+
+  pub async fn cached_proof(&self, key: &PrivateKey, lang: Lang, format: RenderedProofFormat) -> ConstataResult<Vec<u8>> {
+    let cache = self.proof_cache_key(lang, format).await?;
+    let mut unreadable = None;
+
+    if let Some((_, cache_key)) = &cache {
+      if let Some(cached) = self.state.rendered_proof().select().cache_key_eq(cache_key).optional().await? {
+        match cached.storage_fetch().await {
+          Ok(bytes) => return Ok(bytes),
+          Err(_) => unreadable = Some(cached),
+        }
+      }
+    }
+
+    let proof = self.proof(self.state.settings.network, key).await?;
+    let bytes = match format {
+      RenderedProofFormat::Html => proof.render_html(lang)?.into_bytes(),
+      RenderedProofFormat::PartsZip => {
+        let mut bytes = vec![];
+        proof.generate_each_part_html_and_zip(lang)?.read_to_end(&mut bytes)?;
+        bytes
+      }
+    };
+
+    /* The row is there but its contents were lost, we store them again under the same key. */
+    if let Some(stale) = unreadable {
+      stale.storage_put(&bytes).await?;
+      stale.update().size_in_bytes(bytes.len() as i32).save().await?;
+    } else if let Some((snapshot, cache_key)) = cache {
+      let inserted = self.state.rendered_proof().insert(InsertRenderedProof{
+        story_id: self.attrs.id,
+        story_snapshot_id: snapshot.attrs.id,
+        cache_key,
+        lang,
+        format,
+        size_in_bytes: bytes.len() as i32,
+      }).save().await;
+
+      /* A concurrent render of the same proof may have won the race, its contents are the same. */
+      if let Ok(rendered) = inserted {
+        rendered.storage_put(&bytes).await?;
+      }
+    }
+
+    Ok(bytes)
+  }
+
+  pub async fn cached_html_proof(&self, key: &PrivateKey, lang: Lang) -> ConstataResult<String> {
+    Ok(String::from_utf8(self.cached_proof(key, lang, RenderedProofFormat::Html).await?)?)
+  }
+}
+
+describe! {
+  regtest!{ caches_rendered_proofs_until_story_or_bulletins_change (site, c, mut chain)
+    let alice = c.alice().await.add_funds().await;
+    let story = alice.story_with_signed_doc(b"alice", None, "").await;
+    let key = TestBlockchain::default_private_key().await?;
+
+    assert!(story.cached_html_proof(&key, Lang::Es).await.is_err());
+    assert_eq!(site.rendered_proof().select().count().await?, 0);
+
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+
+    let first = story.cached_html_proof(&key, Lang::Es).await?;
+    let (_, first_key) = story.proof_cache_key(Lang::Es, RenderedProofFormat::Html).await?.unwrap();
+    assert_eq!(site.rendered_proof().select().count().await?, 1);
+    assert_eq!(story.cached_html_proof(&key, Lang::Es).await?, first);
+    assert_eq!(site.rendered_proof().select().count().await?, 1);
+
+    story.cached_proof(&key, Lang::En, RenderedProofFormat::PartsZip).await?;
+    assert_eq!(site.rendered_proof().select().count().await?, 2);
+
+    let lost = site.rendered_proof().select().cache_key_eq(&first_key).one().await?;
+    lost.storage_delete().await?;
+    assert_eq!(story.cached_html_proof(&key, Lang::Es).await?, first);
+    assert_eq!(site.rendered_proof().select().count().await?, 2);
+    assert!(lost.storage_fetch().await.is_ok());
+
+    alice.make_signed_document(&story, b"hello world", None).await;
+    assert_eq!(site.rendered_proof().select().count().await?, 0);
+    assert!(lost.storage_fetch().await.is_err());
+    let (_, second_key) = story.proof_cache_key(Lang::Es, RenderedProofFormat::Html).await?.unwrap();
+    assert!(first_key != second_key);
+
+    chain.simulate_stamping().await;
+    site.rendered_proof().warm_up(&key, 10).await?;
+    let warmed = site.rendered_proof().select().story_id_eq(story.id()).one().await?;
+    assert_eq!(warmed.attrs.lang, *story.lang());
+  }
+}
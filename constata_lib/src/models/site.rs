@@ -44,6 +44,7 @@ pub struct SiteSettings {
   pub customer_care_email: Option<String>,
   pub recaptcha_threshold: Option<f64>,
   pub default_logo_url: Option<String>,
+  pub web_capture_allowed_hosts: Option<Vec<String>>,
 }
 
 impl SiteSettings {
@@ -67,6 +68,11 @@ impl SiteSettings {
     self.default_logo_url.as_deref().unwrap_or("https://constata.eu/assets/images/logo.png")
   }
 
+  /* Hosts web captures can reach over plain http, or when they resolve to private addresses. Meant for tests. */
+  pub fn web_capture_allowed_hosts(&self) -> Vec<String> {
+    self.web_capture_allowed_hosts.clone().unwrap_or_default()
+  }
+
   pub fn minimum_bulletin_interval(&self) -> Duration {
     Duration::minutes(self.minimum_bulletin_interval)
   }
@@ -0,0 +1,185 @@
+/*
+ * Reads issuance entries from XLSX and ODS files, so staff can upload the lists they export from Excel
+ * without going through CSV and its guessed encodings.
+ * Header cells are matched to schema fields by name or label, ignoring case and spaces, unless a column
+ * mapping says otherwise. Other headers are turned into ASCII names, like 'Dirección' into 'direccion',
+ * as the Wizard only takes those. The rows are then turned into a plain utf8 CSV and processed by the Wizard
+ * like any other upload, so the issuance export keeps working the same way.
+ */
+use super::*;
+use calamine::{Reader, Xlsx, Ods, Range, DataType};
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+pub struct Spreadsheet {
+  pub bytes: Vec<u8>,
+  pub sheet_name: Option<String>,
+  pub column_mapping: HashMap<String, String>,
+}
+
+impl Spreadsheet {
+  pub async fn into_wizard(self, person: Person, template: WizardTemplate, name: String) -> ConstataResult<Wizard> {
+    let schema = template.schema(&person).await?;
+    let csv = self.to_csv(&schema)?;
+    Ok(Wizard{ person, template, name, csv })
+  }
+
+  pub fn to_csv(&self, schema: &TemplateSchema) -> ConstataResult<Vec<u8>> {
+    let range = self.read_range()?;
+    let mut rows = range.rows();
+
+    let headers: Vec<String> = rows.next()
+      .ok_or_else(|| Error::validation("payload", "spreadsheet_is_empty"))?
+      .iter()
+      .enumerate()
+      .map(|(i, cell)| self.field_for_header(schema, &cell_to_string(cell), i))
+      .collect();
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(&headers)?;
+
+    for row in rows {
+      let values: Vec<String> = row.iter().map(cell_to_string).collect();
+      if values.iter().all(|v| v.is_empty()) {
+        continue;
+      }
+      writer.write_record(&values)?;
+    }
+
+    writer.into_inner().map_err(|_| Error::validation("payload", "unexpected"))
+  }
+
+  fn read_range(&self) -> ConstataResult<Range<DataType>> {
+    let invalid = || Error::validation("payload", "not_a_spreadsheet");
+    let cursor = Cursor::new(self.bytes.as_slice());
+
+    match Self::detect_format(&self.bytes) {
+      Some("ods") => self.first_or_named_sheet(Ods::new(cursor).map_err(|_| invalid())?),
+      Some("xlsx") => self.first_or_named_sheet(Xlsx::new(cursor).map_err(|_| invalid())?),
+      _ => Err(invalid()),
+    }
+  }
+
+  fn first_or_named_sheet<R: Reader<Cursor<&'_ [u8]>>>(&self, mut workbook: R) -> ConstataResult<Range<DataType>> {
+    let name = match &self.sheet_name {
+      Some(name) => name.clone(),
+      None => workbook.sheet_names().first().cloned()
+        .ok_or_else(|| Error::validation("payload", "spreadsheet_is_empty"))?,
+    };
+
+    workbook.worksheet_range(&name)
+      .ok_or_else(|| Error::validation("sheet_name", "not_found"))?
+      .map_err(|_| Error::validation("payload", "not_a_spreadsheet"))
+  }
+
+  /* Both formats are zip files, ODS says so in its mimetype file and XLSX has a workbook in the xl directory. */
+  fn detect_format(bytes: &[u8]) -> Option<&'static str> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).ok()?;
+
+    if let Ok(mut mimetype) = archive.by_name("mimetype") {
+      let mut content = String::new();
+      mimetype.read_to_string(&mut content).ok()?;
+      return (content.trim() == "application/vnd.oasis.opendocument.spreadsheet").then(|| "ods");
+    }
+
+    archive.by_name("xl/workbook.xml").ok().map(|_| "xlsx")
+  }
+
+  fn field_for_header(&self, schema: &TemplateSchema, header: &str, index: usize) -> String {
+    if let Some(field) = self.column_mapping.get(header) {
+      return field.clone();
+    }
+
+    let normalize = |s: &str| s.trim().to_lowercase().replace(' ', "_");
+    let wanted = normalize(header);
+
+    schema.iter()
+      .find(|f| {
+        normalize(&f.name) == wanted ||
+        f.label.as_deref().map(normalize).as_ref() == Some(&wanted) ||
+        f.label_es.as_deref().map(normalize).as_ref() == Some(&wanted)
+      })
+      .map(|f| f.name.clone())
+      .unwrap_or_else(|| slugify_header(&wanted, index))
+  }
+}
+
+fn slugify_header(header: &str, index: usize) -> String {
+  let mut slug = String::new();
+  for c in header.chars() {
+    let folded = match c {
+      'á' | 'à' | 'ä' | 'â' | 'ã' => 'a',
+      'é' | 'è' | 'ë' | 'ê' => 'e',
+      'í' | 'ì' | 'ï' | 'î' => 'i',
+      'ó' | 'ò' | 'ö' | 'ô' | 'õ' => 'o',
+      'ú' | 'ù' | 'ü' | 'û' => 'u',
+      'ñ' => 'n',
+      'ç' => 'c',
+      c if c.is_ascii_alphanumeric() => c,
+      _ => '_',
+    };
+    if folded != '_' || !slug.ends_with('_') {
+      slug.push(folded);
+    }
+  }
+
+  let slug = slug.trim_matches('_');
+  if slug.is_empty() { format!("column_{}", index + 1) } else { slug.to_string() }
+}
+
+/* Dates are written as YYYY-MM-DD, which is what date fields expect, and whole numbers lose their trailing '.0'. */
+fn cell_to_string(cell: &DataType) -> String {
+  match cell {
+    DataType::Empty => String::new(),
+    DataType::String(s) => s.trim().to_string(),
+    DataType::Float(f) if f.fract() == 0.0 => format!("{}", *f as i64),
+    DataType::DateTime(_) => cell.as_date().map(|d| d.format("%Y-%m-%d").to_string()).unwrap_or_default(),
+    DataType::DateTimeIso(s) => s.chars().take(10).collect(),
+    other => other.to_string(),
+  }
+}
+
+describe! {
+  test!{ reads_mapped_rows_from_xlsx_and_ods_sheets
+    let schema: TemplateSchema = serde_json::from_value(serde_json::json!([
+      {"name": "name", "optional": false, "common": false, "label": "Full name"},
+      {"name": "course", "optional": false, "common": true},
+      {"name": "date", "optional": false, "common": true, "field_type": "date"},
+      {"name": "email", "optional": true, "common": false},
+    ]))?;
+
+    let spreadsheet = |file: &str, sheet_name: Option<&str>, mapping: &[(&str, &str)]| Spreadsheet {
+      bytes: read(file),
+      sheet_name: sheet_name.map(|s| s.to_string()),
+      column_mapping: mapping.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+    };
+
+    let expected = "name,course,date,email,hours\n\
+      José Pérez,Derecho Épico,2022-03-22,jose@example.com,40\n\
+      Ana Muñoz,Derecho Épico,2022-03-22,,12.5\n";
+
+    let xlsx = spreadsheet("issuance.xlsx", Some("Alumnos"), &[("Fecha", "date")]).to_csv(&schema)?;
+    assert_eq!(String::from_utf8(xlsx)?, expected);
+
+    let ods = spreadsheet("issuance.ods", None, &[("Fecha", "date")]).to_csv(&schema)?;
+    assert_eq!(String::from_utf8(ods)?, expected);
+
+    assert_that!(
+      &spreadsheet("issuance.xlsx", Some("Nope"), &[]).to_csv(&schema).unwrap_err(),
+      structure!{ Error::Validation{ field: eq("sheet_name".to_string()), message: eq("not_found".to_string()) } }
+    );
+    assert_that!(
+      &spreadsheet("issuance.csv", None, &[]).to_csv(&schema).unwrap_err(),
+      structure!{ Error::Validation{ message: eq("not_a_spreadsheet".to_string()) } }
+    );
+  }
+
+  test!{ turns_unmatched_headers_into_ascii_names
+    let schema: TemplateSchema = serde_json::from_value(serde_json::json!([{"name": "name", "optional": false, "common": false}]))?;
+    let spreadsheet = Spreadsheet { bytes: vec![], sheet_name: None, column_mapping: HashMap::new() };
+    let fields: Vec<String> = ["Name", "Dirección", "E-mail", "Año de Egreso", "¿?"].iter().enumerate()
+      .map(|(i, h)| spreadsheet.field_for_header(&schema, h, i))
+      .collect();
+    assert_eq!(fields, vec!["name", "direccion", "e_mail", "ano_de_egreso", "column_5"]);
+  }
+}
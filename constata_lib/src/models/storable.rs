@@ -26,6 +26,11 @@ pub trait Storable: Sized {
   async fn storage_backup_fetch(&self) -> ConstataResult<Vec<u8>> {
     self.site().storage_backup.get(&self.storage_id()).await
   }
+
+  async fn storage_delete(&self) -> ConstataResult<()> {
+    self.site().storage.delete(&self.storage_id()).await?;
+    self.site().storage_backup.delete(&self.storage_id()).await
+  }
 }
 
 macro_rules! derive_storable {
@@ -72,6 +72,22 @@ impl Storage {
     }
   }
 
+  pub async fn delete(&self, name: &str) -> ConstataResult<()> {
+    if self.local {
+      match std::fs::remove_file(format!("/tmp/constata-local-{}-{}", self.bucket.region, name)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {},
+      }
+    } else {
+      let response = self.bucket.delete_object(name).await?;
+      if ![200, 204].contains(&response.status_code()) {
+        return self.error("Deleting", &response, name)
+      }
+    }
+
+    Ok(())
+  }
+
   pub fn error<T>(&self, action: &str, r: &ResponseData, filename: &str) -> ConstataResult<T> {
     let content = format!("{:?}.{:?}.{}.{:?}",
       String::from_utf8_lossy(r.bytes()),
@@ -36,6 +36,10 @@ model!{
     deletion_id: Option<i32>,
     #[sqlx_model_hints(language)]
     lang: Lang,
+    #[sqlx_model_hints(timestamptz, default)]
+    sealed_at: Option<UtcDateTime>,
+    #[sqlx_model_hints(boolean, default)]
+    awaiting_cosigners: bool,
   },
   has_many {
     StorySnapshot(story_id),
@@ -59,6 +63,22 @@ impl StoryHub {
     }).save().await
   }
 
+  /* Seals every story whose open_until has passed, see Story::seal. */
+  pub async fn seal_all_expired(&self) -> ConstataResult<()> {
+    let ids = self.state.db.fetch_all_scalar(sqlx::query_scalar!(
+      "SELECT id FROM stories WHERE open_until < now() AND sealed_at IS NULL AND deletion_id IS NULL"
+    )).await?;
+
+    /* A story that can't be sealed now is retried on the next run, it must not hold back the others. */
+    for id in ids {
+      if let Err(e) = self.find(&id).await?.seal().await {
+        log::error!("Error sealing story {}: {:?}", id, e);
+      }
+    }
+
+    Ok(())
+  }
+
   pub async fn create_for_email_thread(&self, org_id: i32, thread_id: &str, lang: Lang) -> sqlx::Result<Story> {
     self.insert(InsertStory{
       org_id,
@@ -76,26 +96,64 @@ impl Story {
   }
 
   pub async fn hash(&self) -> ConstataResult<String> {
-    let docs: String = self.documents().await?.into_iter().map(|d| d.attrs.id ).collect();
+    Ok(hexdigest(self.snapshot_preimage(&self.document_ids().await?, self.attrs.sealed_at)?.as_bytes()))
+  }
 
-    let preimage = format!("{}-{}-{}-{}",
+  pub async fn document_ids(&self) -> ConstataResult<Vec<String>> {
+    Ok(self.documents().await?.into_iter().map(|d| d.attrs.id ).collect())
+  }
+
+  /* Sealed stories get a distinct final snapshot, unsealed ones keep their original preimage format. */
+  pub fn snapshot_preimage(&self, document_ids: &[String], sealed_at: Option<UtcDateTime>) -> ConstataResult<String> {
+    let mut preimage = format!("{}-{}-{}-{}",
       self.attrs.id,
-      self.org().await?.attrs.id,
+      self.attrs.org_id,
       serde_json::to_string(self.open_until())?,
-      docs
+      document_ids.concat()
     );
 
-    Ok(hexdigest(preimage.as_bytes()))
+    if let Some(sealed_at) = sealed_at {
+      preimage.push_str(&format!("-sealed:{}", sealed_at.timestamp()));
+    }
+
+    Ok(preimage)
+  }
+
+  pub fn is_sealed(&self) -> bool {
+    self.attrs.sealed_at.is_some()
+  }
+
+  /* Once sealed, no more documents can be added. The final snapshot gets stamped like any other,
+   * and the attestation for this story, if any, notifies its web callbacks. */
+  pub async fn seal(self) -> ConstataResult<Story> {
+    if self.is_sealed() {
+      return Ok(self);
+    }
+
+    let story = self.update().sealed_at(Some(Utc::now())).save().await?;
+    story.get_or_create_snapshot().await?;
+
+    for attestation in story.attestation_vec().await? {
+      story.state.web_callback().schedule_attestation_sealed(&attestation).await?;
+    }
+
+    Ok(story)
   }
 
   pub async fn get_or_create_snapshot(&self) -> ConstataResult<StorySnapshot> {
-    let hash = self.hash().await?;
+    let document_ids = self.document_ids().await?;
+    let hash = hexdigest(self.snapshot_preimage(&document_ids, self.attrs.sealed_at)?.as_bytes());
 
     match self.state.story_snapshot().select().hash_eq(&hash).optional().await? {
       Some(x) => Ok(x),
       _ => {
+        self.state.rendered_proof().invalidate_story(self.attrs.id).await?;
         Ok(self.state.story_snapshot()
           .insert(InsertStorySnapshot{ story_id: self.attrs.id, hash })
+          .save().await?
+          .update()
+          .documents_count(Some(document_ids.len() as i32))
+          .sealed(self.is_sealed())
           .save().await?)
       }
     }
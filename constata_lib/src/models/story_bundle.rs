@@ -9,6 +9,7 @@ use super::{
   story::Story as DbStory,
   document_part::DocumentPart as DbDocumentPart,
   document_part_signature::DocumentPartSignature as DbDocumentPartSignature,
+  merkle::MerklePath,
   bulletin,
 };
 use std::collections::{HashMap, HashSet};
@@ -170,6 +171,10 @@ pub struct Signature {
   pub signature_hash: String,
   #[serde(skip_serializing_if = "Option::is_none")]
   pub bulletin_id: Option<i32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub merkle_root: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub merkle_path: Option<MerklePath>,
 }
 
 impl Signature {
@@ -183,6 +188,8 @@ impl Signature {
       signature: sig.attrs.signature,
       signature_hash: sig.attrs.signature_hash,
       bulletin_id: sig.attrs.bulletin_id,
+      merkle_root: sig.attrs.merkle_root,
+      merkle_path: sig.attrs.merkle_path.map(|p| serde_json::from_str(&p)).transpose()?,
     })
   }
 }
@@ -1,7 +1,10 @@
-use crate::models::{
-  Site,
-  model,
-  UtcDateTime,
+use crate::{
+  prelude::*,
+  models::{
+    model,
+    Story,
+    Bulletin,
+  },
 };
 
 model!{
@@ -18,5 +21,137 @@ model!{
     hash: String,
     #[sqlx_model_hints(int4, default)]
     bulletin_id: i32,
+    #[sqlx_model_hints(int4, default)]
+    documents_count: Option<i32>,
+    #[sqlx_model_hints(boolean, default)]
+    sealed: bool,
+  },
+  belongs_to {
+    Story(story_id),
+    Bulletin(bulletin_id),
+  }
+}
+
+/* One point in a story's timeline: the documents the story had when the snapshot was taken,
+ * and the bulletin that stamped the snapshot hash. The preimage lets anyone recompute the hash.
+ * Snapshots taken before the story's open_until changed can't be matched to their documents.
+ * Snapshots know how many documents they had, older ones are matched by trying every prefix of the documents. */
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct TimelineEntry {
+  pub snapshot_id: i32,
+  pub hash: String,
+  pub created_at: UtcDateTime,
+  pub bulletin_id: i32,
+  pub bulletin_state: String,
+  pub transaction_hash: Option<String>,
+  pub block_time: Option<UtcDateTime>,
+  pub preimage: Option<String>,
+  pub document_ids: Option<Vec<String>>,
+  pub is_sealed: bool,
+}
+
+impl StorySnapshot {
+  pub async fn timeline_entry(&self, story: &Story, document_ids: &[String]) -> ConstataResult<TimelineEntry> {
+    let mut preimage = None;
+    let mut matching_ids = None;
+    let mut is_sealed = false;
+
+    let candidates: Vec<(usize, Option<UtcDateTime>)> = match self.attrs.documents_count {
+      Some(count) if count as usize <= document_ids.len() => {
+        vec![(count as usize, if self.attrs.sealed { story.attrs.sealed_at } else { None })]
+      },
+      _ => {
+        let sealings = if story.is_sealed() { vec![None, story.attrs.sealed_at] } else { vec![None] };
+        sealings.into_iter().flat_map(|s| (0..=document_ids.len()).map(move |i| (i, s))).collect()
+      }
+    };
+
+    for (i, sealed_at) in candidates {
+      let candidate = story.snapshot_preimage(&document_ids[..i], sealed_at)?;
+      if hexdigest(candidate.as_bytes()) == self.attrs.hash {
+        preimage = Some(candidate);
+        matching_ids = Some(document_ids[..i].to_vec());
+        is_sealed = sealed_at.is_some();
+        break;
+      }
+    }
+
+    let bulletin = self.bulletin().await?;
+
+    Ok(TimelineEntry {
+      snapshot_id: self.attrs.id,
+      hash: self.attrs.hash.clone(),
+      created_at: self.attrs.created_at,
+      bulletin_id: self.attrs.bulletin_id,
+      bulletin_state: bulletin.attrs.state,
+      transaction_hash: bulletin.attrs.transaction_hash,
+      block_time: bulletin.attrs.block_time,
+      is_sealed,
+      preimage,
+      document_ids: matching_ids,
+    })
+  }
+}
+
+impl Story {
+  pub async fn timeline(&self) -> ConstataResult<Vec<TimelineEntry>> {
+    let document_ids = self.document_ids().await?;
+    let mut entries = vec![];
+    for snapshot in self.story_snapshots().await? {
+      entries.push(snapshot.timeline_entry(self, &document_ids).await?);
+    }
+    Ok(entries)
+  }
+}
+
+describe! {
+  dbtest!{ lists_every_snapshot_with_its_documents (site, c)
+    let alice = c.alice().await;
+    let story = alice.make_story().await;
+    let first = alice.make_signed_document(&story, b"first", None).await;
+    let second = alice.make_signed_document(&story, b"second", None).await;
+
+    let timeline = story.timeline().await?;
+    assert_eq!(timeline.len(), 2);
+    assert_eq!(timeline[0].document_ids, Some(vec![first.attrs.id.clone()]));
+    assert_eq!(timeline[1].document_ids, Some(vec![first.attrs.id.clone(), second.attrs.id.clone()]));
+    assert_eq!(timeline[1].hash, story.hash().await?);
+    assert_eq!(timeline[1].bulletin_state, "draft");
+    assert_eq!(story.story_snapshots().await?[1].attrs.documents_count, Some(2));
+    assert!(timeline[1].block_time.is_none());
+
+    for entry in &timeline {
+      assert_eq!(&hexdigest(entry.preimage.as_ref().unwrap().as_bytes()), &entry.hash);
+    }
+  }
+
+  regtest!{ seals_expired_stories_with_a_final_snapshot (site, c, mut chain)
+    let alice = c.alice().await.add_funds().await;
+    let expired = alice.story_with_signed_doc(b"expired", Some(Utc::now() - Duration::minutes(1)), "").await;
+    let open = alice.story_with_signed_doc(b"open", Some(Utc::now() + Duration::days(1)), "").await;
+
+    site.story().seal_all_expired().await?;
+    let expired = expired.reloaded().await?;
+    assert!(expired.is_sealed());
+    assert!(!open.reloaded().await?.is_sealed());
+
+    assert_that!(
+      &site.document().create_from_signed_payload(&expired, &alice.signed_payload(b"late"), None).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("story_is_sealed".to_string()) } }
+    );
+
+    let timeline = expired.timeline().await?;
+    assert_eq!(timeline.len(), 2);
+    assert!(!timeline[0].is_sealed);
+    assert!(timeline[1].is_sealed);
+    assert_eq!(timeline[0].document_ids, timeline[1].document_ids);
+    assert_eq!(timeline[1].hash, expired.hash().await?);
+
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+
+    let key = TestBlockchain::default_private_key().await?;
+    let html = expired.proof(site.settings.network, &key).await?.render_html(i18n::Lang::Es)?;
+    assert_that!(&html, rematch("Este certificado es <strong>definitivo</strong>"));
   }
 }
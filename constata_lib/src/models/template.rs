@@ -29,6 +29,7 @@ model!{
   },
   has_many {
     Issuance(template_id),
+    TemplateVersion(template_id),
   },
   belongs_to {
     OrgDeletion(deletion_id),
@@ -78,23 +79,22 @@ impl Template {
 
 impl InsertTemplateHub {
   pub async fn validate_and_save(self, payload: &[u8]) -> ConstataResult<Template> {
-    serde_json::from_str::<TemplateSchema>(self.schema()).map_err(|e| Error::validation("schema", &format!("{:?}", e)))?;
+    let schema = serde_json::from_str::<TemplateSchema>(self.schema()).map_err(|e| Error::validation("schema", &format!("{:?}", e)))?;
+    for field in &schema {
+      field.validate_definition().map_err(|e| Error::validation("schema", &e))?;
+    }
 
-    let mut tera = i18n::tera::Tera::default();
     let names_and_bytes = Template::read_name_and_bytes_from_payload(&payload).await
       .map_err(|_| Error::validation("payload", "could_not_process_zip_file"))?;
 
-    let invalid_tera = || Error::validation("payload", "invalid_tera_syntax");
-
-    for (raw_name, bytes) in &names_and_bytes {
-      if Template::is_tera(raw_name).is_some() {
-        let utf8 = std::str::from_utf8(bytes).map_err(|_| invalid_tera())?;
-        tera.add_raw_template(raw_name, utf8).map_err(|_| invalid_tera())?;
-      }
+    let check = TemplateVariablesCheck::check(&schema, &names_and_bytes, self.custom_message().as_deref())?;
+    if !check.is_ok() {
+      return Err(Error::validation("payload", &check.message()));
     }
 
     let template = self.save().await?;
     template.storage_put(payload).await?;
+    template.create_version(Some(payload)).await?;
     Ok(template)
   }
 }
@@ -142,6 +142,30 @@ describe!{
     "could_not_process_zip_file"
   ];
 
+  make_failing_template_test![
+    validates_template_schema_patterns,
+    "template.zip",
+    r#"[{"name":"alumno","optional":false,"common":false,"pattern":"[a-"}]"#,
+    "schema",
+    "invalid_pattern_for_alumno"
+  ];
+
+  make_failing_template_test![
+    validates_variables_match_schema,
+    "template.zip",
+    r#"[{"name":"alumno","optional":false,"common":false}]"#,
+    "payload",
+    "unknown_variables: name; unused_required_fields: alumno"
+  ];
+
+  make_failing_template_test![
+    validates_required_fields_are_used,
+    "template.zip",
+    r#"[{"name":"name","optional":false,"common":false},{"name":"email","optional":false,"common":false},{"name":"course","optional":false,"common":true}]"#,
+    "payload",
+    "unused_required_fields: course"
+  ];
+
   make_failing_template_test![
     validates_template_schema,
     "template.zip",
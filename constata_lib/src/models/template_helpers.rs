@@ -0,0 +1,291 @@
+/*
+ * Filters and functions available to the tera files in a template, besides the entry params.
+ * Every entry is rendered with the same set, whether it's being created, dry-run or previewed,
+ * so what the issuer sees in a preview is what gets signed.
+ * The docs for these are generated from HELPER_DOCS, keep it in sync when adding a helper.
+ */
+use super::*;
+use i18n::{Lang, tera::{self, Tera, Value, Context}};
+use qrcode_generator::QrCodeEcc;
+use std::collections::{BTreeMap, HashMap};
+use chrono::NaiveDate;
+
+/* Only these outputs get their values escaped, text files are rendered verbatim. PDF layouts are escaped as JSON strings. */
+const ESCAPED_OUTPUTS: [&str; 5] = [".html", ".htm", ".xml", ".svg", ".pdf"];
+const EMBEDDABLE_IMAGE_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+const MAX_EMBEDDED_IMAGE_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct HelperDoc {
+  pub name: &'static str,
+  pub kind: &'static str,
+  pub usage: &'static str,
+  pub description: &'static str,
+}
+
+pub const HELPER_DOCS: &[HelperDoc] = &[
+  HelperDoc {
+    name: "localized_date",
+    kind: "filter",
+    usage: r#"{{ date_raw | localized_date }} {{ date_raw | localized_date(style="short") }}"#,
+    description: "Formats a YYYY-MM-DD date in the issuer's language. The default long style is like 'March 3, 1999', the short style is like '03/03/1999'.",
+  },
+  HelperDoc {
+    name: "number",
+    kind: "filter",
+    usage: r#"{{ hours | number }} {{ grade | number(decimals=2) }}"#,
+    description: "Formats a number with the thousands and decimal separators of the issuer's language, rounded to the given decimals, 0 by default.",
+  },
+  HelperDoc {
+    name: "ordinal",
+    kind: "filter",
+    usage: r#"{{ position | ordinal }}"#,
+    description: "Formats a whole number as an ordinal, like '2nd' in english or '2.º' in spanish.",
+  },
+  HelperDoc {
+    name: "certificate_url",
+    kind: "function",
+    usage: r#"<a href="{{ certificate_url() }}">"#,
+    description: "The URL where the public certificate for this entry will be available once it's signed.",
+  },
+  HelperDoc {
+    name: "qr_code",
+    kind: "function",
+    usage: r#"<img src="{{ qr_code() | safe }}">"#,
+    description: "An SVG data URL with a QR code pointing to certificate_url(). Like any value it's escaped in HTML files, add '| safe' to print it as is.",
+  },
+  HelperDoc {
+    name: "params_hash",
+    kind: "function",
+    usage: r#"{{ params_hash() }}"#,
+    description: "A SHA-256 hex digest of the entry params. It's known before rendering, so it can be printed in the certificate itself.",
+  },
+  HelperDoc {
+    name: "issuer_name",
+    kind: "function",
+    usage: r#"{{ issuer_name() }}"#,
+    description: "The public name of the issuing organization, or an empty string if it has none.",
+  },
+  HelperDoc {
+    name: "issuer_logo",
+    kind: "function",
+    usage: r#"<img src="{{ issuer_logo() }}">"#,
+    description: "The logo URL of the issuing organization, or an empty string if it has none.",
+  },
+  HelperDoc {
+    name: "image",
+    kind: "function",
+    usage: r#"<img src="{{ image(file="logo.png") | safe }}">"#,
+    description: "A data URL for an image file in the template. Only PNG, JPEG, GIF and WEBP files up to 2MB can be embedded. Like any value it's escaped in HTML files, add '| safe' to print it as is.",
+  },
+];
+
+#[derive(Debug, Clone)]
+pub struct TemplateHelpers {
+  pub lang: Lang,
+  pub public_url: String,
+  pub params_hash: String,
+  pub issuer_name: Option<String>,
+  pub issuer_logo: Option<String>,
+  pub template_files: Vec<(String, Vec<u8>)>,
+}
+
+impl TemplateHelpers {
+  pub fn new(org: &Org, lang: Lang, public_url: &str, params: &HashMap<String,String>, template_files: &[(String, Vec<u8>)]) -> ConstataResult<Self> {
+    let sorted: BTreeMap<&String, &String> = params.iter().collect();
+
+    Ok(Self {
+      lang,
+      public_url: public_url.to_string(),
+      params_hash: hexdigest(serde_json::to_string(&sorted)?.as_bytes()),
+      issuer_name: org.attrs.public_name.clone(),
+      issuer_logo: org.attrs.logo_url.clone(),
+      template_files: template_files.to_vec(),
+    })
+  }
+
+  /* The output name, like 'diploma.html', decides how values are escaped, see ESCAPED_OUTPUTS. */
+  pub fn render(&self, output_name: &str, source: &str, context: &Context) -> ConstataResult<String> {
+    let mut tera = self.tera()?;
+    if PdfLayout::is_pdf_output(output_name) {
+      tera.set_escape_fn(escape_json_string);
+    }
+    tera.add_raw_template(output_name, source)?;
+    Ok(tera.render(output_name, context)?)
+  }
+
+  fn tera(&self) -> ConstataResult<Tera> {
+    let mut tera = Tera::default();
+    tera.autoescape_on(ESCAPED_OUTPUTS.to_vec());
+
+    let lang = self.lang;
+    tera.register_filter("localized_date", move |value: &Value, args: &HashMap<String, Value>| {
+      let text = tera::try_get_value!("localized_date", "value", String, value);
+      let date = NaiveDate::parse_from_str(&text, "%Y-%m-%d")
+        .map_err(|_| tera::Error::msg(format!("localized_date: '{text}' is not a YYYY-MM-DD date")))?;
+
+      let formatted = match (args.get("style").and_then(|s| s.as_str()), lang) {
+        (Some("short"), Lang::En) => date.format("%m/%d/%Y").to_string(),
+        (Some("short"), Lang::Es) => date.format("%d/%m/%Y").to_string(),
+        _ => localize_date(date, lang),
+      };
+      Ok(Value::String(formatted))
+    });
+
+    tera.register_filter("number", move |value: &Value, args: &HashMap<String, Value>| {
+      let number = number_from_value("number", value)?;
+      let decimals = args.get("decimals").and_then(|d| d.as_u64()).unwrap_or(0) as usize;
+      Ok(Value::String(format_number(number, decimals, lang)))
+    });
+
+    tera.register_filter("ordinal", move |value: &Value, _: &HashMap<String, Value>| {
+      let number = number_from_value("ordinal", value)?;
+      if number.fract() != 0.0 || number < 0.0 {
+        return Err(tera::Error::msg(format!("ordinal: {number} is not a whole number")));
+      }
+      Ok(Value::String(format_ordinal(number as u64, lang)))
+    });
+
+    let public_url = self.public_url.clone();
+    tera.register_function("certificate_url", move |_: &HashMap<String, Value>| Ok(Value::String(public_url.clone())));
+
+    let qr_svg = qrcode_generator::to_svg_to_string(&self.public_url, QrCodeEcc::Medium, 300, None::<&str>)?;
+    let qr_data_url = format!("data:image/svg+xml;base64,{}", base64::encode(qr_svg));
+    tera.register_function("qr_code", move |_: &HashMap<String, Value>| Ok(Value::String(qr_data_url.clone())));
+
+    let params_hash = self.params_hash.clone();
+    tera.register_function("params_hash", move |_: &HashMap<String, Value>| Ok(Value::String(params_hash.clone())));
+
+    let issuer_name = self.issuer_name.clone().unwrap_or_default();
+    tera.register_function("issuer_name", move |_: &HashMap<String, Value>| Ok(Value::String(issuer_name.clone())));
+
+    let issuer_logo = self.issuer_logo.clone().unwrap_or_default();
+    tera.register_function("issuer_logo", move |_: &HashMap<String, Value>| Ok(Value::String(issuer_logo.clone())));
+
+    let files = self.template_files.clone();
+    tera.register_function("image", move |args: &HashMap<String, Value>| {
+      let file = args.get("file").and_then(|f| f.as_str())
+        .ok_or_else(|| tera::Error::msg("image: the file argument is required"))?;
+      embed_image(&files, file).map(Value::String)
+    });
+
+    Ok(tera)
+  }
+
+  pub fn docs_markdown() -> String {
+    let mut docs = "# Template helpers\n\nBesides the entry params, these can be used in any `.tera` file of a template.\n".to_string();
+    for kind in ["filter", "function"] {
+      docs.push_str(&format!("\n## {}s\n", if kind == "filter" { "Filter" } else { "Function" }));
+      for helper in HELPER_DOCS.iter().filter(|h| h.kind == kind) {
+        docs.push_str(&format!("\n### {}\n\n{}\n\n```\n{}\n```\n", helper.name, helper.description, helper.usage));
+      }
+    }
+    docs
+  }
+}
+
+fn escape_json_string(text: &str) -> String {
+  let quoted = Value::String(text.to_string()).to_string();
+  quoted[1..quoted.len() - 1].to_string()
+}
+
+fn number_from_value(filter: &str, value: &Value) -> tera::Result<f64> {
+  match value {
+    Value::Number(n) => n.as_f64(),
+    Value::String(s) => s.trim().parse::<f64>().ok(),
+    _ => None,
+  }.ok_or_else(|| tera::Error::msg(format!("{filter}: {value} is not a number")))
+}
+
+fn format_number(number: f64, decimals: usize, lang: Lang) -> String {
+  let (thousands, decimal) = match lang {
+    Lang::En => (',', '.'),
+    Lang::Es => ('.', ','),
+  };
+
+  let fixed = format!("{:.*}", decimals, number.abs());
+  let (whole, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+  let mut grouped = String::new();
+  for (i, digit) in whole.chars().enumerate() {
+    if i > 0 && (whole.len() - i) % 3 == 0 {
+      grouped.push(thousands);
+    }
+    grouped.push(digit);
+  }
+
+  let sign = if number < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') { "-" } else { "" };
+  if fraction.is_empty() {
+    format!("{sign}{grouped}")
+  } else {
+    format!("{sign}{grouped}{decimal}{fraction}")
+  }
+}
+
+fn format_ordinal(number: u64, lang: Lang) -> String {
+  match lang {
+    Lang::Es => format!("{number}.º"),
+    Lang::En => {
+      let suffix = match (number % 10, number % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+      };
+      format!("{number}{suffix}")
+    }
+  }
+}
+
+/* Images are only taken from the template itself, and only if their contents are really of a type browsers display inertly. */
+fn embed_image(files: &[(String, Vec<u8>)], file: &str) -> tera::Result<String> {
+  let (name, bytes) = files.iter()
+    .find(|(name, _)| name.split('/').last() == Some(file))
+    .ok_or_else(|| tera::Error::msg(format!("image: {file} was not found in the template")))?;
+
+  if bytes.len() > MAX_EMBEDDED_IMAGE_BYTES {
+    return Err(tera::Error::msg(format!("image: {file} is too large to embed")));
+  }
+
+  let (mime, _) = Document::mime_and_ext(bytes, Some(name));
+  if !EMBEDDABLE_IMAGE_TYPES.contains(&mime.as_str()) {
+    return Err(tera::Error::msg(format!("image: {file} is not an image that can be embedded")));
+  }
+
+  Ok(format!("data:{mime};base64,{}", base64::encode(bytes)))
+}
+
+describe! {
+  test!{ renders_helpers_in_the_issuer_language
+    let files = vec![
+      ("template/logo.png".to_string(), read("wizard/logo.png")),
+      ("template/notes.txt".to_string(), b"not an image".to_vec()),
+    ];
+    let helpers = |lang| TemplateHelpers {
+      lang,
+      public_url: "https://constata.eu/certificate/abc".to_string(),
+      params_hash: "f00".to_string(),
+      issuer_name: Some("Constata".to_string()),
+      issuer_logo: None,
+      template_files: files.clone(),
+    };
+    let context = Context::from_serialize(serde_json::json!({"date": "1999-03-03", "hours": "12345.678", "position": "22"}))?;
+    let source = r#"{{ date | localized_date }}|{{ date | localized_date(style="short") }}|{{ hours | number(decimals=2) }}|{{ position | ordinal }}|{{ issuer_name() }}|{{ issuer_logo() }}|{{ params_hash() }}"#;
+
+    assert_eq!(helpers(Lang::En).render("certificate.txt", source, &context)?, "March 3, 1999|03/03/1999|12,345.68|22nd|Constata||f00");
+    assert_eq!(helpers(Lang::Es).render("certificate.txt", source, &context)?, "3 de marzo de 1999|03/03/1999|12.345,68|22.º|Constata||f00");
+    assert_eq!(helpers(Lang::En).render("certificate.html", source, &context)?, "March 3, 1999|03&#x2F;03&#x2F;1999|12,345.68|22nd|Constata||f00");
+
+    let helpers = helpers(Lang::En);
+    assert!(helpers.render("certificate.html", "{{ qr_code() | safe }}", &context)?.starts_with("data:image/svg+xml;base64,"));
+    assert!(helpers.render("certificate.html", r#"{{ image(file="logo.png") | safe }}"#, &context)?.starts_with("data:image/png;base64,"));
+    assert!(helpers.render("certificate.html", r#"{{ image(file="notes.txt") }}"#, &context).is_err());
+    assert!(helpers.render("certificate.html", r#"{{ image(file="missing.png") }}"#, &context).is_err());
+
+    assert_eq!(format_ordinal(11, Lang::En), "11th");
+    assert_eq!(format_ordinal(101, Lang::En), "101st");
+    assert_eq!(format_number(-1234567.0, 0, Lang::Es), "-1.234.567");
+    assert_eq!(TemplateHelpers::docs_markdown(), include_str!("../../docs/template_helpers.md"));
+  }
+}
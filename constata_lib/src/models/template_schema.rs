@@ -1,5 +1,8 @@
-use i18n::Lang; 
+use i18n::Lang;
+use std::collections::HashMap;
+use chrono::{NaiveDate, Datelike};
 
+/* Schemas saved before typed fields existed have none of the new attributes, they're all plain optional text. */
 #[derive(Debug, juniper::GraphQLObject, serde::Deserialize, serde::Serialize)]
 pub struct TemplateSchemaField {
   pub name: String,
@@ -9,6 +12,37 @@ pub struct TemplateSchemaField {
   pub label_es: Option<String>,
   pub help: Option<String>,
   pub sample: Option<String>,
+  #[serde(default)]
+  pub field_type: TemplateFieldType,
+  #[serde(default)]
+  pub pattern: Option<String>,
+  #[serde(default)]
+  pub max_length: Option<i32>,
+  #[serde(default)]
+  pub allowed_values: Option<Vec<String>>,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, juniper::GraphQLEnum, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateFieldType {
+  #[default]
+  Text,
+  Email,
+  /* Dates are given as YYYY-MM-DD and shown in the recipient's language. */
+  Date,
+  Url,
+  Number,
+  /* One of the field's allowed_values. */
+  Enum,
+  /* An http(s) url or a data url for an image. */
+  Image,
+}
+
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct SchemaViolation {
+  pub row: usize,
+  pub field: String,
+  pub message: String,
 }
 
 impl TemplateSchemaField {
@@ -21,6 +55,10 @@ impl TemplateSchemaField {
       label_es: Some(label_es),
       help: None,
       sample: None,
+      field_type: TemplateFieldType::Text,
+      pattern: None,
+      max_length: None,
+      allowed_values: None,
     }
   }
 
@@ -31,6 +69,179 @@ impl TemplateSchemaField {
     };
     local.or(self.label.as_deref())
   }
+
+  /* Checks the field definition itself, so broken patterns are caught when saving the template. */
+  pub fn validate_definition(&self) -> Result<(), String> {
+    if let Some(pattern) = &self.pattern {
+      regex::Regex::new(pattern).map_err(|_| format!("invalid_pattern_for_{}", self.name))?;
+    }
+
+    if self.field_type == TemplateFieldType::Enum && self.allowed_values.as_ref().map(|v| v.is_empty()).unwrap_or(true) {
+      return Err(format!("missing_allowed_values_for_{}", self.name));
+    }
+
+    Ok(())
+  }
+
+  /* Plain text fields only need to be present, like they did before typed fields existed,
+   * so existing CSVs with blank required cells keep working. Typed fields can't be left blank if required. */
+  pub fn is_typed(&self) -> bool {
+    self.field_type != TemplateFieldType::Text || self.pattern.is_some() || self.max_length.is_some() || self.allowed_values.is_some()
+  }
+
+  pub fn validate_value(&self, value: &str) -> Result<(), &'static str> {
+    if value.is_empty() {
+      return if self.optional || !self.is_typed() { Ok(()) } else { Err("cannot_be_empty") };
+    }
+
+    if let Some(max) = self.max_length {
+      if value.chars().count() > max as usize {
+        return Err("too_long");
+      }
+    }
+
+    if let Some(pattern) = &self.pattern {
+      let full = regex::Regex::new(&format!("^(?:{pattern})$")).map_err(|_| "invalid_pattern")?;
+      if !full.is_match(value) {
+        return Err("does_not_match_pattern");
+      }
+    }
+
+    if let Some(allowed) = &self.allowed_values {
+      if !allowed.iter().any(|a| a == value) {
+        return Err("not_an_allowed_value");
+      }
+    }
+
+    let valid = match self.field_type {
+      TemplateFieldType::Text | TemplateFieldType::Enum => true,
+      TemplateFieldType::Email => validator::validate_email(value),
+      TemplateFieldType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+      TemplateFieldType::Url => validator::validate_url(value),
+      TemplateFieldType::Number => value.parse::<f64>().map(|n| n.is_finite()).unwrap_or(false),
+      TemplateFieldType::Image => value.starts_with("data:image/") ||
+        (validator::validate_url(value) && (value.starts_with("https://") || value.starts_with("http://"))),
+    };
+
+    if !valid {
+      return Err(match self.field_type {
+        TemplateFieldType::Email => "not_an_email",
+        TemplateFieldType::Date => "not_a_date",
+        TemplateFieldType::Url => "not_a_url",
+        TemplateFieldType::Number => "not_a_number",
+        _ => "not_an_image",
+      });
+    }
+
+    Ok(())
+  }
+
+  /* How a valid value is shown in certificates, only dates are localized for now. */
+  pub fn localize_value(&self, value: &str, lang: Lang) -> String {
+    if self.field_type != TemplateFieldType::Date {
+      return value.to_string();
+    }
+
+    let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") else { return value.to_string() };
+    localize_date(date, lang)
+  }
+}
+
+pub fn localize_date(date: NaiveDate, lang: Lang) -> String {
+  let months = match lang {
+    Lang::En => ["January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December"],
+    Lang::Es => ["enero", "febrero", "marzo", "abril", "mayo", "junio", "julio", "agosto", "septiembre", "octubre", "noviembre", "diciembre"],
+  };
+  let month = months[date.month0() as usize];
+
+  match lang {
+    Lang::En => format!("{} {}, {}", month, date.day(), date.year()),
+    Lang::Es => format!("{} de {} de {}", date.day(), month, date.year()),
+  }
 }
 
 pub type TemplateSchema = Vec<TemplateSchemaField>;
+
+pub fn validate_rows(schema: &TemplateSchema, rows: &[HashMap<String,String>]) -> Vec<SchemaViolation> {
+  let mut violations = vec![];
+
+  for (row, params) in rows.iter().enumerate() {
+    for field in schema {
+      let message = match params.get(&field.name) {
+        None if !field.optional => Some("missing".to_string()),
+        None => None,
+        Some(value) => field.validate_value(value).err().map(|e| e.to_string()),
+      };
+
+      if let Some(message) = message {
+        violations.push(SchemaViolation{ row, field: field.name.clone(), message });
+      }
+    }
+  }
+
+  violations
+}
+
+/* The params used to render a template, with localized values and the raw ones available with a _raw suffix. */
+pub fn localize_params(schema: &TemplateSchema, params: &HashMap<String,String>, lang: Lang) -> HashMap<String,String> {
+  let mut localized = params.clone();
+
+  for field in schema.iter().filter(|f| f.field_type == TemplateFieldType::Date) {
+    if let Some(value) = params.get(&field.name) {
+      localized.insert(format!("{}_raw", field.name), value.clone());
+      localized.insert(field.name.clone(), field.localize_value(value, lang));
+    }
+  }
+
+  localized
+}
+
+describe! {
+  test!{ validates_typed_fields
+    let field = |json: serde_json::Value| -> TemplateSchemaField { serde_json::from_value(json).unwrap() };
+
+    let legacy = field(serde_json::json!({"name": "name", "optional": false, "common": false}));
+    assert_eq!(legacy.field_type, TemplateFieldType::Text);
+    assert_eq!(legacy.validate_value("anything goes"), Ok(()));
+    assert_eq!(legacy.validate_value(""), Ok(()));
+
+    let email = field(serde_json::json!({"name": "email", "optional": true, "common": false, "field_type": "email"}));
+    assert_eq!(email.validate_value(""), Ok(()));
+    assert_eq!(email.validate_value("kenny@cc.com"), Ok(()));
+    assert_eq!(email.validate_value("kenny"), Err("not_an_email"));
+
+    let date = field(serde_json::json!({"name": "date", "optional": false, "common": true, "field_type": "date"}));
+    assert_eq!(date.validate_value("1999-03-03"), Ok(()));
+    assert_eq!(date.validate_value("3 de marzo de 1999"), Err("not_a_date"));
+    assert_eq!(date.validate_value(""), Err("cannot_be_empty"));
+    assert_eq!(date.localize_value("1999-03-03", Lang::Es), "3 de marzo de 1999");
+    assert_eq!(date.localize_value("1999-03-03", Lang::En), "March 3, 1999");
+
+    let grade = field(serde_json::json!({"name": "grade", "optional": false, "common": false, "field_type": "enum", "allowed_values": ["A", "B"]}));
+    assert_eq!(grade.validate_value("A"), Ok(()));
+    assert_eq!(grade.validate_value("C"), Err("not_an_allowed_value"));
+
+    let code = field(serde_json::json!({"name": "code", "optional": false, "common": false, "pattern": "[A-Z]{2}-\\d+", "max_length": 6}));
+    assert_eq!(code.validate_value("AB-123"), Ok(()));
+    assert_eq!(code.validate_value("AB-1234"), Err("too_long"));
+    assert_eq!(code.validate_value("ab-12"), Err("does_not_match_pattern"));
+
+    let number = field(serde_json::json!({"name": "hours", "optional": false, "common": false, "field_type": "number"}));
+    assert_eq!(number.validate_value("12.5"), Ok(()));
+    assert_eq!(number.validate_value("twelve"), Err("not_a_number"));
+
+    let rows: Vec<HashMap<String,String>> = serde_json::from_value(serde_json::json!([
+      {"name": "Stan", "email": "stan@cc.com", "date": "1999-03-03", "grade": "A", "code": "AB-1", "hours": "1"},
+      {"email": "kyle", "date": "1999-03-03", "grade": "A", "code": "AB-1", "hours": "1"},
+    ]))?;
+    let schema = vec![legacy, email, date, grade, code, number];
+    assert_eq!(validate_rows(&schema, &rows), vec![
+      SchemaViolation{ row: 1, field: "name".to_string(), message: "missing".to_string() },
+      SchemaViolation{ row: 1, field: "email".to_string(), message: "not_an_email".to_string() },
+    ]);
+
+    let localized = localize_params(&schema, &rows[0], Lang::En);
+    assert_eq!(localized["date"], "March 3, 1999");
+    assert_eq!(localized["date_raw"], "1999-03-03");
+  }
+}
@@ -0,0 +1,203 @@
+/*
+ * Walks the AST of a template's tera files to find the variables they read from the entry params.
+ * Loop variables, sets and macro arguments are local and don't count.
+ * The check is lenient with scopes: a name that's local anywhere in a file is never reported for that file.
+ * Fields constata reads itself, like the recipient's email, count as used even if no file shows them.
+ */
+use super::*;
+use i18n::tera::{Tera, ast::{Node, Expr, ExprVal}};
+use std::collections::BTreeSet;
+
+const USED_BY_CONSTATA: &[&str] = &["email", "recipient_identification"];
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
+pub struct TemplateVariablesCheck {
+  pub unknown_variables: Vec<String>,
+  pub unused_required_fields: Vec<String>,
+}
+
+impl TemplateVariablesCheck {
+  pub fn check(schema: &TemplateSchema, files: &[(String, Vec<u8>)], custom_message: Option<&str>) -> ConstataResult<Self> {
+    let mut tera = Tera::default();
+    let mut names = vec![];
+
+    for (raw_name, bytes) in files {
+      if Template::is_tera(raw_name).is_some() {
+        let utf8 = std::str::from_utf8(bytes).map_err(|_| Error::validation("payload", "invalid_tera_syntax"))?;
+        tera.add_raw_template(raw_name, utf8).map_err(|_| Error::validation("payload", "invalid_tera_syntax"))?;
+        names.push(raw_name.clone());
+      }
+    }
+
+    if let Some(message) = custom_message {
+      tera.add_raw_template("__custom_message", message).map_err(|_| Error::validation("custom_message", "invalid_tera_syntax"))?;
+      names.push("__custom_message".to_string());
+    }
+
+    let mut referenced = BTreeSet::new();
+    for name in &names {
+      let template = tera.get_template(name).map_err(|_| Error::validation("payload", "invalid_tera_syntax"))?;
+      let mut found = Found::default();
+      found.nodes(&template.ast);
+      referenced.extend(found.used.difference(&found.locals).cloned());
+    }
+
+    let mut known: BTreeSet<String> = schema.iter().map(|f| f.name.clone()).collect();
+    for field in schema.iter().filter(|f| f.field_type == TemplateFieldType::Date) {
+      known.insert(format!("{}_raw", field.name));
+    }
+
+    Ok(Self {
+      unknown_variables: referenced.iter().filter(|v| !known.contains(*v)).cloned().collect(),
+      unused_required_fields: schema.iter()
+        .filter(|f| !f.optional && !referenced.contains(&f.name) && !USED_BY_CONSTATA.contains(&f.name.as_str()))
+        .map(|f| f.name.clone())
+        .collect(),
+    })
+  }
+
+  pub fn is_ok(&self) -> bool {
+    self.unknown_variables.is_empty() && self.unused_required_fields.is_empty()
+  }
+
+  pub fn message(&self) -> String {
+    let mut parts = vec![];
+    if !self.unknown_variables.is_empty() {
+      parts.push(format!("unknown_variables: {}", self.unknown_variables.join(", ")));
+    }
+    if !self.unused_required_fields.is_empty() {
+      parts.push(format!("unused_required_fields: {}", self.unused_required_fields.join(", ")));
+    }
+    parts.join("; ")
+  }
+}
+
+#[derive(Default)]
+struct Found {
+  used: BTreeSet<String>,
+  locals: BTreeSet<String>,
+}
+
+impl Found {
+  fn nodes(&mut self, nodes: &[Node]) {
+    for node in nodes {
+      self.node(node);
+    }
+  }
+
+  fn node(&mut self, node: &Node) {
+    match node {
+      Node::VariableBlock(_, e) => self.expr(e),
+      Node::Set(_, set) => {
+        self.locals.insert(set.key.clone());
+        self.expr(&set.value);
+      },
+      Node::FilterSection(_, section, _) => {
+        self.args(section.filter.args.values());
+        self.nodes(&section.body);
+      },
+      Node::Block(_, block, _) => self.nodes(&block.body),
+      Node::Forloop(_, forloop, _) => {
+        self.locals.insert("loop".to_string());
+        self.locals.insert(forloop.value.clone());
+        if let Some(key) = &forloop.key {
+          self.locals.insert(key.clone());
+        }
+        self.expr(&forloop.container);
+        self.nodes(&forloop.body);
+        if let Some(empty) = &forloop.empty_body {
+          self.nodes(empty);
+        }
+      },
+      Node::If(conditional, _) => {
+        for (_, e, body) in &conditional.conditions {
+          self.expr(e);
+          self.nodes(body);
+        }
+        if let Some((_, body)) = &conditional.otherwise {
+          self.nodes(body);
+        }
+      },
+      Node::MacroDefinition(_, definition, _) => {
+        self.locals.extend(definition.args.keys().cloned());
+        self.nodes(&definition.body);
+      },
+      _ => {},
+    }
+  }
+
+  fn args<'a>(&mut self, args: impl Iterator<Item = &'a Expr>) {
+    for e in args {
+      self.expr(e);
+    }
+  }
+
+  fn expr(&mut self, e: &Expr) {
+    self.val(&e.val);
+    for filter in &e.filters {
+      self.args(filter.args.values());
+    }
+  }
+
+  fn val(&mut self, val: &ExprVal) {
+    match val {
+      ExprVal::Ident(ident) => {
+        let root = ident.split(|c| c == '.' || c == '[').next().unwrap_or(ident);
+        if root != "__tera_context" {
+          self.used.insert(root.to_string());
+        }
+      },
+      ExprVal::Math(m) => { self.expr(&m.lhs); self.expr(&m.rhs); },
+      ExprVal::Logic(l) => { self.expr(&l.lhs); self.expr(&l.rhs); },
+      ExprVal::Test(t) => {
+        self.val(&ExprVal::Ident(t.ident.clone()));
+        self.args(t.args.iter());
+      },
+      ExprVal::MacroCall(m) => self.args(m.args.values()),
+      ExprVal::FunctionCall(f) => self.args(f.args.values()),
+      ExprVal::Array(items) => self.args(items.iter()),
+      ExprVal::StringConcat(concat) => {
+        for v in &concat.values {
+          self.val(v);
+        }
+      },
+      ExprVal::In(i) => { self.expr(&i.lhs); self.expr(&i.rhs); },
+      _ => {},
+    }
+  }
+}
+
+impl Template {
+  pub async fn check_variables(&self) -> ConstataResult<TemplateVariablesCheck> {
+    let files = Template::read_name_and_bytes_from_payload(&self.payload().await?).await?;
+    TemplateVariablesCheck::check(&self.parsed_schema()?, &files, self.custom_message().as_deref())
+  }
+}
+
+describe! {
+  test!{ finds_unknown_variables_and_unused_required_fields
+    let schema: TemplateSchema = serde_json::from_value(serde_json::json!([
+      {"name": "student_name", "optional": false, "common": false},
+      {"name": "course", "optional": false, "common": true},
+      {"name": "grades", "optional": true, "common": false},
+      {"name": "date", "optional": true, "common": true, "field_type": "date"},
+      {"name": "email", "optional": false, "common": false, "field_type": "email"},
+    ]))?;
+
+    let files = vec![
+      ("diploma.html.tera".to_string(), br#"
+        Hello {{ studnet_name | upper }}, on {{ date }} ({{ date_raw }}).
+        {% set total = 0 %}
+        {% for grade in grades | split(pat=",") %}{{ loop.index }}: {{ grade }} {{ total }}{% endfor %}
+        {% if honors is defined and honors %}With honors{% endif %}
+      "#.to_vec()),
+      ("logo.png".to_string(), b"{{ not_a_template }}".to_vec()),
+    ];
+
+    let check = TemplateVariablesCheck::check(&schema, &files, Some("Hi {{ student_name }}"))?;
+    assert_eq!(check.unknown_variables, vec!["honors".to_string(), "studnet_name".to_string()]);
+    assert_eq!(check.unused_required_fields, vec!["course".to_string()]);
+    assert!(!check.is_ok());
+    assert_eq!(check.message(), "unknown_variables: honors, studnet_name; unused_required_fields: course");
+  }
+}
@@ -0,0 +1,193 @@
+/*
+ * Every save of a template creates an immutable TemplateVersion with its schema, messages and zip payload.
+ * Issuances pin the version they were created with, so later edits never change how their entries render.
+ * Versions migrated from before versioning have no payload_hash, their payload is the template's own.
+ */
+use super::*;
+use std::collections::BTreeSet;
+
+model!{
+  state: Site,
+  table: template_versions,
+  struct TemplateVersion {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    template_id: i32,
+    #[sqlx_model_hints(int4)]
+    version_number: i32,
+    #[sqlx_model_hints(text)]
+    schema: String,
+    #[sqlx_model_hints(varchar)]
+    custom_message: Option<String>,
+    #[sqlx_model_hints(varchar)]
+    og_title_override: Option<String>,
+    #[sqlx_model_hints(varchar)]
+    payload_hash: Option<String>,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+  },
+  belongs_to {
+    Template(template_id),
+  }
+}
+
+derive_storable!(TemplateVersion, "tv");
+
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
+pub struct TemplateVersionDiff {
+  pub added_fields: Vec<String>,
+  pub removed_fields: Vec<String>,
+  pub changed_fields: Vec<String>,
+  pub custom_message_changed: bool,
+  pub og_title_override_changed: bool,
+  pub added_files: Vec<String>,
+  pub removed_files: Vec<String>,
+  pub changed_files: Vec<String>,
+}
+
+impl TemplateVersion {
+  pub async fn payload(&self) -> ConstataResult<Vec<u8>> {
+    if self.attrs.payload_hash.is_some() {
+      self.storage_fetch().await
+    } else {
+      self.template().await?.payload().await
+    }
+  }
+
+  pub fn parsed_schema(&self) -> ConstataResult<TemplateSchema> {
+    Ok(serde_json::from_str(self.schema())?)
+  }
+
+  pub async fn diff(&self, newer: &TemplateVersion) -> ConstataResult<TemplateVersionDiff> {
+    let old_schema = self.parsed_schema()?;
+    let new_schema = newer.parsed_schema()?;
+    let as_json = |f: &TemplateSchemaField| serde_json::to_string(f).unwrap_or_default();
+
+    let mut diff = TemplateVersionDiff {
+      custom_message_changed: self.attrs.custom_message != newer.attrs.custom_message,
+      og_title_override_changed: self.attrs.og_title_override != newer.attrs.og_title_override,
+      ..Default::default()
+    };
+
+    for field in &new_schema {
+      match old_schema.iter().find(|f| f.name == field.name) {
+        None => diff.added_fields.push(field.name.clone()),
+        Some(old) if as_json(old) != as_json(field) => diff.changed_fields.push(field.name.clone()),
+        _ => {},
+      }
+    }
+    diff.removed_fields = old_schema.iter()
+      .filter(|f| !new_schema.iter().any(|n| n.name == f.name))
+      .map(|f| f.name.clone())
+      .collect();
+
+    let old_files = Template::read_name_and_bytes_from_payload(&self.payload().await?).await?;
+    let new_files = Template::read_name_and_bytes_from_payload(&newer.payload().await?).await?;
+    let names = |files: &[(String, Vec<u8>)]| -> BTreeSet<String> { files.iter().map(|(n, _)| n.clone()).collect() };
+
+    diff.added_files = names(&new_files).difference(&names(&old_files)).cloned().collect();
+    diff.removed_files = names(&old_files).difference(&names(&new_files)).cloned().collect();
+    diff.changed_files = new_files.iter()
+      .filter(|(name, bytes)| old_files.iter().any(|(n, b)| n == name && b != bytes))
+      .map(|(name, _)| name.clone())
+      .collect();
+
+    Ok(diff)
+  }
+}
+
+impl Template {
+  /* Snapshots the template's current attributes as a new version.
+   * Without a new payload, the payload of the latest version is carried over. */
+  pub async fn create_version(&self, payload: Option<&[u8]>) -> ConstataResult<TemplateVersion> {
+    let latest = self.template_version_scope().order_by(TemplateVersionOrderBy::VersionNumber).desc(true).optional().await?;
+
+    let bytes = match (payload, &latest) {
+      (Some(p), _) => p.to_vec(),
+      (None, Some(l)) => l.payload().await?,
+      (None, None) => self.payload().await?,
+    };
+
+    let version = self.state.template_version().insert(InsertTemplateVersion{
+      template_id: self.attrs.id,
+      version_number: latest.map(|l| l.attrs.version_number + 1).unwrap_or(1),
+      schema: self.attrs.schema.clone(),
+      custom_message: self.attrs.custom_message.clone(),
+      og_title_override: self.attrs.og_title_override.clone(),
+      payload_hash: Some(hexdigest(&bytes)),
+    }).save().await?;
+
+    version.storage_put(&bytes).await?;
+    Ok(version)
+  }
+
+  pub async fn latest_version(&self) -> ConstataResult<TemplateVersion> {
+    Ok(self.template_version_scope().order_by(TemplateVersionOrderBy::VersionNumber).desc(true).one().await?)
+  }
+
+  pub async fn versions(&self) -> ConstataResult<Vec<TemplateVersion>> {
+    Ok(self.template_version_scope().order_by(TemplateVersionOrderBy::VersionNumber).all().await?)
+  }
+
+  pub async fn find_version(&self, version_number: i32) -> ConstataResult<TemplateVersion> {
+    Ok(self.template_version_scope().version_number_eq(&version_number).one().await?)
+  }
+
+  /* Rolling back never rewrites history, the old version's contents become a new latest version. */
+  pub async fn rollback_to(self, version_number: i32) -> ConstataResult<(Template, TemplateVersion)> {
+    let target = self.find_version(version_number).await?;
+    let payload = target.payload().await?;
+
+    let template = self.update()
+      .schema(target.attrs.schema.clone())
+      .custom_message(target.attrs.custom_message.clone())
+      .og_title_override(target.attrs.og_title_override.clone())
+      .save().await?;
+
+    let version = template.create_version(Some(&payload)).await?;
+    Ok((template, version))
+  }
+}
+
+impl Issuance {
+  /* The version this issuance renders with. */
+  pub async fn pinned_template(&self) -> ConstataResult<TemplateVersion> {
+    match self.template_version().await? {
+      Some(version) => Ok(version),
+      None => self.template().await?.latest_version().await,
+    }
+  }
+}
+
+describe! {
+  dbtest!{ pins_template_versions_on_issuances (site, c)
+    let alice = c.alice().await;
+    let template = alice.make_template(read("template.zip")).await;
+    let first = template.latest_version().await?;
+    assert_eq!(first.attrs.version_number, 1);
+    assert_eq!(first.payload().await?, read("template.zip"));
+
+    let issuance = alice.make_issuance(template.attrs.id, read("issuance.csv")).await?;
+    assert_eq!(issuance.pinned_template().await?.attrs.id, first.attrs.id);
+
+    let edited = template.update()
+      .custom_message(Some("Hola {{ name }}, nuevo mensaje".to_string()))
+      .save().await?;
+    let second = edited.create_version(None).await?;
+    assert_eq!(second.attrs.version_number, 2);
+    assert_eq!(second.payload().await?, read("template.zip"));
+
+    let diff = first.diff(&second).await?;
+    assert!(diff.custom_message_changed);
+    assert!(diff.changed_files.is_empty() && diff.added_fields.is_empty());
+
+    let archived = edited.update().archived(true).save().await?;
+    assert_eq!(issuance.reloaded().await?.pinned_template().await?.attrs.id, first.attrs.id);
+
+    let (rolled_back, third) = archived.rollback_to(1).await?;
+    assert_eq!(third.attrs.version_number, 3);
+    assert_eq!(rolled_back.attrs.custom_message, first.attrs.custom_message);
+    assert_eq!(site.template_version().select().template_id_eq(rolled_back.id()).count().await?, 3);
+  }
+}
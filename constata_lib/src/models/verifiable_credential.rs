@@ -0,0 +1,279 @@
+/*
+ * An Open Badges 3.0 AchievementCredential for a completed Entry.
+ * The credential is a W3C Verifiable Credential (JSON-LD) built from the entry params and its template schema.
+ * Its claims are added to the entry payload as credential.json before the issuer signs it, so the issuer's signature
+ * covers them. The exported credential adds Constata's bulletin timestamp as evidence, and a second proof by
+ * Constata's key over the whole credential, the same key that signs HTML proofs.
+ * Both proofs sign the JCS (RFC 8785) canonical serialization, so verifiers can reproduce the signed bytes.
+ */
+use super::*;
+use bitcoin::{secp256k1, Address, PrivateKey};
+use serde_json::{json, Value};
+
+pub const CREDENTIAL_PROOF_TYPE: &str = "BitcoinSignedMessage2023";
+pub const CREDENTIAL_FILE: &str = "credential.json";
+
+/* Params that map to specific credential properties, every other schema field is exported as is. */
+const MAPPED_PARAMS: [&str; 6] = ["name", "email", "recipient_identification", "motive", "custom_text", "date"];
+
+pub struct VerifiableCredential {
+  pub entry: Entry,
+  pub credential: Value,
+}
+
+impl VerifiableCredential {
+  /* The claims the issuer signs along with the entry, or None if the template kind has no credentials. */
+  pub async fn claims_for(entry: &Entry, public_url: &str) -> ConstataResult<Option<Value>> {
+    let template = entry.issuance().await?.pinned_template().await?;
+    if !Self::supports_kind(template.attrs.kind) {
+      return Ok(None);
+    }
+
+    let org = entry.org().await?;
+    let params = entry.parsed_params()?;
+    let settings = &entry.state.settings;
+    let lang = entry.person().await?.attrs.lang;
+    let param = |name: &str| params.get(name).filter(|v| !v.is_empty()).cloned();
+
+    let mut identifiers = vec![];
+    if let Some(email) = param("email") {
+      let salt = hex::encode(rand::random::<[u8; 16]>());
+      identifiers.push(json!({
+        "type": "IdentityObject",
+        "identityType": "emailAddress",
+        "hashed": true,
+        "salt": salt,
+        "identityHash": format!("sha256${}", hasher::hexdigest(format!("{}{}", email.to_lowercase(), salt).as_bytes())),
+      }));
+    }
+    if let Some(identification) = param("recipient_identification") {
+      identifiers.push(json!({
+        "type": "IdentityObject",
+        "identityType": "identifier",
+        "hashed": false,
+        "identityHash": identification,
+      }));
+    }
+
+    let other_fields: Vec<Value> = template.parsed_schema()?.iter()
+      .filter(|f| !MAPPED_PARAMS.contains(&f.name.as_str()))
+      .filter_map(|f| param(&f.name).map(|value| json!({
+        "name": f.name,
+        "label": f.i18n_label(lang),
+        "value": value,
+      })))
+      .collect();
+
+    let achievement_name = entry.title().await?
+      .or_else(|| param("motive"))
+      .unwrap_or_else(|| template.attrs.name.clone());
+
+    let achievement_type = match template.attrs.kind {
+      TemplateKind::Badge => "Badge",
+      _ => "Diploma",
+    };
+
+    Ok(Some(json!({
+      "@context": [
+        "https://www.w3.org/ns/credentials/v2",
+        "https://purl.imsglobal.org/spec/ob/v3p0/context-3.0.2.json",
+      ],
+      "id": public_url,
+      "type": ["VerifiableCredential", "OpenBadgeCredential"],
+      "name": achievement_name,
+      "issuer": {
+        "id": format!("{}/issuers/{}", settings.url, org.attrs.id),
+        "type": ["Profile"],
+        "name": org.name_for_on_behalf_of().await?,
+        "image": org.attrs.logo_url,
+      },
+      "awardedDate": param("date"),
+      "credentialSubject": {
+        "type": ["AchievementSubject"],
+        "name": param("name"),
+        "identifier": identifiers,
+        "achievement": {
+          "id": format!("{}/achievements/{}", settings.url, template.attrs.id),
+          "type": ["Achievement"],
+          "achievementType": achievement_type,
+          "name": achievement_name,
+          "description": param("custom_text").or_else(|| param("shared_text")),
+          "criteria": { "narrative": param("shared_text").unwrap_or_else(|| achievement_name.clone()) },
+        },
+        "constataFields": other_fields,
+      },
+    })))
+  }
+
+  pub async fn for_entry(entry: &Entry, key: &PrivateKey) -> ConstataResult<Self> {
+    let completed = entry.in_completed()?;
+    if entry.revocation().await?.is_some() {
+      return Err(Error::validation("entry", "entry_is_revoked"));
+    }
+    if !Self::supports_kind(entry.template_kind().await?) {
+      return Err(Error::validation("template_kind", "only_badges_and_diplomas_can_be_exported_as_credentials"));
+    }
+
+    let doc = completed.document().await?;
+    let bulletin = doc.in_accepted()?.bulletin().await?.in_published()?;
+    let base_part = doc.base_document_part().await?;
+    let issuer_signature = base_part.document_part_signature_scope().optional().await?
+      .ok_or_else(|| Error::validation("document", "entry_document_has_no_issuer_signature"))?;
+
+    let mut credential = Self::signed_claims(&entry.payload().await?)?;
+    let block_time = bulletin.as_inner().attrs.block_time.map(|t| t.to_rfc3339());
+    let signed_at = entry.attrs.completed_at.or(bulletin.as_inner().attrs.block_time)
+      .ok_or_else(|| Error::validation("entry", "entry_has_no_completion_date"))?;
+
+    credential["validFrom"] = json!(block_time);
+    credential["evidence"] = json!([{
+      "id": format!("urn:constata:document:{}", doc.attrs.id),
+      "type": ["Evidence"],
+      "name": "Constata bitcoin timestamp",
+      "genre": "Timestamp",
+      "documentHash": base_part.attrs.hash,
+      "bulletinId": bulletin.as_inner().attrs.id,
+      "bulletinHash": bulletin.hash(),
+      "transactionHash": bulletin.raw_transaction_hash(),
+      "blockHash": bulletin.as_inner().attrs.block_hash,
+      "blockTime": block_time,
+    }]);
+
+    let mut issuer_proof = json!({
+      "type": CREDENTIAL_PROOF_TYPE,
+      "proofPurpose": "assertionMethod",
+      "verificationMethod": format!("bitcoin:{}", issuer_signature.attrs.pubkey_id),
+      "proofValue": base64::encode(&issuer_signature.attrs.signature),
+      "signedDocumentHash": base_part.attrs.hash,
+      "signedFile": CREDENTIAL_FILE,
+    });
+    if let (Some(root), Some(path)) = (&issuer_signature.attrs.merkle_root, &issuer_signature.attrs.merkle_path) {
+      issuer_proof["merkleRoot"] = json!(root);
+      issuer_proof["merklePath"] = serde_json::from_str(path)?;
+    }
+
+    let constata_proof = Self::proof_for(&credential, key, entry.state.settings.network, signed_at)?;
+    credential["proof"] = json!([issuer_proof, constata_proof]);
+
+    Ok(Self { entry: entry.clone(), credential })
+  }
+
+  /* The credential.json file in an entry payload. Entries created before credentials were added to payloads have none. */
+  pub fn signed_claims(payload: &[u8]) -> ConstataResult<Value> {
+    use std::io::Read;
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(payload))?;
+    let mut file = zip.by_name(CREDENTIAL_FILE)
+      .map_err(|_| Error::validation("entry", "entry_predates_verifiable_credentials"))?;
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)?;
+    Ok(serde_json::from_slice(&bytes)?)
+  }
+
+  /* JCS (RFC 8785) serialization. Our credentials only have strings, integers, booleans and nulls,
+   * so sorting keys by their UTF-16 code units is all it takes. */
+  pub fn canonical_json(value: &Value) -> ConstataResult<String> {
+    fn sorted(value: &Value) -> Value {
+      match value {
+        Value::Object(map) => {
+          let mut keys: Vec<&String> = map.keys().collect();
+          keys.sort_by(|a, b| a.encode_utf16().cmp(b.encode_utf16()));
+          Value::Object(keys.into_iter().map(|k| (k.clone(), sorted(&map[k]))).collect())
+        },
+        Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+        other => other.clone(),
+      }
+    }
+    Ok(serde_json::to_string(&sorted(value))?)
+  }
+
+  pub fn supports_kind(kind: TemplateKind) -> bool {
+    matches!(kind, TemplateKind::Badge | TemplateKind::Diploma)
+  }
+
+  /* Constata's proof signs the canonical serialization of the credential without its proofs. */
+  pub fn proof_for(credential: &Value, key: &PrivateKey, network: bitcoin::Network, created: UtcDateTime) -> ConstataResult<Value> {
+    let message = Self::canonical_json(credential)?;
+    let signature = SignedPayload::sign_with_key(message.as_bytes(), key);
+    let address = Address::p2pkh(&key.public_key(&secp256k1::Secp256k1::new()), network);
+
+    Ok(json!({
+      "type": CREDENTIAL_PROOF_TYPE,
+      "created": created.to_rfc3339(),
+      "proofPurpose": "assertionMethod",
+      "verificationMethod": format!("bitcoin:{address}"),
+      "proofValue": signature.to_base64(),
+    }))
+  }
+
+  /* Checks Constata's proof. The issuer's proof is checked against the signed entry document, available in its proof. */
+  pub fn verify(credential: &Value) -> ConstataResult<bool> {
+    let mut unsigned = credential.clone();
+    let Some(proofs) = unsigned.as_object_mut().and_then(|o| o.remove("proof")) else { return Ok(false) };
+    let Some(proof) = proofs.as_array().and_then(|p| p.last()) else { return Ok(false) };
+
+    let address = proof["verificationMethod"].as_str().unwrap_or_default().trim_start_matches("bitcoin:");
+    let signed_payload = SignedPayload {
+      payload: Self::canonical_json(&unsigned)?.into_bytes(),
+      signer: address.parse().map_err(|_| Error::validation("verificationMethod", "not_a_bitcoin_address"))?,
+      signature: proof["proofValue"].as_str().unwrap_or_default().parse()
+        .map_err(|_| Error::validation("proofValue", "not_a_valid_signature"))?,
+    };
+
+    signed_payload.signed_ok()
+  }
+
+  pub fn filename(&self) -> String {
+    format!("credential_{}.json", self.entry.attrs.id)
+  }
+
+  pub fn to_json(&self) -> ConstataResult<String> {
+    Ok(serde_json::to_string_pretty(&self.credential)?)
+  }
+}
+
+impl Entry {
+  /* Revoked entries no longer have a credential, so no one keeps presenting a signed one. */
+  pub async fn has_verifiable_credential(&self) -> ConstataResult<bool> {
+    Ok(
+      self.is_completed() &&
+      VerifiableCredential::supports_kind(self.template_kind().await?) &&
+      self.revocation().await?.is_none()
+    )
+  }
+
+  pub async fn verifiable_credential(&self, key: &PrivateKey) -> ConstataResult<VerifiableCredential> {
+    VerifiableCredential::for_entry(self, key).await
+  }
+}
+
+describe! {
+  regtest!{ exports_completed_entries_as_open_badges (site, c, mut chain)
+    let alice = c.alice().await;
+    let entry = alice.make_entry_and_sign_it().await;
+    let key = TestBlockchain::default_private_key().await?;
+
+    assert!(matches!(entry.verifiable_credential(&key).await, Err(Error::InvalidFlowState)));
+
+    chain.fund_signer_wallet();
+    chain.simulate_stamping().await;
+    site.issuance().try_complete().await?;
+
+    let vc = entry.reloaded().await?.verifiable_credential(&key).await?;
+    assert_eq!(vc.credential["type"][1], "OpenBadgeCredential");
+    assert_eq!(vc.credential["credentialSubject"]["achievement"]["achievementType"], "Diploma");
+    assert!(vc.credential["evidence"][0]["transactionHash"].is_string());
+    assert!(VerifiableCredential::verify(&vc.credential)?);
+    assert_eq!(vc.credential, entry.reloaded().await?.verifiable_credential(&key).await?.credential);
+
+    let claims = VerifiableCredential::signed_claims(&entry.payload().await?)?;
+    assert_eq!(claims["credentialSubject"], vc.credential["credentialSubject"]);
+    assert_eq!(vc.credential["proof"][0]["verificationMethod"], json!(format!("bitcoin:{}", alice.person().await.pubkey().await?.unwrap().attrs.id)));
+
+    let json = VerifiableCredential::canonical_json(&json!({"b": 1, "a": {"d": true, "c": null}}))?;
+    assert_eq!(json, r#"{"a":{"c":null,"d":true},"b":1}"#);
+
+    let mut tampered = vc.credential.clone();
+    tampered["credentialSubject"]["name"] = json!("Mallory");
+    assert!(!VerifiableCredential::verify(&tampered)?);
+  }
+}
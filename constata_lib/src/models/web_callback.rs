@@ -1,6 +1,7 @@
 use super::{
   *,
   attestation::Attestation,
+  web_callback_payload::*,
 };
 use crate::signed_payload::SignedPayload;
 use juniper::GraphQLEnum;
@@ -26,10 +27,15 @@ model!{
     created_at: UtcDateTime,
     #[sqlx_model_hints(timestamptz, default)]
     next_attempt_on: UtcDateTime,
+    #[sqlx_model_hints(varchar)]
+    document_id: Option<String>,
+    #[sqlx_model_hints(int4)]
+    web_callback_endpoint_id: Option<i32>,
   },
   belongs_to {
     Org(org_id),
     WebCallbackAttempt(last_attempt_id),
+    WebCallbackEndpoint(web_callback_endpoint_id),
   },
   has_many {
     WebCallbackAttempt(web_callback_id),
@@ -54,10 +60,13 @@ model!{
     result_code: WebCallbackResultCode,
     #[sqlx_model_hints(varchar)]
     result_text: String,
+    #[sqlx_model_hints(int4)]
+    web_callback_endpoint_id: Option<i32>,
   },
   belongs_to {
     Org(org_id),
     WebCallback(web_callback_id),
+    WebCallbackEndpoint(web_callback_endpoint_id),
   },
 }
 
@@ -81,6 +90,25 @@ impl sqlx::postgres::PgHasArrayType for WebCallbackState {
 #[serde(rename_all= "SCREAMING_SNAKE_CASE")]
 pub enum WebCallbackKind {
   AttestationDone,
+  AttestationSealed,
+  AttestationCosigningFailed,
+  EntryRevoked,
+  IssuanceCreated,
+  IssuanceCompleted,
+  EntrySigned,
+  EntryCompleted,
+  EntryEmailSent,
+  DocumentParked,
+  DocumentAccepted,
+  InvoicePaid,
+  TokenBalanceLow,
+}
+
+impl WebCallbackKind {
+  /* Sent along with every payload, so integrators can tell which schema they got. See web_callback_payload. */
+  pub fn payload_version(&self) -> i32 {
+    1
+  }
 }
 
 impl sqlx::postgres::PgHasArrayType for WebCallbackKind {
@@ -97,6 +125,7 @@ pub enum WebCallbackResultCode {
   NetworkError,
   NoCallbacksUrlForOrg,
   NonSuccessResponse,
+  EndpointDisabled,
 }
 
 impl sqlx::postgres::PgHasArrayType for WebCallbackResultCode {
@@ -106,30 +135,81 @@ impl sqlx::postgres::PgHasArrayType for WebCallbackResultCode {
 }
 
 impl WebCallbackHub {
-  pub async fn schedule_attestation_done(&self, attestation: &Attestation) -> ConstataResult<Option<Pending>> {
-    let org = attestation.org().await?;
-    if org.web_callbacks_url().is_none() {
-      return Ok(None);
-    }
+  pub async fn schedule_attestation_done(&self, attestation: &Attestation) -> ConstataResult<Vec<Pending>> {
+    self.schedule_for_attestation(WebCallbackKind::AttestationDone, attestation).await
+  }
 
-    let existing = self.state.web_callback().select()
-      .org_id_eq(&org.attrs.id)
-      .kind_eq(&WebCallbackKind::AttestationDone)
-      .resource_id_eq(&attestation.attrs.id)
-      .state_eq(&WebCallbackState::Pending)
-      .optional().await?;
+  pub async fn schedule_attestation_sealed(&self, attestation: &Attestation) -> ConstataResult<Vec<Pending>> {
+    self.schedule_for_attestation(WebCallbackKind::AttestationSealed, attestation).await
+  }
 
-    let callback = if let Some(callback) = existing {
-      callback
-    } else {
-      self.insert(InsertWebCallback{
-        org_id: org.attrs.id,
-        kind: WebCallbackKind::AttestationDone,
-        resource_id: attestation.attrs.id,
-      }).save().await?
-    };
+  pub async fn schedule_attestation_cosigning_failed(&self, attestation: &Attestation) -> ConstataResult<Vec<Pending>> {
+    self.schedule_for_attestation(WebCallbackKind::AttestationCosigningFailed, attestation).await
+  }
 
-    Ok(callback.in_pending().ok())
+  pub async fn schedule_entry_revoked(&self, revocation: &EntryRevocation) -> ConstataResult<Vec<Pending>> {
+    self.schedule(WebCallbackKind::EntryRevoked, &revocation.org().await?, revocation.attrs.id, None).await
+  }
+
+  pub async fn schedule_issuance(&self, kind: WebCallbackKind, issuance: &Issuance) -> ConstataResult<Vec<Pending>> {
+    self.schedule(kind, &issuance.org().await?, issuance.attrs.id, None).await
+  }
+
+  pub async fn schedule_entry(&self, kind: WebCallbackKind, entry: &Entry) -> ConstataResult<Vec<Pending>> {
+    self.schedule(kind, &entry.org().await?, entry.attrs.id, None).await
+  }
+
+  /* Document ids are not integers, so the story is the resource and the document is stored on its own. */
+  pub async fn schedule_document(&self, kind: WebCallbackKind, document: &Document) -> ConstataResult<Vec<Pending>> {
+    self.schedule(kind, &document.org().await?, document.attrs.story_id, Some(document.attrs.id.clone())).await
+  }
+
+  pub async fn schedule_invoice_paid(&self, invoice: &Invoice) -> ConstataResult<Vec<Pending>> {
+    self.schedule(WebCallbackKind::InvoicePaid, &invoice.org().await?, invoice.attrs.id, None).await
+  }
+
+  pub async fn schedule_token_balance_low(&self, org: &Org) -> ConstataResult<Vec<Pending>> {
+    self.schedule(WebCallbackKind::TokenBalanceLow, org, org.attrs.id, None).await
+  }
+
+  async fn schedule_for_attestation(&self, kind: WebCallbackKind, attestation: &Attestation) -> ConstataResult<Vec<Pending>> {
+    self.schedule(kind, &attestation.org().await?, attestation.attrs.id, None).await
+  }
+
+  /* Each endpoint subscribed to this kind of event gets its own callback, with its own attempts. */
+  async fn schedule(&self, kind: WebCallbackKind, org: &Org, resource_id: i32, document_id: Option<String>) -> ConstataResult<Vec<Pending>> {
+    let mut scheduled = vec![];
+
+    for endpoint in self.state.web_callback_endpoint().subscribed_to(org.attrs.id, kind).await? {
+      let mut existing = self.state.web_callback().select()
+        .org_id_eq(&org.attrs.id)
+        .kind_eq(&kind)
+        .resource_id_eq(&resource_id)
+        .web_callback_endpoint_id_eq(&endpoint.attrs.id)
+        .state_eq(&WebCallbackState::Pending);
+
+      if let Some(id) = document_id.as_ref() {
+        existing = existing.document_id_eq(id);
+      }
+
+      let callback = if let Some(callback) = existing.optional().await? {
+        callback
+      } else {
+        self.insert(InsertWebCallback{
+          org_id: org.attrs.id,
+          kind,
+          resource_id,
+          document_id: document_id.clone(),
+          web_callback_endpoint_id: Some(endpoint.attrs.id),
+        }).save().await?
+      };
+
+      if let Ok(pending) = callback.in_pending() {
+        scheduled.push(pending);
+      }
+    }
+
+    Ok(scheduled)
   }
 }
 
@@ -147,12 +227,10 @@ impl WebCallback {
       .map(|o| o.private_key )
       .ok_or_else(|| Error::Internal("No key for signinig callbacks".to_string()))?;
 
-    let content = match self.kind() {
-      kind @ WebCallbackKind::AttestationDone => {
-        let attestation = self.state.attestation().select().org_id_eq(self.org_id()).id_eq(self.resource_id()).one().await?;
-        let resource = WebCallbackResource::AttestationDone(super::attestation::for_api::from_model(attestation).await?);
-        WebCallbackContent{ kind: *kind, resource }
-      },
+    let content = WebCallbackContent{
+      kind: *self.kind(),
+      version: self.kind().payload_version(),
+      resource: self.resource().await?,
     };
 
     let signed_payload = SignedPayload::create(
@@ -163,6 +241,48 @@ impl WebCallback {
 
     Ok(serde_json::to_string(&signed_payload)?)
   }
+
+  async fn resource(&self) -> ConstataResult<WebCallbackResource> {
+    let s = &self.state;
+    let org_id = self.org_id();
+    let id = self.resource_id();
+
+    let attestation = || async move { s.attestation().select().org_id_eq(org_id).id_eq(id).one().await };
+    let issuance = || async move { s.issuance().select().org_id_eq(org_id).id_eq(id).one().await };
+    let entry = || async move { s.entry().select().org_id_eq(org_id).id_eq(id).one().await };
+    let document = || async move {
+      let document_id = self.document_id().clone().unwrap_or_default();
+      s.document().select().org_id_eq(org_id).id_eq(&document_id).one().await
+    };
+
+    Ok(match self.kind() {
+      WebCallbackKind::AttestationDone =>
+        WebCallbackResource::AttestationDone(super::attestation::for_api::from_model(attestation().await?).await?),
+      WebCallbackKind::AttestationSealed =>
+        WebCallbackResource::AttestationSealed(super::attestation::for_api::from_model(attestation().await?).await?),
+      WebCallbackKind::AttestationCosigningFailed =>
+        WebCallbackResource::AttestationCosigningFailed(super::attestation::for_api::from_model(attestation().await?).await?),
+      WebCallbackKind::EntryRevoked => {
+        let revocation = s.entry_revocation().select().org_id_eq(org_id).id_eq(id).one().await?;
+        WebCallbackResource::EntryRevoked(revocation.status().await?)
+      },
+      WebCallbackKind::IssuanceCreated => WebCallbackResource::IssuanceCreated(IssuanceEvent::from_model(&issuance().await?).await?),
+      WebCallbackKind::IssuanceCompleted => WebCallbackResource::IssuanceCompleted(IssuanceEvent::from_model(&issuance().await?).await?),
+      WebCallbackKind::EntrySigned => WebCallbackResource::EntrySigned(EntryEvent::from_model(&entry().await?).await?),
+      WebCallbackKind::EntryCompleted => WebCallbackResource::EntryCompleted(EntryEvent::from_model(&entry().await?).await?),
+      WebCallbackKind::EntryEmailSent => WebCallbackResource::EntryEmailSent(EntryEvent::from_model(&entry().await?).await?),
+      WebCallbackKind::DocumentParked => WebCallbackResource::DocumentParked(DocumentEvent::from_model(&document().await?).await?),
+      WebCallbackKind::DocumentAccepted => WebCallbackResource::DocumentAccepted(DocumentEvent::from_model(&document().await?).await?),
+      WebCallbackKind::InvoicePaid => {
+        let invoice = s.invoice().select().org_id_eq(org_id).id_eq(id).one().await?;
+        WebCallbackResource::InvoicePaid(InvoiceEvent::from_model(&invoice))
+      },
+      WebCallbackKind::TokenBalanceLow => {
+        let account_state = s.org().find(org_id).await?.account_state().await?;
+        WebCallbackResource::TokenBalanceLow(TokenBalanceEvent::from_model(&account_state))
+      },
+    })
+  }
 }
 
 #[duplicate_item(
@@ -236,6 +356,9 @@ impl Flow {
 }
 
 impl WebCallbackHub {
+  /* Orgs are told once when a funding brings their balance below this many tokens. */
+  pub const LOW_TOKEN_BALANCE: Decimal = rust_decimal_macros::dec!(10);
+
   pub async fn attempt_all_pending(&self) -> ConstataResult<()> {
     let all = self.select()
       .state_eq(WebCallbackState::Pending)
@@ -254,27 +377,53 @@ impl WebCallbackHub {
 #[derive(Serialize, Deserialize)]
 pub struct WebCallbackContent {
   kind: WebCallbackKind,
+  version: i32,
   resource: WebCallbackResource,
 }
 #[derive(Serialize, Deserialize)]
 enum WebCallbackResource {
   AttestationDone(super::attestation::for_api::Attestation),
+  AttestationSealed(super::attestation::for_api::Attestation),
+  AttestationCosigningFailed(super::attestation::for_api::Attestation),
+  EntryRevoked(RevocationStatus),
+  IssuanceCreated(IssuanceEvent),
+  IssuanceCompleted(IssuanceEvent),
+  EntrySigned(EntryEvent),
+  EntryCompleted(EntryEvent),
+  EntryEmailSent(EntryEvent),
+  DocumentParked(DocumentEvent),
+  DocumentAccepted(DocumentEvent),
+  InvoicePaid(InvoiceEvent),
+  TokenBalanceLow(TokenBalanceEvent),
 }
 
 impl Pending {
-  async fn attempt(self) -> ConstataResult<WebCallbackAttempt> {
-    let Some(url) = self.as_inner().org().await?.attrs.web_callbacks_url else {
-      return self.create_attempt("", WebCallbackResultCode::NoCallbacksUrlForOrg, String::new()).await;
+  pub async fn attempt(self) -> ConstataResult<WebCallbackAttempt> {
+    let Some(endpoint) = self.as_inner().web_callback_endpoint().await? else {
+      return self.create_attempt(None, "", WebCallbackResultCode::NoCallbacksUrlForOrg, String::new()).await;
     };
 
+    if !endpoint.attrs.enabled {
+      let attempt = self.create_attempt(Some(&endpoint), "", WebCallbackResultCode::EndpointDisabled, String::new()).await?;
+      self.into_inner().update()
+        .last_attempt_id(Some(*attempt.id()))
+        .state(WebCallbackState::Failed)
+        .save().await?;
+      return Ok(attempt);
+    }
+
+    let url = endpoint.attrs.url.clone();
+    let body = self.as_inner().request_body().await?;
     let result = ureq::post(&url)
       .timeout(std::time::Duration::new(5,0))
       .set("Content-Type", "application/json")
-      .send_string(&self.as_inner().request_body().await?);
+      .set("Constata-Web-Callback-Id", &self.id().to_string())
+      .set(WebCallbackEndpointHub::SIGNATURE_HEADER, &endpoint.signature_header(Utc::now().timestamp(), &body)?)
+      .send_string(&body);
 
     match result {
       Ok(response) => {
-        let attempt = self.create_attempt(&url, WebCallbackResultCode::Ok, Self::body_of(response)).await?;
+        let attempt = self.create_attempt(Some(&endpoint), &url, WebCallbackResultCode::Ok, Self::body_of(response)).await?;
         self.into_inner().update().state(WebCallbackState::Done).save().await?;
         return Ok(attempt);
       },
@@ -287,7 +436,7 @@ impl Pending {
           ureq::Error::Transport(t) => (WebCallbackResultCode::NetworkError, t.to_string()),
         };
 
-        let attempt = self.create_attempt(&url, code, text).await?;
+        let attempt = self.create_attempt(Some(&endpoint), &url, code, text).await?;
         let inner = self.into_inner();
         let attempts = inner.web_callback_attempt_scope().count().await?;
         let mut update = inner.update().last_attempt_id(Some(*attempt.id()));
@@ -308,7 +457,12 @@ impl Pending {
     x.into_string().unwrap_or_else(|_| "non_utf8_body".to_string())
   }
 
-  async fn create_attempt(&self, url: &str, result_code: WebCallbackResultCode, mut result_text: String) -> ConstataResult<WebCallbackAttempt> {
+  async fn create_attempt(&self,
+    endpoint: Option<&WebCallbackEndpoint>,
+    url: &str,
+    result_code: WebCallbackResultCode,
+    mut result_text: String
+  ) -> ConstataResult<WebCallbackAttempt> {
     result_text.truncate(5000);
     Ok(self.site().web_callback_attempt().insert(InsertWebCallbackAttempt{
       org_id: *self.org_id(),
@@ -317,6 +471,7 @@ impl Pending {
       url: url.to_string(),
       result_code,
       result_text,
+      web_callback_endpoint_id: endpoint.map(|e| e.attrs.id),
     }).save().await?)
   }
 }
@@ -325,10 +480,9 @@ impl Pending {
 describe! {
   regtest!{ sends_callback_for_attestation_done (site, c, mut chain)
     let alice = c.alice().await;
-    let org = alice.org().await
-      .update()
-      .web_callbacks_url(Some("http://127.0.0.1:1234/callbacks_url".to_string()))
-      .save().await?;
+    let org = alice.org().await;
+    site.web_callback_endpoint()
+      .create(&org, "http://127.0.0.1:1234/callbacks_url", &[WebCallbackKind::AttestationDone], false, true).await?;
 
     let payloads = vec![
       alice.signed_payload(b"hello world"),
@@ -368,9 +522,8 @@ describe! {
 
   regtest!{ attempts_several_times_and_fails (site, c, mut chain)
     let alice = c.alice().await;
-    alice.org().await.update()
-      .web_callbacks_url(Some("http://127.0.0.1:1234/callbacks_url".to_string()))
-      .save().await?;
+    site.web_callback_endpoint()
+      .create(&alice.org().await, "http://127.0.0.1:1234/callbacks_url", &[WebCallbackKind::AttestationDone], false, true).await?;
 
     let payloads = vec![ alice.signed_payload(b"hello world") ];
     site.attestation().create(&alice.person().await, &payloads, None, None, None, vec![]).await?;
@@ -408,7 +561,7 @@ describe! {
     mock.assert();
   }
 
-  regtest!{ does_not_create_if_organization_has_no_callbacks_url (site, c, mut chain)
+  regtest!{ does_not_create_if_organization_has_no_endpoints (site, c, mut chain)
     let alice = c.alice().await;
     let payloads = vec![alice.signed_payload(b"hello world")];
     site.attestation().create(&alice.person().await, &payloads, None, None, None, vec![]).await?;
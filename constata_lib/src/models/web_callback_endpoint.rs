@@ -0,0 +1,314 @@
+/*
+ * The URLs where an org wants to receive web callbacks. Each endpoint gets the kinds of events it's
+ * subscribed to, or all of them if it's set to all_kinds. Every event becomes one WebCallback per
+ * enabled endpoint, so each endpoint has its own retries.
+ *
+ * Besides the SignedPayload body, requests carry a Constata-Signature header with an HMAC-SHA256 of
+ * the timestamp and body, made with the endpoint's secret, which is easier to check from any language.
+ * Receivers should reject timestamps older than a few minutes to prevent replays.
+ * While a secret is being rotated the header has one signature for each secret still valid.
+ */
+use super::*;
+use sha2::Sha256;
+use hmac::{Hmac, Mac, NewMac};
+
+type HmacSha256 = Hmac<Sha256>;
+
+model!{
+  state: Site,
+  table: web_callback_endpoints,
+  struct WebCallbackEndpoint {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    org_id: i32,
+    #[sqlx_model_hints(varchar)]
+    url: String,
+    #[sqlx_model_hints(boolean, default)]
+    enabled: bool,
+    #[sqlx_model_hints(boolean, default)]
+    all_kinds: bool,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+    #[sqlx_model_hints(varchar)]
+    secret: String,
+    #[sqlx_model_hints(varchar, default)]
+    previous_secret: Option<String>,
+    #[sqlx_model_hints(timestamptz, default)]
+    previous_secret_expires_at: Option<UtcDateTime>,
+  },
+  belongs_to {
+    Org(org_id),
+  },
+  has_many {
+    WebCallbackSubscription(web_callback_endpoint_id),
+  }
+}
+
+model!{
+  state: Site,
+  table: web_callback_subscriptions,
+  struct WebCallbackSubscription {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    web_callback_endpoint_id: i32,
+    #[sqlx_model_hints(web_callback_kind)]
+    kind: WebCallbackKind,
+  },
+  belongs_to {
+    WebCallbackEndpoint(web_callback_endpoint_id),
+  }
+}
+
+impl WebCallbackEndpointHub {
+  pub const SIGNATURE_HEADER: &'static str = "Constata-Signature";
+  pub const DEFAULT_ROTATION_OVERLAP_HOURS: i64 = 24;
+  pub const MAX_ROTATION_OVERLAP_HOURS: i64 = 24 * 7;
+
+  pub async fn create(&self, org: &Org, url: &str, kinds: &[WebCallbackKind], all_kinds: bool, enabled: bool)
+    -> ConstataResult<WebCallbackEndpoint>
+  {
+    Self::validate_kinds(kinds, all_kinds)?;
+
+    let endpoint = self.insert(InsertWebCallbackEndpoint{
+      org_id: org.attrs.id,
+      url: Self::validate_url(url)?,
+      secret: Self::make_secret(),
+    }).save().await?;
+
+    let endpoint = endpoint.update().enabled(enabled).save().await?;
+    endpoint.set_kinds(kinds, all_kinds).await
+  }
+
+  pub async fn subscribed_to(&self, org_id: i32, kind: WebCallbackKind) -> ConstataResult<Vec<WebCallbackEndpoint>> {
+    let mut subscribed = vec![];
+    for endpoint in self.select().org_id_eq(&org_id).enabled_eq(&true).order_by(WebCallbackEndpointOrderBy::Id).all().await? {
+      if endpoint.is_subscribed_to(kind).await? {
+        subscribed.push(endpoint);
+      }
+    }
+    Ok(subscribed)
+  }
+
+  fn validate_url(url: &str) -> ConstataResult<String> {
+    let parsed = url::Url::parse(url).map_err(|_| Error::validation("url", "not_an_url"))?;
+    if !["http", "https"].contains(&parsed.scheme()) {
+      return Err(Error::validation("url", "must_be_http_or_https"));
+    }
+    Ok(parsed.to_string())
+  }
+
+  /* Getting every event has to be asked for, so an empty list is never taken as all of them. */
+  fn validate_kinds(kinds: &[WebCallbackKind], all_kinds: bool) -> ConstataResult<()> {
+    if all_kinds && !kinds.is_empty() {
+      return Err(Error::validation("kinds", "set_either_kinds_or_all_kinds"));
+    }
+    if !all_kinds && kinds.is_empty() {
+      return Err(Error::validation("kinds", "must_set_kinds_or_all_kinds"));
+    }
+    Ok(())
+  }
+
+  fn make_secret() -> String {
+    format!("whsec_{}", hex::encode(rand::random::<[u8; 32]>()))
+  }
+}
+
+/* The value of the Constata-Signature header, like "t=1700000000,v1=5257a869...,v1=6ffbb59b..." */
+pub fn signature_header(timestamp: i64, body: &str, secrets: &[&str]) -> ConstataResult<String> {
+  let mut header = format!("t={timestamp}");
+  for secret in secrets {
+    header.push_str(&format!(",v1={}", hex::encode(mac_for(secret, timestamp, body)?.finalize().into_bytes())));
+  }
+  Ok(header)
+}
+
+/* Checks the header was made with the given secret no more than tolerance_seconds before now. */
+pub fn verify_signature_header(header: &str, body: &str, secret: &str, tolerance_seconds: i64, now: i64) -> ConstataResult<()> {
+  let mut timestamp = None;
+  let mut signatures = vec![];
+  for pair in header.split(',') {
+    match pair.trim().split_once('=') {
+      Some(("t", t)) => timestamp = t.parse::<i64>().ok(),
+      Some(("v1", sig)) => signatures.extend(hex::decode(sig).ok()),
+      _ => {},
+    }
+  }
+
+  let timestamp = timestamp.ok_or_else(|| Error::validation("signature", "missing_timestamp"))?;
+  if (now - timestamp).abs() > tolerance_seconds {
+    return Err(Error::validation("signature", "timestamp_outside_tolerance"));
+  }
+
+  for signature in signatures {
+    if mac_for(secret, timestamp, body)?.verify(&signature).is_ok() {
+      return Ok(());
+    }
+  }
+
+  Err(Error::validation("signature", "no_matching_signature"))
+}
+
+fn mac_for(secret: &str, timestamp: i64, body: &str) -> ConstataResult<HmacSha256> {
+  let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+    .map_err(|_| Error::Internal("Unexpected error processing hmac".to_string()))?;
+  mac.update(format!("{timestamp}.{body}").as_bytes());
+  Ok(mac)
+}
+
+impl WebCallbackEndpoint {
+  pub async fn kinds(&self) -> sqlx::Result<Vec<WebCallbackKind>> {
+    Ok(self.web_callback_subscription_vec().await?.into_iter().map(|s| s.attrs.kind).collect())
+  }
+
+  pub async fn is_subscribed_to(&self, kind: WebCallbackKind) -> sqlx::Result<bool> {
+    Ok(self.attrs.all_kinds || self.kinds().await?.contains(&kind))
+  }
+
+  /* Replaces all subscriptions, with either a list of kinds or every kind of event, even the ones added later. */
+  pub async fn set_kinds(self, kinds: &[WebCallbackKind], all_kinds: bool) -> ConstataResult<Self> {
+    WebCallbackEndpointHub::validate_kinds(kinds, all_kinds)?;
+
+    self.state.db.execute(sqlx::query!(
+      "DELETE FROM web_callback_subscriptions WHERE web_callback_endpoint_id = $1",
+      self.attrs.id
+    )).await?;
+
+    for kind in kinds {
+      if self.web_callback_subscription_scope().kind_eq(kind).optional().await?.is_none() {
+        self.state.web_callback_subscription().insert(InsertWebCallbackSubscription{
+          web_callback_endpoint_id: self.attrs.id,
+          kind: *kind,
+        }).save().await?;
+      }
+    }
+    Ok(self.update().all_kinds(all_kinds).save().await?)
+  }
+
+  /* Passing kinds alone replaces the list, and all_kinds alone switches to or from every kind. */
+  pub async fn update_settings(self,
+    url: Option<String>,
+    kinds: Option<Vec<WebCallbackKind>>,
+    all_kinds: Option<bool>,
+    enabled: Option<bool>,
+  ) -> ConstataResult<Self> {
+    let new_kinds = if kinds.is_some() || all_kinds.is_some() {
+      let new_kinds = (kinds.unwrap_or_default(), all_kinds.unwrap_or(false));
+      WebCallbackEndpointHub::validate_kinds(&new_kinds.0, new_kinds.1)?;
+      Some(new_kinds)
+    } else {
+      None
+    };
+
+    let mut updater = self.update();
+    if let Some(url) = url {
+      updater = updater.url(WebCallbackEndpointHub::validate_url(&url)?);
+    }
+    if let Some(enabled) = enabled {
+      updater = updater.enabled(enabled);
+    }
+    let endpoint = updater.save().await?;
+
+    match new_kinds {
+      Some((kinds, all_kinds)) => endpoint.set_kinds(&kinds, all_kinds).await,
+      None => Ok(endpoint),
+    }
+  }
+
+  /* Makes a new secret. The current one keeps signing requests for overlap_hours, so receivers can switch at their own pace. */
+  pub async fn rotate_secret(self, overlap_hours: Option<i64>) -> ConstataResult<Self> {
+    let hours = overlap_hours.unwrap_or(WebCallbackEndpointHub::DEFAULT_ROTATION_OVERLAP_HOURS);
+    if !(0..=WebCallbackEndpointHub::MAX_ROTATION_OVERLAP_HOURS).contains(&hours) {
+      return Err(Error::validation("overlap_hours", "must_be_between_0_and_168"));
+    }
+
+    let previous = self.attrs.secret.clone();
+    Ok(self.update()
+      .secret(WebCallbackEndpointHub::make_secret())
+      .previous_secret(Some(previous))
+      .previous_secret_expires_at(Some(Utc::now() + Duration::hours(hours)))
+      .save().await?)
+  }
+
+  pub fn active_secrets(&self) -> Vec<&str> {
+    let mut secrets = vec![self.attrs.secret.as_str()];
+    if let (Some(previous), Some(expires_at)) = (&self.attrs.previous_secret, self.attrs.previous_secret_expires_at) {
+      if expires_at > Utc::now() {
+        secrets.push(previous.as_str());
+      }
+    }
+    secrets
+  }
+
+  pub fn signature_header(&self, timestamp: i64, body: &str) -> ConstataResult<String> {
+    signature_header(timestamp, body, &self.active_secrets())
+  }
+}
+
+describe! {
+  dbtest!{ finds_enabled_endpoints_subscribed_to_a_kind (site, c)
+    let org = c.alice().await.org().await;
+    let everything = site.web_callback_endpoint().create(&org, "https://example.com/all", &[], true, true).await?;
+    let revoked = site.web_callback_endpoint()
+      .create(&org, "https://example.com/revoked", &[WebCallbackKind::EntryRevoked], false, true).await?;
+    let disabled = site.web_callback_endpoint().create(&org, "https://example.com/off", &[], true, false).await?;
+
+    let ids = |endpoints: Vec<WebCallbackEndpoint>| endpoints.into_iter().map(|e| e.attrs.id).collect::<Vec<i32>>();
+
+    assert_eq!(ids(site.web_callback_endpoint().subscribed_to(org.attrs.id, WebCallbackKind::AttestationDone).await?), vec![everything.attrs.id]);
+    assert_eq!(
+      ids(site.web_callback_endpoint().subscribed_to(org.attrs.id, WebCallbackKind::EntryRevoked).await?),
+      vec![everything.attrs.id, revoked.attrs.id]
+    );
+
+    let disabled = disabled.update_settings(None, Some(vec![WebCallbackKind::AttestationDone]), None, Some(true)).await?;
+    assert!(!disabled.attrs.all_kinds);
+    assert_eq!(site.web_callback_endpoint().subscribed_to(org.attrs.id, WebCallbackKind::AttestationDone).await?.len(), 2);
+    assert_eq!(site.web_callback_endpoint().subscribed_to(org.attrs.id, WebCallbackKind::InvoicePaid).await?.len(), 1);
+
+    let revoked = revoked.update_settings(None, None, Some(true), None).await?;
+    assert!(revoked.kinds().await?.is_empty());
+    assert_eq!(site.web_callback_endpoint().subscribed_to(org.attrs.id, WebCallbackKind::InvoicePaid).await?.len(), 2);
+
+    assert_that!(
+      &site.web_callback_endpoint().create(&org, "ftp://example.com", &[], true, true).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("must_be_http_or_https".to_string()) } }
+    );
+    assert_that!(
+      &site.web_callback_endpoint().create(&org, "https://example.com/none", &[], false, true).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("must_set_kinds_or_all_kinds".to_string()) } }
+    );
+    assert_that!(
+      &site.web_callback_endpoint().create(&org, "https://example.com/both", &[WebCallbackKind::EntryRevoked], true, true).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("set_either_kinds_or_all_kinds".to_string()) } }
+    );
+    assert_that!(
+      &revoked.update_settings(None, Some(vec![]), None, None).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("must_set_kinds_or_all_kinds".to_string()) } }
+    );
+  }
+
+  dbtest!{ signs_with_both_secrets_while_rotating (site, c)
+    let org = c.alice().await.org().await;
+    let endpoint = site.web_callback_endpoint().create(&org, "https://example.com/all", &[], true, true).await?;
+    let old_secret = endpoint.attrs.secret.clone();
+    let now = Utc::now().timestamp();
+    let body = r#"{"payload":"hello"}"#;
+
+    let header = endpoint.signature_header(now, body)?;
+    verify_signature_header(&header, body, &old_secret, 300, now)?;
+    assert!(verify_signature_header(&header, "tampered", &old_secret, 300, now).is_err());
+    assert!(verify_signature_header(&header, body, &old_secret, 300, now + 301).is_err());
+
+    let rotated = endpoint.rotate_secret(None).await?;
+    assert_ne!(rotated.attrs.secret, old_secret);
+    let header = rotated.signature_header(now, body)?;
+    verify_signature_header(&header, body, &old_secret, 300, now)?;
+    verify_signature_header(&header, body, &rotated.attrs.secret, 300, now)?;
+
+    let rotated = rotated.rotate_secret(Some(0)).await?;
+    assert_eq!(rotated.active_secrets(), vec![rotated.attrs.secret.as_str()]);
+    assert!(rotated.rotate_secret(Some(200)).await.is_err());
+  }
+}
@@ -0,0 +1,159 @@
+/*
+ * The resources sent in web callbacks other than attestations and revocations.
+ * Each kind has its own payload version, see WebCallbackKind::payload_version. New fields may be added to
+ * a version, but renaming, removing or changing the meaning of a field requires bumping it.
+ */
+use super::*;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct IssuanceEvent {
+  pub id: i32,
+  pub name: String,
+  pub state: String,
+  pub template_id: i32,
+  pub entries_count: i64,
+  pub created_at: UtcDateTime,
+}
+
+impl IssuanceEvent {
+  pub async fn from_model(issuance: &Issuance) -> ConstataResult<Self> {
+    Ok(Self {
+      id: issuance.attrs.id,
+      name: issuance.attrs.name.clone(),
+      state: issuance.attrs.state.clone(),
+      template_id: issuance.attrs.template_id,
+      entries_count: issuance.entry_scope().count().await?,
+      created_at: issuance.attrs.created_at,
+    })
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct EntryEvent {
+  pub id: i32,
+  pub issuance_id: i32,
+  pub row_number: i32,
+  pub external_id: Option<String>,
+  pub state: String,
+  pub document_id: Option<String>,
+  pub email_sent_at: Option<UtcDateTime>,
+}
+
+impl EntryEvent {
+  pub async fn from_model(entry: &Entry) -> ConstataResult<Self> {
+    let email_sent_at = match entry.email_callback().await? {
+      Some(callback) => callback.attrs.sent_at,
+      None => None,
+    };
+
+    Ok(Self {
+      id: entry.attrs.id,
+      issuance_id: entry.attrs.issuance_id,
+      row_number: entry.attrs.row_number,
+      external_id: entry.attrs.external_id.clone(),
+      state: entry.attrs.state.clone(),
+      document_id: entry.attrs.document_id.clone(),
+      email_sent_at,
+    })
+  }
+}
+
+/* Documents belong to either an attestation or an issuance entry, integrators get whichever applies. */
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DocumentEvent {
+  pub id: String,
+  pub story_id: i32,
+  pub attestation_id: Option<i32>,
+  pub entry_id: Option<i32>,
+  pub state: String,
+  pub cost: Decimal,
+  pub funded_at: Option<UtcDateTime>,
+}
+
+impl DocumentEvent {
+  pub async fn from_model(document: &Document) -> ConstataResult<Self> {
+    let attestation_id = document.state.attestation().select()
+      .story_id_eq(&document.attrs.story_id)
+      .optional().await?
+      .map(|a| a.attrs.id);
+
+    Ok(Self {
+      id: document.attrs.id.clone(),
+      story_id: document.attrs.story_id,
+      attestation_id,
+      entry_id: document.entry_optional().await?.map(|e| e.attrs.id),
+      state: if document.is_accepted() { "accepted" } else { "parked" }.to_string(),
+      cost: document.attrs.cost,
+      funded_at: document.attrs.funded_at,
+    })
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct InvoiceEvent {
+  pub id: i32,
+  pub payment_source: PaymentSource,
+  pub amount: Decimal,
+  pub tokens: Decimal,
+  pub payment_id: Option<i32>,
+  pub created_at: UtcDateTime,
+}
+
+impl InvoiceEvent {
+  pub fn from_model(invoice: &Invoice) -> Self {
+    Self {
+      id: invoice.attrs.id,
+      payment_source: invoice.attrs.payment_source,
+      amount: invoice.attrs.amount,
+      tokens: invoice.attrs.tokens,
+      payment_id: invoice.attrs.payment_id,
+      created_at: invoice.attrs.created_at,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TokenBalanceEvent {
+  pub org_id: i32,
+  pub token_balance: Decimal,
+  pub threshold: Decimal,
+  pub parked_count: i64,
+  pub parked_tokens: Decimal,
+  pub buy_tokens_url: Option<String>,
+}
+
+impl TokenBalanceEvent {
+  pub fn from_model(account_state: &AccountState) -> Self {
+    Self {
+      org_id: account_state.org_id,
+      token_balance: account_state.token_balance,
+      threshold: WebCallbackHub::LOW_TOKEN_BALANCE,
+      parked_count: account_state.parked_count,
+      parked_tokens: account_state.parked_tokens,
+      buy_tokens_url: account_state.pending_invoice_link_url.clone(),
+    }
+  }
+}
+
+describe! {
+  dbtest!{ schedules_versioned_document_callbacks (site, c)
+    let alice = c.alice().await;
+    site.web_callback_endpoint()
+      .create(&alice.org().await, "http://127.0.0.1:1234/callbacks_url", &[WebCallbackKind::DocumentAccepted], false, true).await?;
+
+    let att = site.attestation()
+      .create(&alice.person().await, &[alice.signed_payload(b"hello world")], None, None, None, vec![]).await?;
+    let document = att.story().await?.documents().await?.pop().unwrap();
+
+    let callback = site.web_callback().select().kind_eq(&WebCallbackKind::DocumentAccepted).one().await?;
+    assert_eq!(callback.attrs.resource_id, att.attrs.story_id);
+    assert_eq!(callback.attrs.document_id.as_ref(), Some(&document.attrs.id));
+    assert_eq!(site.web_callback().select().kind_eq(&WebCallbackKind::DocumentParked).count().await?, 0);
+
+    let event = DocumentEvent::from_model(&document).await?;
+    assert_eq!(event.attestation_id, Some(att.attrs.id));
+    assert_eq!(event.entry_id, None);
+    assert_eq!(event.state, "accepted");
+    assert_eq!(WebCallbackKind::DocumentAccepted.payload_version(), 1);
+  }
+}
@@ -0,0 +1,522 @@
+/*
+ * Captures what a URL showed at some point in time, as attestation evidence.
+ * A worker fetches the page and the images, scripts and stylesheets it references, and stores every
+ * request and response, headers included, in a WARC file. That file is added as a document to the
+ * attestation's story and stamped like any other. Like PubkeyDomainEndorsement, failed captures are
+ * retried a few times, and every failure is kept in the attempts_log.
+ * Users choose what we fetch, so every host we connect to, redirects and resources included, must resolve
+ * to public addresses only. See Warc::resolve.
+ */
+use super::*;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+model!{
+  state: Site,
+  table: web_captures,
+  struct WebCapture {
+    #[sqlx_model_hints(int4, default)]
+    id: i32,
+    #[sqlx_model_hints(int4)]
+    org_id: i32,
+    #[sqlx_model_hints(int4)]
+    person_id: PersonId,
+    #[sqlx_model_hints(int4)]
+    attestation_id: i32,
+    #[sqlx_model_hints(text)]
+    url: String,
+    #[sqlx_model_hints(varchar, default)]
+    state: String,
+    #[sqlx_model_hints(int4, default)]
+    attempts: i32,
+    #[sqlx_model_hints(timestamptz, default)]
+    next_attempt: UtcDateTime,
+    #[sqlx_model_hints(text, default)]
+    attempts_log: String,
+    #[sqlx_model_hints(varchar, default)]
+    document_id: Option<String>,
+    #[sqlx_model_hints(timestamptz, default)]
+    created_at: UtcDateTime,
+  },
+  belongs_to {
+    Org(org_id),
+    Person(person_id),
+    Attestation(attestation_id),
+  }
+}
+
+impl WebCaptureHub {
+  /* The attestation is created right away with no documents, the WARC file is added once it's captured. */
+  pub async fn create(&self,
+    person: &Person,
+    url: &str,
+    markers: Option<String>,
+    maybe_lang: Option<i18n::Lang>,
+  ) -> ConstataResult<Attestation> {
+    let parsed = url::Url::parse(url).map_err(|_| Error::validation("url", "not_an_url"))?;
+    let allowed_hosts = self.state.settings.web_capture_allowed_hosts();
+
+    let is_allowed = parsed.host_str().map(|h| allowed_hosts.iter().any(|a| a == h)).unwrap_or(false);
+    if parsed.scheme() != "https" && !is_allowed {
+      return Err(Error::validation("url", "must_be_https"));
+    }
+
+    let checked = parsed.clone();
+    tokio::task::spawn_blocking(move || Warc::check_host(&checked, &allowed_hosts)).await
+      .map_err(|e| Error::Internal(format!("Web capture host check failed: {e}")))??;
+
+    let attestation = self.state.attestation().create(person, &[], None, markers, maybe_lang, vec![]).await?;
+
+    self.insert(InsertWebCapture{
+      org_id: person.attrs.org_id,
+      person_id: person.attrs.id,
+      attestation_id: attestation.attrs.id,
+      url: parsed.to_string(),
+    }).save().await?;
+
+    Ok(attestation)
+  }
+
+  pub async fn process_all(&self) -> ConstataResult<Vec<WebCapture>> {
+    let all = self.select()
+      .state_eq(&"pending".to_string())
+      .next_attempt_lte(&Utc::now())
+      .all().await?;
+
+    /* A capture that breaks on our side is retried on the next run, it must not hold back the others. */
+    let mut processed = vec![];
+    for capture in all {
+      let id = capture.attrs.id;
+      match capture.process().await {
+        Ok(done) => processed.push(done),
+        Err(e) => log::error!("Error processing web capture {}: {:?}", id, e),
+      }
+    }
+    Ok(processed)
+  }
+}
+
+impl WebCapture {
+  pub fn is_pending(&self) -> bool { self.attrs.state == "pending" }
+  pub fn is_done(&self) -> bool { self.attrs.state == "done" }
+  pub fn is_failed(&self) -> bool { self.attrs.state == "failed" }
+
+  async fn process(self) -> ConstataResult<WebCapture> {
+    let url = url::Url::parse(self.url()).map_err(|_| Error::validation("url", "not_an_url"))?;
+    let allowed_hosts = self.state.settings.web_capture_allowed_hosts();
+
+    let captured = tokio::task::spawn_blocking(move || Warc::capture(&url, allowed_hosts)).await
+      .map_err(|e| Error::Internal(format!("Web capture thread failed: {e}")))
+      .and_then(|result| result);
+
+    let stored = match captured {
+      Ok(warc) => self.store(&warc).await,
+      Err(error) => Err(error),
+    };
+
+    match stored {
+      Ok(doc) => {
+        let capture = self.update().state("done".to_string()).document_id(Some(doc.attrs.id)).save().await?;
+        capture.attestation().await?.refresh_search_index().await?;
+        Ok(capture)
+      },
+      Err(error) => self.record_failed_attempt(error).await,
+    }
+  }
+
+  async fn store(&self, warc: &[u8]) -> ConstataResult<Document> {
+    let story = self.attestation().await?.story().await?;
+    let mime = Some(("application/warc".to_string(), ".warc".to_string()));
+    self.state.document()
+      .create_and_index(&story, warc, None, self.attrs.person_id, mime, DocumentSource::WebCapture, false)
+      .await
+  }
+
+  /* Failures fetching or storing the capture are retried with a growing backoff, until we give up. */
+  async fn record_failed_attempt(self, error: Error) -> ConstataResult<WebCapture> {
+    let message = format!("{}{}\n", self.attempts_log(), error);
+    let attempts = self.attrs.attempts + 1;
+    let updater = self.update().attempts(attempts).attempts_log(message);
+
+    if attempts >= 10 {
+      Ok(updater.state("failed".to_string()).save().await?)
+    } else {
+      Ok(updater.next_attempt(Utc::now() + Duration::minutes(5 * attempts as i64)).save().await?)
+    }
+  }
+}
+
+/* Writes WARC 1.1 records. Request records have the exact headers we send, and response records have the
+ * status line and headers as received. Bodies are stored decoded, so we ask servers not to compress them.
+ * Redirects are followed by hand, so each hop gets its own records. */
+pub struct Warc {
+  bytes: Vec<u8>,
+  fetched: Vec<String>,
+  agent: ureq::Agent,
+}
+
+pub struct WarcFetch {
+  pub status: u16,
+  pub content_type: String,
+  pub location: Option<String>,
+  pub body: Vec<u8>,
+}
+
+impl Warc {
+  pub const MAX_RESOURCES: usize = 50;
+  pub const MAX_BYTES: usize = 20 * 1024 * 1024;
+  pub const MAX_REDIRECTS: usize = 5;
+  pub const USER_AGENT: &'static str = "Constata web capture (+https://constata.eu)";
+
+  pub fn new(allowed_hosts: Vec<String>) -> Self {
+    let agent = ureq::AgentBuilder::new()
+      .redirects(0)
+      .timeout(std::time::Duration::from_secs(10))
+      .resolver(move |netloc: &str| Self::resolve(netloc, &allowed_hosts))
+      .build();
+
+    Warc{ bytes: vec![], fetched: vec![], agent }
+  }
+
+  pub fn capture(url: &url::Url, allowed_hosts: Vec<String>) -> ConstataResult<Vec<u8>> {
+    let mut warc = Warc::new(allowed_hosts);
+    warc.write_record("warcinfo", None, None, "application/warc-fields",
+      format!("software: Constata\r\nformat: WARC File Format 1.1\r\nhttp-header-user-agent: {}\r\n", Self::USER_AGENT).as_bytes());
+
+    let (url, page) = warc.fetch_following_redirects(url)?;
+    if page.status >= 300 {
+      return Err(Error::validation("url", &format!("unexpected_status_{}", page.status)));
+    }
+
+    if page.content_type == "text/html" {
+      for resource in sub_resources(&url, &page.body).into_iter().take(Self::MAX_RESOURCES) {
+        if warc.bytes.len() >= Self::MAX_BYTES {
+          break;
+        }
+        /* A missing image or stylesheet doesn't make the capture fail, the page is what matters. */
+        if let Err(error) = warc.fetch_following_redirects(&resource) {
+          warc.write_record("metadata", Some(resource.as_str()), None, "application/warc-fields",
+            format!("fetch-error: {error}\r\n").as_bytes());
+        }
+      }
+    }
+
+    Ok(warc.bytes)
+  }
+
+  /* Returns the last URL fetched along with its response, which is still a redirect if there were too many. */
+  pub fn fetch_following_redirects(&mut self, url: &url::Url) -> ConstataResult<(url::Url, WarcFetch)> {
+    let mut url = url.clone();
+    let mut response = self.fetch(&url)?;
+
+    for _ in 0..Self::MAX_REDIRECTS {
+      let Some(next) = response.location.as_ref().and_then(|l| url.join(l).ok()) else { break };
+      if next.scheme() != "http" && next.scheme() != "https" {
+        return Err(Error::validation("url", "redirect_is_not_http"));
+      }
+      url = next;
+      response = self.fetch(&url)?;
+    }
+
+    Ok((url, response))
+  }
+
+  /* Used when a capture is requested, so users know right away if their URL points somewhere we won't go.
+   * The worker's resolver checks again on every connection, as DNS answers may change in the meantime. */
+  pub fn check_host(url: &url::Url, allowed_hosts: &[String]) -> ConstataResult<()> {
+    let host = url.host_str().ok_or_else(|| Error::validation("url", "not_an_url"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+
+    match Self::resolve(&format!("{host}:{port}"), allowed_hosts) {
+      Ok(_) => Ok(()),
+      Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Err(Error::validation("url", "must_be_a_public_address")),
+      Err(_) => Err(Error::validation("url", "cannot_resolve_host")),
+    }
+  }
+
+  /* Resolves a host:port like ureq would, but refuses hosts with any loopback, private, link-local or otherwise
+   * reserved address, unless they're in the allowed hosts setting. */
+  pub fn resolve(netloc: &str, allowed_hosts: &[String]) -> std::io::Result<Vec<SocketAddr>> {
+    let addresses: Vec<SocketAddr> = netloc.to_socket_addrs()?.collect();
+    let host = netloc.rsplit_once(':').map(|(host, _)| host).unwrap_or(netloc);
+
+    if allowed_hosts.iter().any(|a| a == host) {
+      return Ok(addresses);
+    }
+
+    if let Some(refused) = addresses.iter().find(|a| !is_public_ip(&a.ip())) {
+      return Err(std::io::Error::new(std::io::ErrorKind::PermissionDenied,
+        format!("{host} resolves to {}, which is not a public address", refused.ip())));
+    }
+
+    Ok(addresses)
+  }
+
+  pub fn fetch(&mut self, url: &url::Url) -> ConstataResult<WarcFetch> {
+    self.fetched.push(url.to_string());
+
+    let host = match url.port() {
+      Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+      None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let path = match url.query() {
+      Some(query) => format!("{}?{query}", url.path()),
+      None => url.path().to_string(),
+    };
+    let headers = [("Host", host.as_str()), ("User-Agent", Self::USER_AGENT), ("Accept", "*/*"), ("Accept-Encoding", "identity")];
+
+    let mut request = format!("GET {path} HTTP/1.1\r\n");
+    let mut call = self.agent.get(url.as_str());
+    for (name, value) in headers {
+      request.push_str(&format!("{name}: {value}\r\n"));
+      if name != "Host" {
+        call = call.set(name, value);
+      }
+    }
+    request.push_str("\r\n");
+
+    let response = match call.call() {
+      Ok(response) => response,
+      Err(ureq::Error::Status(_, response)) => response,
+      Err(error) => return Err(error.into()),
+    };
+
+    let status = response.status();
+    let content_type = response.content_type().to_string();
+    let location = if (300..400).contains(&status) { response.header("Location").map(|l| l.to_string()) } else { None };
+    let mut head = format!("{} {} {}\r\n", response.http_version(), status, response.status_text());
+    for name in response.headers_names() {
+      if let Some(value) = response.header(&name) {
+        head.push_str(&format!("{name}: {value}\r\n"));
+      }
+    }
+    head.push_str("\r\n");
+
+    let mut body = vec![];
+    let remaining = Self::MAX_BYTES.saturating_sub(self.bytes.len()) as u64;
+    response.into_reader().take(remaining).read_to_end(&mut body)?;
+
+    let request_id = self.write_record("request", Some(url.as_str()), None, "application/http;msgtype=request", request.as_bytes());
+    self.write_record("response", Some(url.as_str()), Some(&request_id), "application/http;msgtype=response",
+      &[head.as_bytes(), &body].concat());
+
+    Ok(WarcFetch{ status, content_type, location, body })
+  }
+
+  fn write_record(&mut self, kind: &str, target: Option<&str>, concurrent_to: Option<&str>, content_type: &str, block: &[u8]) -> String {
+    let random: [u8; 16] = rand::random();
+    let hex = hex::encode(random);
+    let record_id = format!("<urn:uuid:{}-{}-4{}-a{}-{}>", &hex[0..8], &hex[8..12], &hex[13..16], &hex[17..20], &hex[20..32]);
+
+    let mut header = format!("WARC/1.1\r\nWARC-Type: {kind}\r\nWARC-Record-ID: {record_id}\r\nWARC-Date: {}\r\n",
+      Utc::now().format("%Y-%m-%dT%H:%M:%SZ"));
+    if let Some(uri) = target {
+      header.push_str(&format!("WARC-Target-URI: {uri}\r\n"));
+    }
+    if let Some(id) = concurrent_to {
+      header.push_str(&format!("WARC-Concurrent-To: {id}\r\n"));
+    }
+    header.push_str(&format!("Content-Type: {content_type}\r\nWARC-Block-Digest: sha256:{}\r\nContent-Length: {}\r\n\r\n",
+      hexdigest(block), block.len()));
+
+    self.bytes.extend_from_slice(header.as_bytes());
+    self.bytes.extend_from_slice(block);
+    self.bytes.extend_from_slice(b"\r\n\r\n");
+
+    record_id
+  }
+}
+
+/* Besides the obvious ones, this refuses shared address space (100.64.0.0/10), unique local IPv6 addresses,
+ * and IPv4 addresses mapped into IPv6. */
+fn is_public_ip(ip: &IpAddr) -> bool {
+  match ip {
+    IpAddr::V4(v4) => {
+      let [a, b, ..] = v4.octets();
+      !(v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified() || v4.is_broadcast()
+        || v4.is_multicast() || v4.is_documentation() || a == 0 || a >= 240 || (a == 100 && (64..128).contains(&b)))
+    },
+    IpAddr::V6(v6) => {
+      if let Some(v4) = v6.to_ipv4_mapped() {
+        return is_public_ip(&IpAddr::V4(v4));
+      }
+      let first = v6.segments()[0];
+      !(v6.is_loopback() || v6.is_unspecified() || v6.is_multicast() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80)
+    },
+  }
+}
+
+/* Images, scripts, frames and media sources, and stylesheets or icons from link tags. */
+fn sub_resources(base: &url::Url, html: &[u8]) -> Vec<url::Url> {
+  lazy_static::lazy_static! {
+    static ref SRC: regex::Regex = regex::Regex::new(
+      r#"(?i)<(?:img|script|iframe|source|video|audio|embed)\b[^>]*?\ssrc\s*=\s*["']([^"']+)["']"#
+    ).unwrap();
+    static ref LINK: regex::Regex = regex::Regex::new(r#"(?i)<link\b[^>]*>"#).unwrap();
+    static ref REL: regex::Regex = regex::Regex::new(r#"(?i)\srel\s*=\s*["'][^"']*(stylesheet|icon)[^"']*["']"#).unwrap();
+    static ref HREF: regex::Regex = regex::Regex::new(r#"(?i)\shref\s*=\s*["']([^"']+)["']"#).unwrap();
+  }
+
+  let html = String::from_utf8_lossy(html);
+  let mut found: Vec<url::Url> = vec![];
+
+  let srcs = SRC.captures_iter(&html).filter_map(|c| c.get(1).map(|m| m.as_str().to_string()));
+  let hrefs = LINK.find_iter(&html)
+    .filter(|tag| REL.is_match(tag.as_str()))
+    .filter_map(|tag| HREF.captures(tag.as_str()).and_then(|c| c.get(1)).map(|m| m.as_str().to_string()));
+
+  for reference in srcs.chain(hrefs) {
+    let Ok(url) = base.join(reference.trim()) else { continue };
+    if (url.scheme() == "http" || url.scheme() == "https") && !found.contains(&url) && &url != base {
+      found.push(url);
+    }
+  }
+
+  found
+}
+
+describe! {
+  /* The mockito server listens on 127.0.0.1 over plain http, which is refused by default. */
+  async fn site_allowing_mockito(site: &Site) -> ConstataResult<Site> {
+    Site::from_settings_and_password(SiteSettings{
+      web_capture_allowed_hosts: Some(vec!["127.0.0.1".to_string()]),
+      .. (*site.settings).clone()
+    }, site.password.as_deref()).await
+  }
+
+  dbtest!{ captures_a_page_and_its_resources_as_a_warc_file (site, c)
+    let alice = c.alice().await;
+    let site = site_allowing_mockito(&site).await?;
+
+    let _page = mockito::mock("GET", "/page")
+      .with_status(200)
+      .with_header("content-type", "text/html")
+      .with_body(r#"<html><head><link rel="stylesheet" href="/style.css"><link rel="canonical" href="/other"></head>
+        <body><h1>Hello world</h1><img src="logo.png"></body></html>"#)
+      .create();
+    let _style = mockito::mock("GET", "/style.css").with_status(200).with_body("h1 { color: red; }").create();
+
+    let url = format!("{}/page", mockito::server_url());
+    let attestation = site.web_capture().create(&alice.person().await, &url, None, None).await?;
+    assert!(attestation.story().await?.documents().await?.is_empty());
+
+    let capture = site.web_capture().process_all().await?.pop().unwrap();
+    assert!(capture.is_done());
+
+    let documents = attestation.story().await?.documents().await?;
+    assert_eq!(documents.len(), 1);
+    assert_eq!(documents[0].attrs.sourced_from, DocumentSource::WebCapture);
+
+    let part = documents[0].base_document_part().await?;
+    assert_eq!(part.attrs.content_type, "application/warc");
+
+    let warc = String::from_utf8(part.contents().await?)?;
+    assert_that!(&warc, rematch("WARC-Type: warcinfo"));
+    assert_that!(&warc, rematch(&format!("WARC-Target-URI: {url}")));
+    assert_that!(&warc, rematch("GET /page HTTP/1.1\r\nHost: 127.0.0.1:1234\r\n"));
+    assert_that!(&warc, rematch("HTTP/1.1 200 OK"));
+    assert_that!(&warc, rematch("<h1>Hello world</h1>"));
+    assert_that!(&warc, rematch("h1 \\{ color: red; \\}"));
+    assert_that!(&warc, rematch("HTTP/1.1 501"));
+    assert!(!warc.contains("/other"));
+    assert!(site.web_capture().process_all().await?.is_empty());
+  }
+
+  dbtest!{ retries_and_fails_captures_of_missing_pages (site, c)
+    let person = c.alice().await.person().await;
+    let site = site_allowing_mockito(&site).await?;
+
+    assert_that!(
+      &site.web_capture().create(&person, "http://example.com", None, None).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("must_be_https".to_string()) } }
+    );
+
+    let url = format!("{}/missing", mockito::server_url());
+    site.web_capture().create(&person, &url, None, None).await?;
+
+    let capture = site.web_capture().process_all().await?.pop().unwrap();
+    assert!(capture.is_pending());
+    assert_eq!(capture.attrs.attempts, 1);
+    assert_that!(capture.attempts_log(), rematch("unexpected_status_501"));
+    assert!(site.web_capture().process_all().await?.is_empty());
+
+    let capture = capture.update().attempts(9).next_attempt(Utc::now()).save().await?;
+    assert!(site.web_capture().process_all().await?.pop().unwrap().is_failed());
+    assert!(capture.attestation().await?.story().await?.documents().await?.is_empty());
+  }
+
+  dbtest!{ retries_captures_that_could_not_be_stored (site, c)
+    let person = c.alice().await.person().await;
+    let site = site_allowing_mockito(&site).await?;
+    let _page = mockito::mock("GET", "/stored").with_status(200).with_body("Hello world").create();
+
+    let url = format!("{}/stored", mockito::server_url());
+    let attestation = site.web_capture().create(&person, &url, None, None).await?;
+    attestation.story().await?.update().sealed_at(Some(Utc::now())).save().await?;
+
+    let capture = site.web_capture().process_all().await?.pop().unwrap();
+    assert!(capture.is_pending());
+    assert_eq!(capture.attrs.attempts, 1);
+    assert!(capture.attrs.next_attempt > Utc::now());
+    assert_that!(capture.attempts_log(), rematch("story_is_sealed"));
+  }
+
+  dbtest!{ refuses_urls_that_resolve_to_private_addresses (site, c)
+    let person = c.alice().await.person().await;
+
+    for url in ["https://127.0.0.1/page", "https://localhost/page", "https://169.254.169.254/latest/meta-data", "https://[::1]/", "https://10.1.2.3/"] {
+      assert_that!(
+        &site.web_capture().create(&person, url, None, None).await.unwrap_err(),
+        structure!{ Error::Validation{ message: eq("must_be_a_public_address".to_string()) } }
+      );
+    }
+
+    assert_that!(
+      &site.web_capture().create(&person, &format!("{}/page", mockito::server_url()), None, None).await.unwrap_err(),
+      structure!{ Error::Validation{ message: eq("must_be_https".to_string()) } }
+    );
+  }
+
+  dbtest!{ checks_every_redirect_and_resource_address (site, c)
+    let alice = c.alice().await;
+    let site = site_allowing_mockito(&site).await?;
+
+    let _moved = mockito::mock("GET", "/moved").with_status(301).with_header("location", "/landing").create();
+    let _landing = mockito::mock("GET", "/landing")
+      .with_status(200)
+      .with_header("content-type", "text/html")
+      .with_body(r#"<html><body><img src="http://10.1.2.3/internal.png"><img src="/metadata.png"></body></html>"#)
+      .create();
+    let _metadata = mockito::mock("GET", "/metadata.png")
+      .with_status(302)
+      .with_header("location", "http://169.254.169.254/latest/meta-data")
+      .create();
+
+    let attestation = site.web_capture().create(&alice.person().await, &format!("{}/moved", mockito::server_url()), None, None).await?;
+    assert!(site.web_capture().process_all().await?.pop().unwrap().is_done());
+
+    let part = attestation.story().await?.documents().await?[0].base_document_part().await?;
+    let warc = String::from_utf8(part.contents().await?)?;
+    assert_that!(&warc, rematch("HTTP/1.1 301"));
+    assert_that!(&warc, rematch("GET /landing HTTP/1.1"));
+    assert_that!(&warc, rematch("HTTP/1.1 302"));
+    assert_that!(&warc, rematch("10.1.2.3 resolves to 10.1.2.3, which is not a public address"));
+    assert_that!(&warc, rematch("169.254.169.254 resolves to 169.254.169.254, which is not a public address"));
+
+    let _leak = mockito::mock("GET", "/leak").with_status(302).with_header("location", "http://[::1]:1234/landing").create();
+    site.web_capture().create(&alice.person().await, &format!("{}/leak", mockito::server_url()), None, None).await?;
+    let capture = site.web_capture().process_all().await?.pop().unwrap();
+    assert!(capture.is_pending());
+    assert_that!(capture.attempts_log(), rematch("which is not a public address"));
+  }
+
+  test!{ tells_public_addresses_apart
+    for public in ["1.1.1.1", "8.8.8.8", "2606:4700:4700::1111"] {
+      assert!(is_public_ip(&public.parse().unwrap()), "{public}");
+    }
+    for private in ["127.0.0.1", "10.0.0.1", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+      "::1", "::", "fe80::1", "fd00::1", "::ffff:127.0.0.1", "::ffff:169.254.169.254"] {
+      assert!(!is_public_ip(&private.parse().unwrap()), "{private}");
+    }
+  }
+}
@@ -44,6 +44,31 @@ impl WizardTemplate {
     Ok(id)
   }
 
+  pub async fn schema(&self, person: &Person) -> ConstataResult<TemplateSchema> {
+    match self {
+      WizardTemplate::Existing{ template_id } => {
+        person.org().await?.template_scope().id_eq(template_id).one().await?.latest_version().await?.parsed_schema()
+      },
+      WizardTemplate::New { kind, .. } => Ok(kind.default_schema()),
+    }
+  }
+
+  /* Like get_template_id, but new templates are only built in memory. */
+  pub async fn schema_and_files(self, person: &Person) -> ConstataResult<(TemplateSchema, Vec<(String, Vec<u8>)>)> {
+    let (schema, payload) = match self {
+      WizardTemplate::Existing{ template_id } => {
+        let version = person.org().await?.template_scope().id_eq(template_id).one().await?.latest_version().await?;
+        (version.parsed_schema()?, version.payload().await?)
+      },
+      WizardTemplate::New { logo, kind, .. } => {
+        let (_, payload) = WizardTemplate::make_template_zip(person.attrs.lang, logo, kind).await?;
+        (kind.default_schema(), payload)
+      }
+    };
+
+    Ok((schema, Template::read_name_and_bytes_from_payload(&payload).await?))
+  }
+
   pub async fn make_template_zip(lang: i18n::Lang, logo: ImageOrText, kind: TemplateKind) -> ConstataResult<(String, Vec<u8>)> {
     use std::io::Write;
     use zip::write::FileOptions;
@@ -92,14 +117,39 @@ impl Wizard {
 
     let template_id = self.template.get_template_id(&self.person).await?;
 
-    let sanitized = if let Err(std::str::Utf8Error{..}) = std::str::from_utf8(&self.csv) {
-      Some(self.csv.iter().map(|b| *b as char).collect::<String>().into_bytes())
-    } else {
-      None
-    };
+    let reader_buffer = Wizard::sanitize_csv(&self.csv);
+    let params = Wizard::parse_csv(&reader_buffer).await?;
+
+    let template_version_id = Some(self.person.state.template().find(&template_id).await?.latest_version().await?.attrs.id);
+
+    let issuance = self.person.state.issuance()
+      .insert(InsertIssuance{
+        person_id: self.person.attrs.id,
+        org_id: org.attrs.id,
+        template_id,
+        template_version_id,
+        state: "received".to_string(),
+        name: self.name,
+      }).save().await?;
 
-    let reader_buffer: &[u8] = &sanitized.as_deref().unwrap_or(&self.csv);
+    issuance.storage_put(&reader_buffer).await?;
 
+    let received = issuance.in_received()?;
+    received.append_entries(&params).await?;
+
+    Ok(issuance)
+  }
+
+  /* Files that are not valid utf8 are read as latin1, which is what spreadsheets often export. */
+  pub fn sanitize_csv(csv: &[u8]) -> Vec<u8> {
+    if std::str::from_utf8(csv).is_err() {
+      csv.iter().map(|b| *b as char).collect::<String>().into_bytes()
+    } else {
+      csv.to_vec()
+    }
+  }
+
+  pub async fn parse_csv(reader_buffer: &[u8]) -> ConstataResult<Vec<HashMap<String,String>>> {
     let mut rows = Wizard::read_csv_from_payload(reader_buffer).await;
 
     for header in rows.headers()? {
@@ -124,21 +174,12 @@ impl Wizard {
       }
     }
 
-    let issuance = self.person.state.issuance()
-      .insert(InsertIssuance{
-        person_id: self.person.attrs.id,
-        org_id: org.attrs.id,
-        template_id,
-        state: "received".to_string(),
-        name: self.name,
-      }).save().await?;
-
-    issuance.storage_put(&reader_buffer).await?;
-
-    let received = issuance.in_received()?;
-    received.append_entries(&params).await?;
+    Ok(params)
+  }
 
-    Ok(issuance)
+  pub async fn dry_run(self, previews: usize) -> ConstataResult<DryRunReport> {
+    let params = Wizard::parse_csv(&Wizard::sanitize_csv(&self.csv)).await?;
+    DryRunReport::build(&self.person, self.template, &params, previews).await
   }
 
   pub async fn read_csv_from_payload(reader_buffer: &[u8]) -> csv::Reader<&[u8]> {
@@ -162,19 +203,16 @@ impl JsonIssuanceBuilder {
 
     let template_id = self.template.get_template_id(&self.person).await?;
 
-    for e in &self.entries {
-      for k in e.keys() {
-        if !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
-          return Err(Error::validation("payload", "non_ascii_character"));
-        }
-      }
-    }
+    JsonIssuanceBuilder::check_keys(&self.entries)?;
     
+    let template_version_id = Some(self.person.state.template().find(&template_id).await?.latest_version().await?.attrs.id);
+
     let issuance = self.person.state.issuance()
       .insert(InsertIssuance{
         person_id: self.person.attrs.id,
         org_id: org.attrs.id,
         template_id,
+        template_version_id,
         state: "received".to_string(),
         name: self.name,
       }).save().await?;
@@ -183,4 +221,20 @@ impl JsonIssuanceBuilder {
 
     Ok(issuance.reloaded().await?)
   }
+
+  pub async fn dry_run(self, previews: usize) -> ConstataResult<DryRunReport> {
+    JsonIssuanceBuilder::check_keys(&self.entries)?;
+    DryRunReport::build(&self.person, self.template, &self.entries, previews).await
+  }
+
+  fn check_keys(entries: &[HashMap<String,String>]) -> ConstataResult<()> {
+    for e in entries {
+      for k in e.keys() {
+        if !k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+          return Err(Error::validation("payload", "non_ascii_character"));
+        }
+      }
+    }
+    Ok(())
+  }
 }
@@ -518,8 +518,8 @@ impl SignerClient {
   pub async fn make_template(&self, template_file: Vec<u8>) -> Template {
     let schema = serde_json::to_string(&vec![
       TemplateSchemaField::new("name", true, false, "Name".into(), "Nombre".into()),
-      TemplateSchemaField::new("course", false, true, "Course".into(), "Curso".into()),
-      TemplateSchemaField::new("date", false, true, "Date".into(), "Fecha".into()),
+      TemplateSchemaField::new("course", true, true, "Course".into(), "Curso".into()),
+      TemplateSchemaField::new("date", true, true, "Date".into(), "Fecha".into()),
       TemplateSchemaField::new("email", false, false, "Email".into(), "Email".into()),
     ]).unwrap();
     self.try_make_template(template_file, &schema).await.unwrap()
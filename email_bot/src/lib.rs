@@ -6,9 +6,11 @@ use constata_lib::{
   models::{
     Site,
     email_callback::EmailCallback,
+    job::{Job, JobKind, JobOutcome},
     ParkedReminder,
     OutgoingEmailMessage,
     OutgoingEmailMessageKind,
+    AttestationCosigner,
   }
 };
 use log::*;
@@ -136,11 +138,8 @@ impl EmailBot {
     Ok(())
   }
 
+  /* Email callbacks are sent through the job queue, see handle_email_callback_jobs. */
   pub async fn handle_notify_emails(&self) -> MailerResult<()> {
-    for email_callback in self.site.email_callback().ready_to_send().all().await? {
-      self.notify_callback(email_callback).await?;
-    }
-
     for parked_reminder in self.site.parked_reminder().not_sent().await? {
       self.notify_parked_documents(&parked_reminder).await?;
     }
@@ -149,9 +148,31 @@ impl EmailBot {
       self.send_outgoing_email_message(&outgoing).await?;
     }
 
+    for cosigner in self.site.attestation_cosigner().not_notified().await? {
+      self.notify_cosigner(cosigner).await?;
+    }
+
     Ok(())
   }
 
+  pub async fn handle_email_callback_jobs(&self) -> MailerResult<()> {
+    for job in self.site.job().claim(JobKind::SendEmailCallback).await? {
+      let lease = job.keep_leased();
+      let outcome = self.perform_job(&job).await;
+      lease.abort();
+      job.finish(outcome).await?;
+    }
+    Ok(())
+  }
+
+  async fn perform_job(&self, job: &Job) -> MailerResult<JobOutcome> {
+    let email_callback = self.site.email_callback().find(job.resource_id()).await?;
+    if email_callback.sent_at().is_none() {
+      self.notify_callback(email_callback).await?;
+    }
+    Ok(JobOutcome::Done)
+  }
+
   async fn notify_callback(&self, email_callback: EmailCallback) -> MailerResult<()> {
     let html = email_callback.render_mailer_html().await?;
     let doc = email_callback.document().await?;
@@ -194,6 +215,32 @@ impl EmailBot {
     Ok(())
   }
 
+  /* Co-signers without a known address are marked as notified all the same, so they're not looked at again. */
+  async fn notify_cosigner(&self, cosigner: AttestationCosigner) -> MailerResult<()> {
+    if let Some(address) = cosigner.address().await? {
+      let attestation = cosigner.attestation().await?;
+      let from = attestation.org().await?.name_for_on_behalf_of().await?;
+      let lang = attestation.story().await?.attrs.lang;
+
+      let subject = if cosigner.is_reminder() {
+        i18n::t!(lang, mailer_cosigner_reminder_subject, from)
+      } else {
+        i18n::t!(lang, mailer_cosigner_invitation_subject, from)
+      };
+
+      self.send_email(EmailContent {
+        recipients: vec![address],
+        subject,
+        html: cosigner.render_mailer_html().await?,
+        attachments: vec![],
+      }, None, None, true).await?;
+    }
+
+    cosigner.mark_notified().await?;
+
+    Ok(())
+  }
+
   async fn send_outgoing_email_message(&self, outgoing: &OutgoingEmailMessage) -> MailerResult<()> {
     let person = outgoing.person().await?;
     let lang = person.attrs.lang;
@@ -242,7 +289,8 @@ constata_lib::describe_one!{
 
     chain.fund_signer_wallet();
     chain.simulate_stamping().await;
-    bot.handle_notify_emails().await.unwrap();
+    site.job().enqueue_pending().await?;
+    bot.handle_email_callback_jobs().await.unwrap();
   }
 
   use constata_lib::models::{UtcDateTime};
@@ -38,6 +38,12 @@ make_translations!{
   mailer_parked_document_reminder_because_payment_is_needed_subject:
     "You have pending certifications, your payment is needed."
     "Tienes certificaciones pendientes, se necesita un pago.";
+  mailer_cosigner_invitation_subject:
+    "{0} invited you to sign some documents"
+    "{0} te invitó a firmar algunos documentos";
+  mailer_cosigner_reminder_subject:
+    "Reminder: {0} is waiting for your signature"
+    "Recordatorio: {0} espera tu firma";
   mailer_welcome_after_website_signup_subject:
     "You have signed up to Constata.eu"
     "Te registraste a Constata.eu";
@@ -214,11 +214,11 @@ impl Selenium {
   pub fn template_custom_schema(&self) -> String {
     serde_json::json!{[
       { "name": "name", "optional": false, "common": false, "label": "Alumno", "help": "ej: Doctor Jane Doe, Mr. John Smith.", "sample": "Luciano" },
-      { "name": "curso", "optional": false, "common": true, "label": "Curso", "help": "ej: Systems analist, Social media expert GPA 81/100", "sample": "Desarrollo Web" },
-      { "name": "day", "optional": false, "common": true, "label": "Día", "help": "ej: 5", "sample": "5" },
-      { "name": "month", "optional": false, "common": true, "label": "Mes", "help": "ej: Enero, Marzo", "sample": "Diciembre" },
-      { "name": "year", "optional": false, "common": true, "label": "Año", "help": "ej: 2012. 2016", "sample": "2022" },
-      { "name": "nota_global", "optional": false, "common": false, "label": "Nota final", "help": "ej: 10, 6", "sample": "10" },
+      { "name": "curso", "optional": true, "common": true, "label": "Curso", "help": "ej: Systems analist, Social media expert GPA 81/100", "sample": "Desarrollo Web" },
+      { "name": "day", "optional": true, "common": true, "label": "Día", "help": "ej: 5", "sample": "5" },
+      { "name": "month", "optional": true, "common": true, "label": "Mes", "help": "ej: Enero, Marzo", "sample": "Diciembre" },
+      { "name": "year", "optional": true, "common": true, "label": "Año", "help": "ej: 2012. 2016", "sample": "2022" },
+      { "name": "nota_global", "optional": true, "common": false, "label": "Nota final", "help": "ej: 10, 6", "sample": "10" },
     ]}.to_string()
   }
 
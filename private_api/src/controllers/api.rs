@@ -17,6 +17,7 @@ constata_lib::pub_mods!{
   gift::{Gift, GiftFilter};
   invoice::{Invoice, InvoiceFilter};
   invoice_link::{InvoiceLink, InvoiceLinkFilter};
+  job::{Job, JobFilter};
   kyc_endorsement::{KycEndorsement, KycEndorsementFilter, KycEndorsementInput};
   kyc_request::{KycRequest, KycRequestFilter};
   kyc_request_evidence::{KycRequestEvidence, KycRequestEvidenceFilter};
@@ -235,6 +236,7 @@ make_graphql_query!{
     [Story, allStories, allStoriesMeta, "_allStoriesMeta", StoryFilter, i32],
     [OrgDeletion, allOrgDeletions, allOrgDeletionsMeta, "_allOrgDeletionsMeta", OrgDeletionFilter, i32],
     [Template, allTemplates, allTemplatesMeta, "_allTemplatesMeta", TemplateFilter, i32],
+    [Job, allJobs, allJobsMeta, "_allJobsMeta", JobFilter, i32],
   }
 
   #[graphql(name="DownloadProofLink")]
@@ -345,6 +347,10 @@ impl Mutation {
     OrgDeletion::physical_deletion(context, org_deletion_id).await
   }
 
+  async fn retry_job(context: &Context, id: i32) -> FieldResult<Job> {
+    Job::retry_job(context, id).await
+  }
+
   async fn create_admin_user(
     context: &Context, username: String, password: String, role: String
   ) -> FieldResult<AdminUser> {
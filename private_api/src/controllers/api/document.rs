@@ -56,6 +56,7 @@ impl Showable<db::Document, DocumentFilter> for Document {
         "Api" => Some(DocumentSource::Api),
         "Telegram" => Some(DocumentSource::Telegram),
         "Internal" => Some(DocumentSource::Internal),
+        "WebCapture" => Some(DocumentSource::WebCapture),
         _ => None,
       }
     );
@@ -0,0 +1,76 @@
+use super::*;
+use db::*;
+
+#[derive(GraphQLObject)]
+#[graphql(description = "A background job, like rendering an issuance or sending a web callback")]
+pub struct Job {
+  id: i32,
+  kind: JobKind,
+  resource_id: i32,
+  state: JobState,
+  attempts: i32,
+  max_attempts: i32,
+  run_at: UtcDateTime,
+  locked_at: Option<UtcDateTime>,
+  last_error: Option<String>,
+  created_at: UtcDateTime,
+  finished_at: Option<UtcDateTime>,
+}
+
+#[derive(Clone, GraphQLInputObject, Debug)]
+pub struct JobFilter {
+  ids: Option<Vec<i32>>,
+  id_eq: Option<i32>,
+  kind_eq: Option<JobKind>,
+  resource_id_eq: Option<i32>,
+  state_eq: Option<JobState>,
+}
+
+#[rocket::async_trait]
+impl Showable<db::Job, JobFilter> for Job {
+  fn sort_field_to_order_by(field: &str) -> Option<JobOrderBy> {
+    match field {
+      "id" => Some(JobOrderBy::Id),
+      "kind" => Some(JobOrderBy::Kind),
+      "state" => Some(JobOrderBy::State),
+      "attempts" => Some(JobOrderBy::Attempts),
+      "runAt" => Some(JobOrderBy::RunAt),
+      "createdAt" => Some(JobOrderBy::CreatedAt),
+      _ => None,
+    }
+  }
+
+  fn filter_to_select(f: JobFilter) -> SelectJob {
+    SelectJob{
+      id_in: f.ids,
+      id_eq: f.id_eq,
+      kind_eq: f.kind_eq,
+      resource_id_eq: f.resource_id_eq,
+      state_eq: f.state_eq,
+      ..Default::default()
+    }
+  }
+
+  async fn db_to_graphql(d: db::Job) -> ConstataResult<Self> {
+    Ok(Job {
+      id: d.attrs.id,
+      kind: d.attrs.kind,
+      resource_id: d.attrs.resource_id,
+      state: d.attrs.state,
+      attempts: d.attrs.attempts,
+      max_attempts: d.attrs.max_attempts,
+      run_at: d.attrs.run_at,
+      locked_at: d.attrs.locked_at,
+      last_error: d.attrs.last_error,
+      created_at: d.attrs.created_at,
+      finished_at: d.attrs.finished_at,
+    })
+  }
+}
+
+impl Job {
+  pub async fn retry_job(context: &Context, id: i32) -> FieldResult<Job> {
+    let job = context.site.job().find(&id).await?.retry().await?;
+    Ok(Job::db_to_graphql(job).await?)
+  }
+}
@@ -137,6 +137,8 @@ impl Template {
       .og_title_override(og_title_override)
       .archived(archived)
       .save().await?;
+
+    db_template.create_version(None).await?;
       
     Ok(Template::db_to_graphql(db_template).await?)
   }
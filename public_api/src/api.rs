@@ -21,15 +21,25 @@ use sqlx_models_orm::*;
 
 constata_lib::pub_mods!{
   showable::{Showable, ListMetadata};
-  template::{Template,TemplateInput,TemplateFilter};
+  template::{Template,TemplateInput,TemplateFilter,TemplateVersion,TemplateVersionDiff,TemplateRollbackInput};
   issuance::{
     Issuance,
     IssuanceFilter,
     CreateIssuanceInput,
     CreateIssuanceFromCsvInput,
     CreateIssuanceFromJsonInput,
+    CreateIssuanceFromSpreadsheetInput,
+    SpreadsheetColumnMapping,
     AppendEntriesToIssuanceInput,
-    IssuanceExport
+    IssuanceExport,
+    EntryParams,
+    IssuanceBatchSigningRoot,
+    IssuanceBatchLeaf,
+    SignIssuanceBatchInput,
+    IssuanceDryRun,
+    IssuanceDryRunViolation,
+    IssuanceDryRunPreview,
+    IssuanceDryRunDuplicate
   };
   entry::{
     Entry,
@@ -37,7 +47,10 @@ constata_lib::pub_mods!{
     UnsignedEntryPayload,
     SigningIteratorInput,
     EntryHtmlExport,
-    PreviewEntry
+    EntryVerifiableCredential,
+    PreviewEntry,
+    RevokeEntryInput,
+    ReissueEntryInput
   };
   account_state::{AccountState};
   kyc_request::{KycRequest, KycRequestFilter, KycRequestInput};
@@ -45,16 +58,36 @@ constata_lib::pub_mods!{
     Attestation,
     AttestationFilter,
     AttestationInput,
+    CreateAttestationsInput,
+    CreatedAttestation,
     AttestationSetPublishedInput,
-    AttestationHtmlExport
+    AppendDocumentsToAttestationInput,
+    Cosigner,
+    CosignerInput,
+    CosignAttestationInput,
+    WebCaptureAttestationInput,
+    AttestationHtmlExport,
+    StoryTimeline,
+    StoryTimelineSnapshot
   };
   email_address::{EmailAddress, EmailAddressFilter, EmailAddressInput, EmailAddressVerification};
   signup::{Signup, SignupInput};
   download_proof_link::{DownloadProofLink, DownloadProofLinkInput, AbridgedProofZip};
   invoice_link::{InvoiceLink, InvoiceLinkInput};
-  web_callback::{WebCallback, WebCallbackFilter, WebCallbackAttempt, WebCallbackAttemptFilter};
+  web_callback::{
+    WebCallback,
+    WebCallbackFilter,
+    WebCallbackAttempt,
+    WebCallbackAttemptFilter,
+    WebCallbackEndpoint,
+    WebCallbackEndpointFilter,
+    CreateWebCallbackEndpointInput,
+    UpdateWebCallbackEndpointInput,
+    RotateWebCallbackEndpointSecretInput,
+  };
   pubkey::{Pubkey, PubkeyFilter};
   proof::{Proof};
+  part_disclosure::{PartDisclosure, PartDisclosureInput};
 }
 
 #[rocket::get("/graphiql")]
@@ -221,6 +254,7 @@ make_graphql_query!{
     [Attestation, allAttestations, allAttestationsMeta, "_allAttestationsMeta", AttestationFilter, i32],
     [WebCallback, allWebCallbacks, allWebCallbacksMeta, "_allWebCallbacksMeta", WebCallbackFilter, i32],
     [WebCallbackAttempt, allWebCallbackAttempts, allWebCallbackAttemptsMeta, "_allWebCallbackAttemptsMeta", WebCallbackAttemptFilter, i32],
+    [WebCallbackEndpoint, allWebCallbackEndpoints, allWebCallbackEndpointsMeta, "_allWebCallbackEndpointsMeta", WebCallbackEndpointFilter, i32],
   }
 
   #[graphql(name="PreviewEntry")]
@@ -252,6 +286,21 @@ make_graphql_query!{
     })
   }
 
+  #[graphql(name="EntryVerifiableCredential")]
+  async fn entry_verifiable_credential(context: &Context, id: i32) -> FieldResult<EntryVerifiableCredential> {
+    let entry = context.org().await?.entry_scope().id_eq(&id).one().await?;
+    if !entry.has_verifiable_credential().await? {
+      return Err(field_error("not_ready", "Only completed diplomas and badges that were not revoked can be exported as verifiable credentials."))
+    }
+    let vc = entry.verifiable_credential(&context.key).await?;
+    Ok(EntryVerifiableCredential{
+      id,
+      filename: vc.filename(),
+      credential: vc.to_json()?,
+      entry: Entry::db_to_graphql(entry).await?,
+    })
+  }
+
   #[graphql(name="AccountState")]
   async fn account_state(context: &Context, _id: i32) -> FieldResult<AccountState> {
     AccountState::from_db(context.org().await?.account_state().await?)
@@ -287,6 +336,12 @@ make_graphql_query!{
     Proof::proof(context).await
   }
 
+  #[graphql(name="IssuanceBatchSigningRoot")]
+  async fn issuance_batch_signing_root(context: &Context, id: i32) -> FieldResult<IssuanceBatchSigningRoot> {
+    let issuance = context.org().await?.issuance_scope().id_eq(&id).one().await?;
+    Ok(IssuanceBatchSigningRoot::from_db(issuance).await?)
+  }
+
   #[graphql(name="IssuanceExport")]
   async fn issuance_export(context: &Context, id: i32) -> FieldResult<IssuanceExport> {
     let request = context.org().await?.issuance_scope().id_eq(&id).one().await?;
@@ -298,14 +353,30 @@ make_graphql_query!{
   async fn attestation_html_export(context: &Context, id: i32) -> FieldResult<AttestationHtmlExport> {
     let attestation = context.org().await?.attestation_scope().id_eq(&id).one().await?;
     let verifiable_html = attestation.story().await?
-      .proof(context.site.settings.network, &context.key).await?
-      .render_html(context.lang)?;
+      .cached_html_proof(&context.key, context.lang).await?;
     Ok(AttestationHtmlExport{
       id,
       attestation: api::Attestation::db_to_graphql(attestation).await?,
       verifiable_html
     })
   }
+
+  #[graphql(name="TemplateVersions")]
+  async fn template_versions(context: &Context, id: i32) -> FieldResult<Vec<TemplateVersion>> {
+    let template = context.site.template().select().id_eq(&id).org_id_eq(context.org_id()).one().await?;
+    Ok(template.versions().await?.into_iter().map(TemplateVersion::db_to_graphql).collect::<ConstataResult<Vec<_>>>()?)
+  }
+
+  #[graphql(name="TemplateVersionDiff")]
+  async fn template_version_diff(context: &Context, id: i32, from_version: i32, to_version: i32) -> FieldResult<TemplateVersionDiff> {
+    let template = context.site.template().select().id_eq(&id).org_id_eq(context.org_id()).one().await?;
+    Ok(TemplateVersionDiff::between(&template, from_version, to_version).await?)
+  }
+
+  #[graphql(name="StoryTimeline")]
+  async fn story_timeline(context: &Context, id: i32) -> FieldResult<StoryTimeline> {
+    StoryTimeline::from_db(context.org().await?.attestation_scope().id_eq(&id).one().await?).await
+  }
 }
 
 pub struct Mutation;
@@ -316,7 +387,14 @@ impl Mutation {
     //return Err(constata_lib::Error::validation("auth", "no_signups_allowed"));
     input.process(context).await
   }
-  /*
+
+  pub async fn rollback_template(context: &Context, input: TemplateRollbackInput) -> FieldResult<Template> {
+    input.process(context).await
+  }
+
+  pub async fn create_part_disclosure(context: &Context, input: PartDisclosureInput) -> FieldResult<PartDisclosure> {
+    input.process(context).await
+  }
 
   pub async fn create_issuance_from_csv(context: &Context, input: CreateIssuanceFromCsvInput) -> FieldResult<Issuance> {
     input.process(context).await
@@ -326,6 +404,22 @@ impl Mutation {
     input.process(context).await
   }
 
+  pub async fn create_issuance_from_spreadsheet(context: &Context, input: CreateIssuanceFromSpreadsheetInput) -> FieldResult<Issuance> {
+    input.process(context).await
+  }
+
+  pub async fn dry_run_issuance_from_csv(context: &Context, input: CreateIssuanceFromCsvInput) -> FieldResult<IssuanceDryRun> {
+    input.dry_run(context).await
+  }
+
+  pub async fn dry_run_issuance_from_json(context: &Context, input: CreateIssuanceFromJsonInput) -> FieldResult<IssuanceDryRun> {
+    input.dry_run(context).await
+  }
+
+  pub async fn dry_run_issuance_from_spreadsheet(context: &Context, input: CreateIssuanceFromSpreadsheetInput) -> FieldResult<IssuanceDryRun> {
+    input.dry_run(context).await
+  }
+
   pub async fn append_entries_to_issuance(context: &Context, input: AppendEntriesToIssuanceInput) -> FieldResult<Issuance> {
     input.process(context).await
   }
@@ -334,15 +428,45 @@ impl Mutation {
     input.create_attestation(context).await
   }
 
+  pub async fn create_attestations(context: &Context, input: CreateAttestationsInput) -> FieldResult<Vec<CreatedAttestation>> {
+    input.process(context).await
+  }
+
   pub async fn attestation_set_published(context: &Context, input: AttestationSetPublishedInput)
     -> FieldResult<Attestation>
   {
     input.process(context).await
   }
 
+  pub async fn append_documents_to_attestation(context: &Context, input: AppendDocumentsToAttestationInput)
+    -> FieldResult<Attestation>
+  {
+    input.process(context).await
+  }
+
+  pub async fn cosign_attestation(context: &Context, input: CosignAttestationInput) -> FieldResult<Cosigner> {
+    input.process(context).await
+  }
+
+  pub async fn create_web_capture_attestation(context: &Context, input: WebCaptureAttestationInput) -> FieldResult<Attestation> {
+    input.process(context).await
+  }
+
   pub async fn signing_iterator(context: &Context, input: SigningIteratorInput) -> FieldResult<Option<UnsignedEntryPayload>> {
     input.sign(context).await
   }
+
+  pub async fn sign_issuance_batch(context: &Context, input: SignIssuanceBatchInput) -> FieldResult<Issuance> {
+    input.process(context).await
+  }
+
+  pub async fn revoke_entry(context: &Context, input: RevokeEntryInput) -> FieldResult<Entry> {
+    input.process(context).await
+  }
+
+  pub async fn reissue_entry(context: &Context, input: ReissueEntryInput) -> FieldResult<Entry> {
+    input.process(context).await
+  }
   
   pub async fn create_kyc_request(context: &Context, input: KycRequestInput) -> FieldResult<KycRequest> {
     input.process(context).await
@@ -388,12 +512,17 @@ impl Mutation {
     input.update_template(context).await
   }
 
-  pub async fn update_web_callbacks_url(context: &Context, url: Option<String>) -> FieldResult<AccountState> {
-    let mut org = context.org().await?;
-    org = org.update().web_callbacks_url(url).save().await?;
-    AccountState::from_db(org.account_state().await?)
+  pub async fn create_web_callback_endpoint(context: &Context, input: CreateWebCallbackEndpointInput) -> FieldResult<WebCallbackEndpoint> {
+    input.process(context).await
+  }
+
+  pub async fn update_web_callback_endpoint(context: &Context, input: UpdateWebCallbackEndpointInput) -> FieldResult<WebCallbackEndpoint> {
+    input.process(context).await
+  }
+
+  pub async fn rotate_web_callback_endpoint_secret(context: &Context, input: RotateWebCallbackEndpointSecretInput) -> FieldResult<WebCallbackEndpoint> {
+    input.process(context).await
   }
-  */
 }
 
 // A root schema consists of a query and a mutation.
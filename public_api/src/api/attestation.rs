@@ -1,6 +1,6 @@
 use super::*;
 use db::{*, attestation::for_api::from_model};
-pub use db::attestation::for_api::Attestation;
+pub use db::attestation::for_api::{Attestation, Cosigner};
 
 #[derive(GraphQLInputObject, Serialize, Deserialize)]
 #[graphql(
@@ -17,14 +17,178 @@ pub struct AttestationInput {
   pub markers: Option<String>,
   #[graphql(description = "A list of email addresses to notify when the documents are attested. Constata will email them an administrative access link to view, download or share the document certificate. You can pass an empty list if you want to omit Constata's emails, and manage distribution of the attestation in any other way.")]
   pub email_admin_access_url_to: Vec<String>,
+  #[graphql(description = "People who must also sign every document before it's stamped. The documents are held until enough of them sign. See cosignAttestation.")]
+  pub cosigners: Option<Vec<CosignerInput>>,
+  #[graphql(description = "How many of the cosigners must sign before stamping. Defaults to all of them.")]
+  pub cosigning_threshold: Option<i32>,
+  #[graphql(description = "Cosigners who haven't signed by this date are timed out. Defaults to two weeks from now.")]
+  pub cosigning_deadline: Option<UtcDateTime>,
+}
+
+#[derive(GraphQLInputObject, Serialize, Deserialize, Clone, Debug)]
+#[graphql(description = "Invites someone to co-sign an attestation. Set either their email address or their public key.")]
+#[serde(rename_all = "camelCase")]
+pub struct CosignerInput {
+  #[graphql(description = "Anyone whose verified email address is this one can co-sign. We'll email them an invitation and some reminders.")]
+  pub email: Option<String>,
+  #[graphql(description = "Only the holder of this public key can co-sign.")]
+  pub pubkey: Option<String>,
+}
+
+impl CosignerInput {
+  fn into_invitee(self) -> FieldResult<CosignerInvitee> {
+    match (self.email, self.pubkey) {
+      (Some(email), None) => Ok(CosignerInvitee::Email(email)),
+      (None, Some(pubkey)) => Ok(CosignerInvitee::Pubkey(pubkey)),
+      _ => Err(field_error("cosigners", "set_either_email_or_pubkey")),
+    }
+  }
 }
 
 impl AttestationInput {
+  fn cosigning(&self) -> FieldResult<Option<Cosigning>> {
+    let Some(cosigners) = self.cosigners.clone() else { return Ok(None) };
+    Ok(Some(Cosigning{
+      invitees: cosigners.into_iter().map(|c| c.into_invitee()).collect::<FieldResult<Vec<_>>>()?,
+      threshold: self.cosigning_threshold,
+      deadline: self.cosigning_deadline,
+    }))
+  }
+
+  /* Everything that would make create_attestation fail before writing anything. */
+  async fn validate(&self, context: &Context) -> FieldResult<()> {
+    for payload in &self.documents {
+      if !payload.signed_ok()? {
+        return Err(field_error("signed_payload", "wrong_signature"));
+      }
+      if context.site.pubkey().find_optional(&payload.signer.to_string()).await?.is_none() {
+        return Err(field_error("signed_payload/signer", "signer_is_unknown"));
+      }
+    }
+
+    if let Some(cosigning) = self.cosigning()? {
+      cosigning.validate()?;
+    }
+
+    Ok(())
+  }
+
   pub async fn create_attestation(self, context: &Context) -> FieldResult<Attestation> {
     let person = context.person();
+    let cosigning = self.cosigning()?;
 
     let att = context.site.attestation()
-      .create(&person, &self.documents, self.open_until, self.markers, Some(context.lang), self.email_admin_access_url_to)
+      .create_cosigned(&person, &self.documents, self.open_until, self.markers, Some(context.lang), self.email_admin_access_url_to, cosigning)
+      .await?;
+
+    Ok(Attestation::db_to_graphql(att).await?)
+  }
+}
+
+#[derive(GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(
+  description = "Creates several attestations in one request, up to 50 of them. Every attestation is validated before creating any of them, so a bad document or cosigner rejects the whole batch. After that, each attestation gets its own result, so you know which ones were created even if some fail."
+  scalar=GqlScalar
+)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAttestationsInput {
+  #[graphql(description = "One AttestationInput for each attestation to create. Their results are returned in the same order.")]
+  pub attestations: Vec<AttestationInput>,
+}
+
+#[derive(Debug, Clone, GraphQLObject, Serialize, Deserialize)]
+#[graphql(description = "The result of creating one of the attestations sent to createAttestations. Either attestation or error is set.")]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedAttestation {
+  #[graphql(description = "The position of this attestation in the input list, starting at 0.")]
+  pub index: i32,
+  pub attestation: Option<Attestation>,
+  #[graphql(description = "Why this attestation could not be created.")]
+  pub error: Option<String>,
+}
+
+impl CreateAttestationsInput {
+  pub const MAX_ATTESTATIONS: usize = 50;
+
+  pub async fn process(self, context: &Context) -> FieldResult<Vec<CreatedAttestation>> {
+    if self.attestations.is_empty() || self.attestations.len() > Self::MAX_ATTESTATIONS {
+      return Err(field_error("attestations", "must_be_between_1_and_50"));
+    }
+
+    for input in &self.attestations {
+      input.validate(context).await?;
+    }
+
+    let mut results = vec![];
+    for (index, input) in self.attestations.into_iter().enumerate() {
+      let (attestation, error) = match input.create_attestation(context).await {
+        Ok(attestation) => (Some(attestation), None),
+        Err(e) => (None, Some(e.message().to_string())),
+      };
+      results.push(CreatedAttestation{ index: index as i32, attestation, error });
+    }
+    Ok(results)
+  }
+}
+
+#[derive(GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(
+  description = "Adds more documents to an attestation that is still open. They are charged like the first ones, and the attestation proof is regenerated to include them."
+  scalar=GqlScalar
+)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendDocumentsToAttestationInput {
+  #[graphql(description = "The attestation to append the documents to. Its open_until date must not have passed.")]
+  pub attestation_id: i32,
+  #[graphql(description = "An array of SignedPayloads with the new documents. See the tutorial for more info on signing payloads.")]
+  pub documents: Vec<signed_payload::SignedPayload>,
+}
+
+impl AppendDocumentsToAttestationInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Attestation> {
+    let attestation = context.person().org().await?.attestation_scope().id_eq(&self.attestation_id).one().await?;
+    let appended = attestation.append_documents(&self.documents).await?;
+    Ok(Attestation::db_to_graphql(appended).await?)
+  }
+}
+
+#[derive(GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(
+  description = "Co-signs every document in an attestation you were invited to. Once enough cosigners sign, the documents are stamped with all the signatures. You get back your own invitation as a Cosigner."
+  scalar=GqlScalar
+)]
+#[serde(rename_all = "camelCase")]
+pub struct CosignAttestationInput {
+  #[graphql(description = "The attestation you were invited to co-sign.")]
+  pub attestation_id: i32,
+  #[graphql(description = "One SignedPayload for each document in the attestation, all signed with the same key.")]
+  pub documents: Vec<signed_payload::SignedPayload>,
+}
+
+/* Co-signers usually belong to some other org, so they only get their own invitation back, never the attestation. */
+impl CosignAttestationInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Cosigner> {
+    let attestation = context.site.attestation().find(&self.attestation_id).await?;
+    Ok(Cosigner::from_model(attestation.cosign(&self.documents).await?))
+  }
+}
+
+#[derive(Debug, Clone, Default, GraphQLInputObject, Serialize, Deserialize, clap::Args)]
+#[graphql(description = "Creates an attestation of what a web page showed right now. Constata fetches the page, its images, scripts and stylesheets, and stamps the whole HTTP exchange as a WARC file.")]
+#[serde(rename_all = "camelCase")]
+pub struct WebCaptureAttestationInput {
+  #[arg(help="The https URL of the page to capture")]
+  #[graphql(description = "The https URL of the page to capture.")]
+  pub url: String,
+  #[arg(short, long, help="Text that can be used for searching this attestation later.")]
+  #[graphql(description = "Markers is a text that can be used for searching this attestation later. Markers cannot be updated after creation.")]
+  pub markers: Option<String>,
+}
+
+impl WebCaptureAttestationInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Attestation> {
+    let att = context.site.web_capture()
+      .create(&context.person(), &self.url, self.markers, Some(context.lang))
       .await?;
 
     Ok(Attestation::db_to_graphql(att).await?)
@@ -41,6 +205,55 @@ pub struct AttestationHtmlExport {
   pub verifiable_html: String,
 }
 
+#[derive(Debug, Clone, GraphQLObject, Serialize, Deserialize)]
+#[graphql(description = "Every snapshot of the documents in an attestation, in the order they were stamped. It proves which documents existed together at each point in time.")]
+#[serde(rename_all = "camelCase")]
+pub struct StoryTimeline {
+  pub id: i32,
+  pub attestation: Attestation,
+  pub snapshots: Vec<StoryTimelineSnapshot>,
+}
+
+#[derive(Debug, Clone, GraphQLObject, Serialize, Deserialize)]
+#[graphql(description = "The set of documents an attestation had at some point, and the bulletin where its fingerprint was stamped.")]
+#[serde(rename_all = "camelCase")]
+pub struct StoryTimelineSnapshot {
+  #[graphql(description = "The fingerprint of this snapshot, as included in the bulletin.")]
+  pub hash: String,
+  #[graphql(description = "Date in which the snapshot was taken.")]
+  pub created_at: UtcDateTime,
+  #[graphql(description = "The bulletin where this snapshot was stamped.")]
+  pub bulletin_id: i32,
+  #[graphql(description = "The state of the bulletin: 'draft', 'proposed', 'submitted' or 'published'.")]
+  pub bulletin_state: String,
+  #[graphql(description = "The bitcoin transaction for the bulletin, once it's submitted.")]
+  pub transaction_hash: Option<String>,
+  #[graphql(description = "The time of the block that included the bulletin's transaction.")]
+  pub block_time: Option<UtcDateTime>,
+  #[graphql(description = "Ids of the documents the attestation had at this point. Empty when they can't be matched to the snapshot.")]
+  pub document_ids: Vec<String>,
+}
+
+impl StoryTimeline {
+  pub async fn from_db(attestation: db::Attestation) -> FieldResult<Self> {
+    let snapshots = attestation.story().await?.timeline().await?.into_iter().map(|e| StoryTimelineSnapshot{
+      hash: e.hash,
+      created_at: e.created_at,
+      bulletin_id: e.bulletin_id,
+      bulletin_state: e.bulletin_state,
+      transaction_hash: e.transaction_hash,
+      block_time: e.block_time,
+      document_ids: e.document_ids.unwrap_or_default(),
+    }).collect();
+
+    Ok(StoryTimeline{
+      id: attestation.attrs.id,
+      attestation: Attestation::db_to_graphql(attestation).await?,
+      snapshots,
+    })
+  }
+}
+
 #[derive(Debug, Clone, Default, GraphQLInputObject, Serialize, Deserialize, clap::Args)]
 #[serde(rename_all = "camelCase")]
 pub struct AttestationFilter {
@@ -51,6 +264,13 @@ pub struct AttestationFilter {
   person_id_eq: Option<i32>,
   #[arg(long, help="Filter attestations that have this text in their markers")]
   markers_like: Option<String>,
+  #[arg(long, help="Full text search over markers, file names and the text of the attested documents. \
+    Use \"quotes\" for phrases, OR for alternatives and -word to exclude a word.")]
+  text_search: Option<String>,
+  #[arg(long, help="Only attestations created on or after this date, ie: 2024-01-31T00:00:00Z")]
+  created_at_gte: Option<UtcDateTime>,
+  #[arg(long, help="Only attestations created on or before this date, ie: 2024-01-31T23:59:59Z")]
+  created_at_lte: Option<UtcDateTime>,
 }
 
 #[rocket::async_trait]
@@ -72,6 +292,8 @@ impl Showable<db::Attestation, AttestationFilter> for Attestation {
         id_eq: f.id_eq,
         person_id_eq: f.person_id_eq,
         markers_ilike: into_like_search(f.markers_like),
+        created_at_gte: f.created_at_gte,
+        created_at_lte: f.created_at_lte,
         deletion_id_is_set: Some(false),
         ..Default::default()
       }
@@ -91,6 +313,19 @@ impl Showable<db::Attestation, AttestationFilter> for Attestation {
   async fn db_to_graphql(d: db::Attestation) -> ConstataResult<Self> {
     Ok(from_model(d).await?)
   }
+
+  /* Matches from the full text search are narrowed down by the ids given in the filter, if any. */
+  async fn resolve_filter(context: &Context, filter: Option<AttestationFilter>) -> FieldResult<Option<AttestationFilter>> {
+    let Some(mut f) = filter else { return Ok(None) };
+    let Some(text) = f.text_search.take() else { return Ok(Some(f)) };
+
+    let mut found = context.site.attestation().search_ids(context.org_id(), &text).await?;
+    if let Some(ids) = f.ids.as_ref() {
+      found.retain(|id| ids.contains(id));
+    }
+    f.ids = Some(found);
+    Ok(Some(f))
+  }
 }
 
 #[derive(Debug, Clone, Default, GraphQLInputObject, Serialize, Deserialize, clap::Args)]
@@ -217,6 +452,9 @@ constata_lib::describe_one! {
         id_eq: None,
         ids: None,
         person_id_eq: None,
+        text_search: None,
+        created_at_gte: None,
+        created_at_lte: None,
       }),
     };
     let attestations: all::ResponseData = client.gql(&AllAttestations::build_query(search)).await;
@@ -237,6 +475,9 @@ constata_lib::describe_one! {
         id_eq: None,
         ids: None,
         person_id_eq: None,
+        text_search: None,
+        created_at_gte: None,
+        created_at_lte: None,
       }),
     };
     let empty_list: all::ResponseData = client.gql(&AllAttestations::build_query(empty_search)).await;
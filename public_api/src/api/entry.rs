@@ -40,6 +40,14 @@ pub struct Entry {
   pub is_published: bool,
   #[graphql(description = "The publicly accessible URL for this entry, which could be converted into a QR code. Only available if the entry has been published.")]
   pub public_certificate_url: Option<String>,
+  #[graphql(description = "Date since which this entry is revoked, if it was revoked.")]
+  pub revoked_at: Option<UtcDateTime>,
+  #[graphql(description = "The reason given when revoking this entry, shown to anyone viewing its certificate.")]
+  pub revocation_reason: Option<String>,
+  #[graphql(description = "When this entry is a corrected reissue, the id of the entry it replaces.")]
+  pub reissue_of_id: Option<i32>,
+  #[graphql(description = "When this entry was reissued with corrections, the id of the entry that replaces it.")]
+  pub superseded_by_id: Option<i32>,
 }
 
 #[derive(Clone, Default, Debug, GraphQLInputObject, Serialize, Deserialize)]
@@ -114,6 +122,13 @@ impl Showable<db::Entry, EntryFilter> for Entry {
       (false, 0, None, false, None)
     };
 
+    let (revoked_at, revocation_reason) = match d.revocation().await? {
+      Some(r) => (Some(r.attrs.revoked_at), Some(r.attrs.reason)),
+      None => (None, None),
+    };
+
+    let superseded_by_id = d.superseded_by().await?.map(|e| e.attrs.id);
+
     Ok(Entry {
       id: d.attrs.id,
       issuance_id: d.attrs.issuance_id,
@@ -131,11 +146,55 @@ impl Showable<db::Entry, EntryFilter> for Entry {
       public_visit_count,
       admin_access_url,
       is_published,
-      public_certificate_url
+      public_certificate_url,
+      revoked_at,
+      revocation_reason,
+      reissue_of_id: d.attrs.reissue_of_id,
+      superseded_by_id,
     })
   }
 }
 
+#[derive(GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(description = "Revokes a completed entry. Its certificate and proof will show it as revoked since now, with the given reason, and the revocation will be timestamped.")]
+#[serde(rename_all = "camelCase")]
+pub struct RevokeEntryInput {
+  #[graphql(description = "Id of the completed entry to revoke.")]
+  pub entry_id: i32,
+  #[graphql(description = "Why the entry is being revoked. It will be public.")]
+  pub reason: String,
+}
+
+impl RevokeEntryInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Entry> {
+    let entry = context.org().await?.entry_scope().id_eq(&self.entry_id).one().await?;
+    let revocation = entry.in_completed()?.revoke(&context.person(), self.reason).await?;
+    Ok(Entry::db_to_graphql(revocation.entry().await?).await?)
+  }
+}
+
+#[derive(GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(description = "Reissues a completed entry with corrected params, for example to fix a misspelled name. \
+  The new entry is added to the same issuance, which goes back to 'created' until you sign it with the signingIterator. \
+  The original entry's certificate will link to the new one once it's completed.")]
+#[serde(rename_all = "camelCase")]
+pub struct ReissueEntryInput {
+  #[graphql(description = "Id of the completed entry to reissue.")]
+  pub entry_id: i32,
+  #[graphql(description = "The corrected params for the new entry, they must match the template schema.")]
+  pub params: EntryParams,
+  #[graphql(description = "Whether to email the recipient again when the new entry is signed.")]
+  pub notify: bool,
+}
+
+impl ReissueEntryInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Entry> {
+    let entry = context.org().await?.entry_scope().id_eq(&self.entry_id).one().await?;
+    let created = entry.in_completed()?.reissue(self.params.0, self.notify).await?;
+    Ok(Entry::db_to_graphql(created.into_inner()).await?)
+  }
+}
+
 #[derive(GraphQLInputObject, Serialize, Deserialize)]
 #[graphql(description = "SigningIteratorInput Object")]
 #[serde(rename_all = "camelCase")]
@@ -219,3 +278,17 @@ pub struct EntryHtmlExport{
   pub verifiable_html: String
 }
 
+
+#[derive(Debug, GraphQLObject, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "An Open Badges 3.0 AchievementCredential for a completed Diploma or Badge entry. It's a W3C Verifiable Credential in JSON-LD, signed by Constata, with the bulletin timestamp as evidence.")]
+pub struct EntryVerifiableCredential {
+  #[graphql(description = "The numerical identifier of the entry.")]
+  pub id: i32,
+  #[graphql(description = "The entry itself.")]
+  pub entry: Entry,
+  #[graphql(description = "Suggested filename for the credential.")]
+  pub filename: String,
+  #[graphql(description = "The JSON-LD verifiable credential.")]
+  pub credential: String,
+}
@@ -7,16 +7,17 @@ use juniper::{InputValue, ScalarValue, Value, ScalarToken, ParseScalarResult, Pa
 #[rocket::async_trait]
 pub trait CreateIssuanceInput: Send + Sized {
   async fn create(self, person: Person, template: WizardTemplate) -> ConstataResult<db::Issuance>;
+  async fn dry_run_report(self, person: Person, template: WizardTemplate) -> ConstataResult<DryRunReport>;
   fn attrs(&self) -> (&Option<i32>, &Option<TemplateKind>, &Option<String>, &Option<String>, &Option<Vec<u8>>);
 
   fn required<T: Clone>(val: &Option<T>, name: &str) -> ConstataResult<T> {
     val.clone().ok_or_else(|| Error::validation(name, "cannot_be_empty"))
   }
 
-  async fn process(self, context: &Context) -> FieldResult<Issuance> {
+  fn wizard_template(&self) -> ConstataResult<WizardTemplate> {
     let (template_id, new_kind, new_name, new_logo_text, new_logo_image) = self.attrs();
 
-    let template = match template_id {
+    Ok(match template_id {
       Some(id) => WizardTemplate::Existing{ template_id: *id},
       None => {
         WizardTemplate::New {
@@ -29,13 +30,25 @@ pub trait CreateIssuanceInput: Send + Sized {
           }
         }
       }
-    };
+    })
+  }
 
+  async fn process(self, context: &Context) -> FieldResult<Issuance> {
+    let template = self.wizard_template()?;
     let person = context.site.person().find(context.person_id()).await?;
     Ok(Issuance::db_to_graphql(self.create(person, template).await?).await?)
   }
+
+  async fn dry_run(self, context: &Context) -> FieldResult<IssuanceDryRun> {
+    let template = self.wizard_template()?;
+    let person = context.site.person().find(context.person_id()).await?;
+    Ok(IssuanceDryRun::from_db(self.dry_run_report(person, template).await?))
+  }
 }
 
+/* How many entries are rendered in a dry run, the rest are only validated. */
+const DRY_RUN_PREVIEWS: usize = 5;
+
 #[serde_as]
 #[derive(GraphQLInputObject, Serialize)]
 #[graphql(
@@ -92,6 +105,15 @@ impl CreateIssuanceInput for CreateIssuanceFromJsonInput {
     }.process().await
   }
 
+  async fn dry_run_report(self, person: Person, template: WizardTemplate) -> ConstataResult<DryRunReport> {
+    JsonIssuanceBuilder {
+      person: person,
+      entries: self.entries.into_iter().map(|e| e.0).collect(),
+      name: self.name.clone(),
+      template
+    }.dry_run(DRY_RUN_PREVIEWS).await
+  }
+
   fn attrs(&self) -> (&Option<i32>, &Option<TemplateKind>, &Option<String>, &Option<String>, &Option<Vec<u8>>) {
     (&self.template_id, &self.new_kind, &self.new_name, &self.new_logo_text, &self.new_logo_image)
   }
@@ -184,13 +206,185 @@ impl CreateIssuanceInput for CreateIssuanceFromCsvInput {
     }.process().await
   }
 
+  async fn dry_run_report(self, person: Person, template: WizardTemplate) -> ConstataResult<DryRunReport> {
+    Wizard {
+      person: person,
+      csv: self.csv.into_bytes(),
+      name: self.name.clone(),
+      template
+    }.dry_run(DRY_RUN_PREVIEWS).await
+  }
+
   fn attrs(&self) -> (&Option<i32>, &Option<TemplateKind>, &Option<String>, &Option<String>, &Option<Vec<u8>>) {
     (&self.template_id, &self.new_kind, &self.new_name, &self.new_logo_text, &self.new_logo_image)
   }
 }
 
+#[serde_as]
+#[derive(GraphQLInputObject, Serialize)]
+#[graphql(
+  description = "A CreateIssuanceFromSpreadsheetInput configures a new Issuance from the rows of an XLSX or ODS file, like the ones exported from Excel or LibreOffice. The first row must have the column headers, which are matched to the template fields by name or label, ignoring case and spaces. Use columnMapping when a header does not match its field. Dates are read as dates, no matter how they're displayed in the spreadsheet.",
+  scalar=GqlScalar
+)]
+#[serde(rename_all = "camelCase")]
+#[derive(clap::Args)]
+pub struct CreateIssuanceFromSpreadsheetInput {
+  #[graphql(description = "The base64 encoded XLSX or ODS file.")]
+  #[arg(skip)]
+  #[serde_as(as = "Base64")]
+  pub spreadsheet: Bytes,
+
+  #[arg(long, help="The name of the sheet to read the entries from. The first sheet is used by default.")]
+  #[graphql(description = "The name of the sheet to read the entries from. The first sheet is used by default.")]
+  pub sheet_name: Option<String>,
+
+  #[arg(long="map-column", value_name="HEADER=FIELD", value_parser=clap_column_mapping, action=clap::ArgAction::Append,
+    help="Use the column with this header for the given template field. You can repeat this argument. ie: --map-column 'Fecha de emisión=date'")]
+  #[graphql(description = "Columns whose header does not match the name or label of a template field.")]
+  pub column_mapping: Option<Vec<SpreadsheetColumnMapping>>,
+
+  #[arg(help="The name of the Issuance to be created")]
+  #[graphql(description = "The name of the Issuance to be created.")]
+  pub name: String,
+
+  #[arg(short, long, help="The kind of template to be created if no template_id is given.")]
+  #[graphql(description = "The ID of an existing template to use, if any. See the Templates resource.")]
+  pub template_id: Option<i32>,
+
+  #[arg(long, help="The kind of template to be created if no template_id is given.")]
+  #[graphql(description = "The kind of template to be created if no template_id is given.")]
+  pub new_kind: Option<TemplateKind>,
+
+  #[arg(long, help="The name of the new template to be created, if no template_id is given.")]
+  #[graphql(description = "The name of the new template to be created, if no template_id is given.")]
+  pub new_name: Option<String>,
+
+  #[arg(long, help="The text to be used as the logo for the new template, if no template_id is given.")]
+  #[graphql(description = "The text to be used as the logo for the new template, if no template_id is given.")]
+  pub new_logo_text: Option<String>,
+
+  #[arg(long, help="A base64 encoded image to use as your logo. \
+    Use --new-logo-image-file to use a local file instead. \
+    If you leave it empty your new_logo_text will be displayed."
+  )]
+  #[graphql(description = "The base64 encoded image to be used as the logo for the new template, if no template_id is given. If you leave it empty your new_logo_text will be displayed.")]
+  #[serde_as(as = "Option<Base64>")]
+  pub new_logo_image: Option<Bytes>,
+}
+
+#[derive(Clone, Debug, GraphQLInputObject, Serialize)]
+#[graphql(description = "Reads the spreadsheet column with the given header as the given template field.")]
+pub struct SpreadsheetColumnMapping {
+  #[graphql(description = "The header of the column, as written in the first row of the sheet.")]
+  pub column: String,
+  #[graphql(description = "The name of the template field.")]
+  pub field: String,
+}
+
+fn clap_column_mapping(s: &str) -> Result<SpreadsheetColumnMapping, String> {
+  let (column, field) = s.rsplit_once('=')
+    .ok_or_else(|| format!("Column mappings should look like HEADER=FIELD, it was: {}", &s))?;
+  Ok(SpreadsheetColumnMapping{ column: column.to_string(), field: field.to_string() })
+}
+
+impl CreateIssuanceFromSpreadsheetInput {
+  fn spreadsheet(&self) -> Spreadsheet {
+    Spreadsheet {
+      bytes: self.spreadsheet.clone(),
+      sheet_name: self.sheet_name.clone(),
+      column_mapping: self.column_mapping.iter().flatten().map(|m| (m.column.clone(), m.field.clone())).collect(),
+    }
+  }
+}
+
+#[rocket::async_trait]
+impl CreateIssuanceInput for CreateIssuanceFromSpreadsheetInput {
+  async fn create(self, person: Person, template: WizardTemplate) -> ConstataResult<db::Issuance> {
+    self.spreadsheet().into_wizard(person, template, self.name.clone()).await?.process().await
+  }
+
+  async fn dry_run_report(self, person: Person, template: WizardTemplate) -> ConstataResult<DryRunReport> {
+    self.spreadsheet().into_wizard(person, template, self.name.clone()).await?.dry_run(DRY_RUN_PREVIEWS).await
+  }
+
+  fn attrs(&self) -> (&Option<i32>, &Option<TemplateKind>, &Option<String>, &Option<String>, &Option<Vec<u8>>) {
+    (&self.template_id, &self.new_kind, &self.new_name, &self.new_logo_text, &self.new_logo_image)
+  }
+}
+
+#[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "The outcome of checking a new issuance without creating it. Rows are validated against the template schema, the first 5 entries are rendered, and repeated emails or recipient identifiers are listed. Nothing is saved, not even a new template. Row numbers start at 0, in the order entries were given.")]
+pub struct IssuanceDryRun {
+  #[graphql(description = "Whether the issuance would be created and its previews rendered without errors.")]
+  pub is_valid: bool,
+  #[graphql(description = "How many entries the issuance would have.")]
+  pub entries_count: i32,
+  #[graphql(description = "Every value that does not match the template schema.")]
+  pub violations: Vec<IssuanceDryRunViolation>,
+  #[graphql(description = "The entries that were rendered, with their size or the error found when rendering them.")]
+  pub previews: Vec<IssuanceDryRunPreview>,
+  #[graphql(description = "Emails, recipient identifiers or external ids found in more than one row. Compared ignoring case and surrounding spaces.")]
+  pub duplicates: Vec<IssuanceDryRunDuplicate>,
+  #[graphql(description = "Tokens the issuance would need, assuming entries not rendered are as large as the average preview.")]
+  pub estimated_tokens: i32,
+}
+
+#[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+#[graphql(description = "A value in a dry run that does not match the template schema.")]
+pub struct IssuanceDryRunViolation {
+  #[graphql(description = "The row with the wrong value.")]
+  pub row: i32,
+  #[graphql(description = "The name of the schema field.")]
+  pub field: String,
+  #[graphql(description = "What's wrong with the value, like 'missing' or 'not_an_email'.")]
+  pub message: String,
+}
+
+#[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "An entry rendered in a dry run.")]
+pub struct IssuanceDryRunPreview {
+  #[graphql(description = "The row that was rendered.")]
+  pub row: i32,
+  #[graphql(description = "Size of the rendered entry, 0 if it could not be rendered.")]
+  pub size_in_bytes: i32,
+  #[graphql(description = "The error found when rendering the entry, if any.")]
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+#[graphql(description = "A value repeated across rows in a dry run.")]
+pub struct IssuanceDryRunDuplicate {
+  #[graphql(description = "The name of the repeated field, like 'email' or 'recipient_identification'.")]
+  pub field: String,
+  #[graphql(description = "The repeated value, lowercased and trimmed.")]
+  pub value: String,
+  #[graphql(description = "All rows where the value was found.")]
+  pub rows: Vec<i32>,
+}
+
+impl IssuanceDryRun {
+  pub fn from_db(d: DryRunReport) -> Self {
+    IssuanceDryRun {
+      is_valid: d.is_valid(),
+      entries_count: d.entries_count as i32,
+      violations: d.violations.into_iter()
+        .map(|v| IssuanceDryRunViolation{ row: v.row as i32, field: v.field, message: v.message })
+        .collect(),
+      previews: d.previews.into_iter()
+        .map(|p| IssuanceDryRunPreview{ row: p.row as i32, size_in_bytes: p.size_in_bytes as i32, error: p.error })
+        .collect(),
+      duplicates: d.duplicates.into_iter()
+        .map(|x| IssuanceDryRunDuplicate{ field: x.field, value: x.value, rows: x.rows.into_iter().map(|r| r as i32).collect() })
+        .collect(),
+      estimated_tokens: d.estimated_tokens,
+    }
+  }
+}
+
 #[derive(Clone, GraphQLInputObject, Serialize)]
-#[graphql(description = "This is the best way to compose an Issuance incrementally. You can add new entries at any time before signing the Issuance. Entries will be validated as they are recevied, and then will be 'created' by our workers. In the unlikely case an entry passes validation and is received, but then an error when our worker tries to create it, the issuance will be marked as failed, as well as all other entries. ")]
+#[graphql(description = "This is the best way to compose an Issuance incrementally. You can add new entries at any time before signing the Issuance. Entries will be validated as they are recevied, and then will be 'created' by our workers. In the unlikely case an entry passes validation and is received, but then an error when our worker tries to create it, the issuance will be marked as failed, as well as all other entries. Entries with a constata_external_id, like a student id, are updated instead of duplicated when appended again, unless they were already signed.")]
 #[serde(rename_all = "camelCase")]
 #[derive(clap::Args)]
 pub struct AppendEntriesToIssuanceInput {
@@ -260,6 +454,68 @@ pub struct IssuanceExport {
   pub csv: String,
 }
 
+#[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "The merkle root over the hashes of all entries in a created issuance. \
+  Instead of signing each entry with the signingIterator, you may sign this root once with signIssuanceBatch. \
+  Leaves are sorted by entry id, each one is the hex encoded sha256 hash of the entry's unsigned payload. \
+  In the tree, a leaf is hashed as the sha256 of a 0x00 byte followed by its hex string, \
+  and a node is the sha256 of a 0x01 byte followed by its children's hex strings. An odd node is promoted as is.")]
+pub struct IssuanceBatchSigningRoot {
+  #[graphql(description = "Unique identifier of the issuance.")]
+  pub id: i32,
+  #[graphql(description = "The hex encoded merkle root to sign.")]
+  pub root: String,
+  #[graphql(description = "The entries included in this root, in order.")]
+  pub leaves: Vec<IssuanceBatchLeaf>,
+}
+
+#[derive(Debug, Clone, GraphQLObject, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "An entry included in a batch signing root.")]
+pub struct IssuanceBatchLeaf {
+  #[graphql(description = "Id of the entry.")]
+  pub entry_id: i32,
+  #[graphql(description = "The hex encoded sha256 hash of the entry's unsigned payload, see UnsignedEntryPayload.")]
+  pub hash: String,
+}
+
+impl IssuanceBatchSigningRoot {
+  pub async fn from_db(issuance: db::Issuance) -> ConstataResult<Self> {
+    let batch = issuance.in_created()?.batch_signing_root().await?;
+    Ok(Self {
+      id: issuance.attrs.id,
+      root: batch.root,
+      leaves: batch.leaves.into_iter().map(|l| IssuanceBatchLeaf{ entry_id: l.entry_id, hash: l.hash }).collect(),
+    })
+  }
+}
+
+#[derive(GraphQLInputObject, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "Signs all entries of a created issuance at once, with a single signature over their merkle root. See IssuanceBatchSigningRoot. The entries are signed in the background, the issuance moves to 'signed' once they all are.")]
+pub struct SignIssuanceBatchInput {
+  #[graphql(description = "Id of the issuance to sign.")]
+  pub issuance_id: i32,
+  #[graphql(description = "The merkle root you signed, it must match the current IssuanceBatchSigningRoot.")]
+  pub root: String,
+  #[graphql(description = "Your base64 encoded signature of the root, signed as a message.")]
+  pub signature: String,
+}
+
+impl SignIssuanceBatchInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Issuance> {
+    let signature = constata_lib::bitcoin::util::misc::MessageSignature::from_base64(&self.signature)?;
+    let created = context.org().await?
+      .issuance_scope()
+      .id_eq(&self.issuance_id)
+      .one().await?
+      .in_created()?
+      .apply_batch_signature(&self.root, signature).await?;
+    Ok(Issuance::db_to_graphql(created.into_inner()).await?)
+  }
+}
+
 #[derive(Debug, Clone, Default, GraphQLInputObject, serde::Serialize, serde::Deserialize, clap::Args)]
 #[serde(rename_all = "camelCase")]
 pub struct IssuanceFilter {
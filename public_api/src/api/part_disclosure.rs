@@ -0,0 +1,53 @@
+use super::*;
+
+#[derive(GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(description = "Shares a single file from an attestation, without revealing the other files certified alongside it, like the rest of the attachments of an email or the rest of the files in a zip.")]
+#[serde(rename_all = "camelCase")]
+pub struct PartDisclosureInput {
+  #[graphql(description = "Id of the attestation containing the file to share.")]
+  pub attestation_id: i32,
+  #[graphql(description = "The sha256 hash of the file to share, hex encoded.")]
+  pub part_hash: String,
+}
+
+#[derive(Debug, GraphQLObject, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "A link to a redacted certificate for a single file. It proves the file belongs to a certified document without disclosing the document's other files.")]
+pub struct PartDisclosure {
+  #[graphql(description = "Unique id for this disclosure.")]
+  pub id: i32,
+  #[graphql(description = "The sha256 hash of the disclosed file.")]
+  pub part_hash: String,
+  #[graphql(description = "The name of the disclosed file.")]
+  pub friendly_name: String,
+  #[graphql(description = "Anyone with this url can see the redacted certificate.")]
+  pub url: String,
+  #[graphql(description = "Date in which this disclosure was created.")]
+  pub created_at: UtcDateTime,
+}
+
+impl PartDisclosureInput {
+  pub async fn process(self, context: &Context) -> FieldResult<PartDisclosure> {
+    let story = context.org().await?
+      .attestation_scope()
+      .id_eq(&self.attestation_id)
+      .one().await?
+      .story().await?;
+
+    let disclosure = context.site.part_disclosure().create(&story, &self.part_hash).await?;
+    PartDisclosure::db_to_graphql(disclosure).await
+  }
+}
+
+impl PartDisclosure {
+  pub async fn db_to_graphql(d: db::PartDisclosure) -> FieldResult<Self> {
+    let part = d.document_part().await?;
+    Ok(PartDisclosure {
+      id: d.attrs.id,
+      url: d.url(),
+      part_hash: part.attrs.hash,
+      friendly_name: part.attrs.friendly_name,
+      created_at: d.attrs.created_at,
+    })
+  }
+}
@@ -17,6 +17,13 @@ pub trait Showable<Model: SqlxModel<State=Site>, Filter: Send>: Sized {
   fn select_by_id(org_id: i32, id: <Model as SqlxModel>::Id) -> <Model as SqlxModel>::SelectModel;
   async fn db_to_graphql(d: Model) -> ConstataResult<Self>;
 
+  /* Filters that can't be expressed as a plain select, like full text searches, are resolved here first. */
+  async fn resolve_filter(_context: &Context, filter: Option<Filter>) -> FieldResult<Option<Filter>>
+    where Filter: 'async_trait
+  {
+    Ok(filter)
+  }
+
   async fn resource(context: &Context, id: <Model as SqlxModel>::Id) -> FieldResult<Self> 
     where <Model as SqlxModel>::Id: 'async_trait
   {
@@ -58,6 +65,7 @@ pub trait Showable<Model: SqlxModel<State=Site>, Filter: Send>: Sized {
       }
     }; 
 
+    let filter = Self::resolve_filter(context, filter).await?;
     let selected = <Model as SqlxModel>::SelectModelHub::from_state(context.site.clone())
       .use_struct( Self::filter_to_select(context.org_id(), filter) )
       .maybe_order_by(maybe_order_by)
@@ -76,6 +84,7 @@ pub trait Showable<Model: SqlxModel<State=Site>, Filter: Send>: Sized {
   async fn count( context: &Context, filter: Option<Filter>) -> FieldResult<ListMetadata>
     where Filter: 'async_trait
   {
+    let filter = Self::resolve_filter(context, filter).await?;
     let count = <Model as SqlxModel>::SelectModelHub::from_state(context.site.clone())
       .use_struct( Self::filter_to_select(context.org_id(), filter) )
       .count().await?
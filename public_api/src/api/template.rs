@@ -126,3 +126,93 @@ impl TemplateInput {
     Ok(Template::db_to_graphql(template).await?)
   }
 }
+
+#[derive(Debug, GraphQLObject, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "An immutable version of a template. Each change to a template creates a new version, and issuances always render with the version they were created with.")]
+pub struct TemplateVersion {
+  #[graphql(description = "An identifier for this version")]
+  pub id: i32,
+  #[graphql(description = "The template this is a version of")]
+  pub template_id: i32,
+  #[graphql(description = "Versions are numbered from 1, in the order they were created")]
+  pub version_number: i32,
+  #[graphql(description = "The schema as of this version")]
+  pub schema: TemplateSchema,
+  #[graphql(description = "The custom message as of this version")]
+  pub custom_message: Option<String>,
+  #[graphql(description = "The date when this version was created")]
+  pub created_at: UtcDateTime,
+}
+
+impl TemplateVersion {
+  pub fn db_to_graphql(d: db::TemplateVersion) -> ConstataResult<Self> {
+    Ok(TemplateVersion {
+      schema: d.parsed_schema()?,
+      id: d.attrs.id,
+      template_id: d.attrs.template_id,
+      version_number: d.attrs.version_number,
+      custom_message: d.attrs.custom_message,
+      created_at: d.attrs.created_at,
+    })
+  }
+}
+
+#[derive(Debug, GraphQLObject, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+#[graphql(description = "What changed between two versions of a template.")]
+pub struct TemplateVersionDiff {
+  pub template_id: i32,
+  pub from_version: i32,
+  pub to_version: i32,
+  #[graphql(description = "Schema fields only present in the newer version")]
+  pub added_fields: Vec<String>,
+  #[graphql(description = "Schema fields only present in the older version")]
+  pub removed_fields: Vec<String>,
+  #[graphql(description = "Schema fields whose type, label or constraints changed")]
+  pub changed_fields: Vec<String>,
+  pub custom_message_changed: bool,
+  pub og_title_override_changed: bool,
+  #[graphql(description = "Files only present in the newer version's zip")]
+  pub added_files: Vec<String>,
+  #[graphql(description = "Files only present in the older version's zip")]
+  pub removed_files: Vec<String>,
+  #[graphql(description = "Files present in both zips with different contents")]
+  pub changed_files: Vec<String>,
+}
+
+impl TemplateVersionDiff {
+  pub async fn between(template: &db::Template, from_version: i32, to_version: i32) -> ConstataResult<Self> {
+    let diff = template.find_version(from_version).await?.diff(&template.find_version(to_version).await?).await?;
+    Ok(TemplateVersionDiff {
+      template_id: template.attrs.id,
+      from_version,
+      to_version,
+      added_fields: diff.added_fields,
+      removed_fields: diff.removed_fields,
+      changed_fields: diff.changed_fields,
+      custom_message_changed: diff.custom_message_changed,
+      og_title_override_changed: diff.og_title_override_changed,
+      added_files: diff.added_files,
+      removed_files: diff.removed_files,
+      changed_files: diff.changed_files,
+    })
+  }
+}
+
+#[derive(Clone, GraphQLInputObject, Serialize, Deserialize)]
+#[graphql(description = "Makes the contents of an older version the latest version of a template. Existing versions are never modified.")]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateRollbackInput {
+  pub id: i32,
+  pub version_number: i32,
+}
+
+impl TemplateRollbackInput {
+  pub async fn process(self, context: &Context) -> FieldResult<Template> {
+    let template = context.site.template()
+      .select().id_eq(&self.id).org_id_eq(context.org_id()).one().await?;
+    let (template, _) = template.rollback_to(self.version_number).await?;
+    Ok(Template::db_to_graphql(template).await?)
+  }
+}
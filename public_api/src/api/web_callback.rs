@@ -11,6 +11,10 @@ pub struct WebCallback {
   pub kind: WebCallbackKind,
   #[graphql(description = "The related resource ID, has a different meaning depending on the web callback kind. If its about an attestation, the it's the attestation's id.")]
   pub resource_id: i32,
+  #[graphql(description = "For DocumentParked and DocumentAccepted callbacks, the document id. Their resource_id is the document's story id.")]
+  pub document_id: Option<String>,
+  #[graphql(description = "The WebCallbackEndpoint this callback is sent to.")]
+  pub web_callback_endpoint_id: Option<i32>,
   #[graphql(description = "The state of this callback. Pending, Done or Failed. Callbacks are retried 10 times with exponential backoff. The first attempt is done immediately, the second one 5 minutes later, then at 10 minutes, 20, and so on. All attempts are WebCallbackAttempt.")]
   pub state: WebCallbackState,
   #[graphql(description = "The most recent attempt, if any.")]
@@ -78,6 +82,8 @@ impl Showable<db::WebCallback, WebCallbackFilter> for WebCallback {
       id: d.attrs.id,
       kind: d.attrs.kind,
       resource_id: d.attrs.resource_id,
+      document_id: d.attrs.document_id.clone(),
+      web_callback_endpoint_id: d.attrs.web_callback_endpoint_id,
       state: d.attrs.state,
       last_attempt_id: d.attrs.last_attempt_id,
       created_at: d.attrs.created_at,
@@ -88,7 +94,7 @@ impl Showable<db::WebCallback, WebCallbackFilter> for WebCallback {
 }
 
 #[derive(GraphQLObject)]
-#[graphql(description = "Every attempt we made to deliver a WebCallback to your web_callbacks_url")]
+#[graphql(description = "Every attempt we made to deliver a WebCallback to one of your WebCallbackEndpoints")]
 pub struct WebCallbackAttempt {
   #[graphql(description = "Unique identifier for this Entry, across all Issuances.")]
   id: i32,
@@ -96,8 +102,10 @@ pub struct WebCallbackAttempt {
   web_callback_id: i32,
   #[graphql(description = "The date in which we made this attempt.")]
   attempted_at: UtcDateTime,
-  #[graphql(description = "The url to which we made this attempt, which was your web_callbacks_url at the time.")]
+  #[graphql(description = "The url to which we made this attempt, which was the endpoint's url at the time.")]
   url: String,
+  #[graphql(description = "The endpoint this attempt was made to.")]
+  web_callback_endpoint_id: Option<i32>,
   #[graphql(description = "The result of making this attempt. OK means everything went fine.")]
   result_code: WebCallbackResultCode,
   #[graphql(description = "A text associated to the result code, showing your server's response body or details about network errors.")]
@@ -152,10 +160,176 @@ impl Showable<db::WebCallbackAttempt, WebCallbackAttemptFilter> for WebCallbackA
       url: d.attrs.url,
       result_code: d.attrs.result_code,
       result_text: d.attrs.result_text,
+      web_callback_endpoint_id: d.attrs.web_callback_endpoint_id,
     })
   }
 }
 
+#[derive(Debug, GraphQLObject, serde::Deserialize, serde::Serialize)]
+#[graphql(description = "A URL on your own website where we send WebCallbacks, such as 'https://yoursite.com/web_callbacks_from_constata'. You can have several, each one subscribed to some kinds of events.")]
+#[serde(rename_all = "camelCase")]
+pub struct WebCallbackEndpoint {
+  #[graphql(description = "Unique identifier for this endpoint.")]
+  pub id: i32,
+  #[graphql(description = "We POST web callbacks to this url.")]
+  pub url: String,
+  #[graphql(description = "The kinds of events this endpoint is subscribed to, unless it gets all of them.")]
+  pub kinds: Vec<WebCallbackKind>,
+  #[graphql(description = "Whether this endpoint gets every kind of event, including the ones we add later.")]
+  pub all_kinds: bool,
+  #[graphql(description = "Disabled endpoints don't get new callbacks, and pending ones are failed without sending them.")]
+  pub enabled: bool,
+  #[graphql(description = "The date in which this endpoint was registered.")]
+  pub created_at: UtcDateTime,
+  #[graphql(description = "We sign every request to this endpoint with this secret, see the Constata-Signature header.")]
+  pub secret: String,
+  #[graphql(description = "After a rotation, requests are also signed with the previous secret until this date.")]
+  pub previous_secret_expires_at: Option<UtcDateTime>,
+}
+
+#[derive(Debug, Clone, Default, GraphQLInputObject, serde::Serialize, serde::Deserialize, clap::Args)]
+#[serde(rename_all = "camelCase")]
+pub struct WebCallbackEndpointFilter {
+  #[arg(long, help="Fetch a specific list of endpoints by their ids", action=clap::ArgAction::Append)]
+  pub ids: Option<Vec<i32>>,
+  #[arg(long, help="Fetch a specific endpoint by id")]
+  pub id_eq: Option<i32>,
+  #[arg(long, help="Filter enabled or disabled endpoints")]
+  pub enabled_eq: Option<bool>,
+}
+
+#[rocket::async_trait]
+impl Showable<db::WebCallbackEndpoint, WebCallbackEndpointFilter> for WebCallbackEndpoint {
+  fn sort_field_to_order_by(field: &str) -> Option<WebCallbackEndpointOrderBy> {
+    match field {
+      "id" => Some(WebCallbackEndpointOrderBy::Id),
+      "createdAt" => Some(WebCallbackEndpointOrderBy::CreatedAt),
+      _ => None,
+    }
+  }
+
+  fn filter_to_select(org_id: i32, filter: Option<WebCallbackEndpointFilter>) -> SelectWebCallbackEndpoint {
+    if let Some(f) = filter {
+      SelectWebCallbackEndpoint {
+        id_in: f.ids,
+        org_id_eq: Some(org_id),
+        id_eq: f.id_eq,
+        enabled_eq: f.enabled_eq,
+        ..Default::default()
+      }
+    } else {
+      SelectWebCallbackEndpoint {
+        org_id_eq: Some(org_id),
+        ..Default::default()
+      }
+    }
+  }
+
+  fn select_by_id(org_id: i32, id: i32) -> SelectWebCallbackEndpoint {
+    SelectWebCallbackEndpoint { id_eq: Some(id), org_id_eq: Some(org_id), ..Default::default() }
+  }
+
+  async fn db_to_graphql(d: db::WebCallbackEndpoint) -> ConstataResult<Self> {
+    Ok(WebCallbackEndpoint{
+      id: d.attrs.id,
+      kinds: d.kinds().await?,
+      all_kinds: d.attrs.all_kinds,
+      url: d.attrs.url,
+      enabled: d.attrs.enabled,
+      created_at: d.attrs.created_at,
+      secret: d.attrs.secret,
+      previous_secret_expires_at: d.attrs.previous_secret_expires_at,
+    })
+  }
+}
+
+#[derive(Debug, Clone, Default, GraphQLInputObject, serde::Serialize, serde::Deserialize, clap::Args)]
+#[graphql(description = "Registers a new url to send web callbacks to.")]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebCallbackEndpointInput {
+  #[arg(help="The url where we should POST web callbacks")]
+  #[graphql(description = "The url where we should POST web callbacks.")]
+  pub url: String,
+  #[arg(long="kind", value_name="KIND", action=clap::ArgAction::Append,
+    help="Subscribe to this kind of event. Repeat it to subscribe to many. Required unless you use --all-kinds.")]
+  #[graphql(description = "The kinds of events to send to this endpoint. Required unless all_kinds is true.")]
+  pub kinds: Option<Vec<WebCallbackKind>>,
+  #[arg(long, num_args=0..=1, default_missing_value="true",
+    help="Subscribe to every kind of event, including the ones we add later.")]
+  #[graphql(description = "Send every kind of event to this endpoint, including the ones we add later. Can't be used along with kinds.")]
+  pub all_kinds: Option<bool>,
+  #[arg(long, help="Pass false to register the endpoint disabled.")]
+  #[graphql(description = "Whether to start sending callbacks right away. Defaults to true.")]
+  pub enabled: Option<bool>,
+}
+
+impl CreateWebCallbackEndpointInput {
+  pub async fn process(self, context: &Context) -> FieldResult<WebCallbackEndpoint> {
+    let endpoint = context.site.web_callback_endpoint().create(
+      &context.org().await?,
+      &self.url,
+      &self.kinds.unwrap_or_default(),
+      self.all_kinds.unwrap_or(false),
+      self.enabled.unwrap_or(true),
+    ).await?;
+
+    Ok(WebCallbackEndpoint::db_to_graphql(endpoint).await?)
+  }
+}
+
+#[derive(Debug, Clone, Default, GraphQLInputObject, serde::Serialize, serde::Deserialize, clap::Args)]
+#[graphql(description = "Changes the url, subscriptions or status of one of your web callback endpoints. Missing fields are left as they are.")]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebCallbackEndpointInput {
+  #[arg(help="The id of the endpoint to update")]
+  #[graphql(description = "The id of the endpoint to update.")]
+  pub id: i32,
+  #[arg(long, help="A new url for this endpoint")]
+  #[graphql(description = "A new url for this endpoint.")]
+  pub url: Option<String>,
+  #[arg(long="kind", value_name="KIND", action=clap::ArgAction::Append,
+    help="Replaces the subscriptions with these kinds of events. Repeat it to subscribe to many.")]
+  #[graphql(description = "Replaces the subscriptions with these kinds of events. It can't be empty, use all_kinds to get every event.")]
+  pub kinds: Option<Vec<WebCallbackKind>>,
+  #[arg(long, num_args=0..=1, default_missing_value="true",
+    help="Pass true to get every kind of event, or false along with --kind to go back to a list.")]
+  #[graphql(description = "Set to true to get every kind of event, including the ones we add later. Can't be used along with kinds.")]
+  pub all_kinds: Option<bool>,
+  #[arg(long, help="Enable or disable this endpoint")]
+  #[graphql(description = "Enable or disable this endpoint.")]
+  pub enabled: Option<bool>,
+}
+
+impl UpdateWebCallbackEndpointInput {
+  pub async fn process(self, context: &Context) -> FieldResult<WebCallbackEndpoint> {
+    let endpoint = context.org().await?.web_callback_endpoint_scope().id_eq(&self.id).one().await?
+      .update_settings(self.url, self.kinds, self.all_kinds, self.enabled).await?;
+
+    Ok(WebCallbackEndpoint::db_to_graphql(endpoint).await?)
+  }
+}
+
+#[derive(Debug, Clone, Default, GraphQLInputObject, serde::Serialize, serde::Deserialize, clap::Args)]
+#[graphql(description = "Replaces the secret of one of your web callback endpoints. Requests keep being signed with the old secret too for overlap_hours, so you have time to update your server.")]
+#[serde(rename_all = "camelCase")]
+pub struct RotateWebCallbackEndpointSecretInput {
+  #[arg(help="The id of the endpoint whose secret you want to rotate")]
+  #[graphql(description = "The id of the endpoint whose secret you want to rotate.")]
+  pub id: i32,
+  #[arg(long, help="Keep signing with the old secret for this many hours, 24 by default, at most 168.")]
+  #[graphql(description = "Keep signing with the old secret for this many hours. 24 by default, at most 168. Use 0 if the old secret leaked.")]
+  pub overlap_hours: Option<i32>,
+}
+
+impl RotateWebCallbackEndpointSecretInput {
+  pub async fn process(self, context: &Context) -> FieldResult<WebCallbackEndpoint> {
+    let endpoint = context.org().await?.web_callback_endpoint_scope().id_eq(&self.id).one().await?
+      .rotate_secret(self.overlap_hours.map(|h| h as i64)).await?;
+
+    Ok(WebCallbackEndpoint::db_to_graphql(endpoint).await?)
+  }
+}
+
 /*
 constata_lib::describe_one! {
   fulltest!{ can_list_web_callbacks (site, c, client, mut chain)
@@ -163,7 +337,7 @@ constata_lib::describe_one! {
 
     use gql::{
       *,
-      update_web_callbacks_url as update,
+      create_web_callback_endpoint as create_endpoint,
       all_web_callbacks as all,
       all_web_callback_attempts as all_attempts,
     };
@@ -171,9 +345,16 @@ constata_lib::describe_one! {
     let alice = &client.signer;
 
     let url = "http://127.0.0.1:1234/callbacks_url".to_string();
-    let updated: update::ResponseData = client
-      .gql( &UpdateWebCallbacksUrl::build_query(update::Variables{ url: Some(url.clone()) }) ).await;
-    assert_eq!(updated.update_web_callbacks_url.web_callbacks_url, Some(url));
+    let created: create_endpoint::ResponseData = client
+      .gql( &CreateWebCallbackEndpoint::build_query(create_endpoint::Variables{
+        input: create_endpoint::CreateWebCallbackEndpointInput{
+          url: url.clone(),
+          kinds: Some(vec![create_endpoint::WebCallbackKind::ATTESTATION_DONE]),
+          all_kinds: None,
+          enabled: None,
+        }
+      })).await;
+    assert_eq!(created.create_web_callback_endpoint.url, url);
 
     {
       let payloads = vec![alice.signed_payload(b"hello world") ];
@@ -22,6 +22,7 @@ pub mod explorer;
 pub mod payments;
 pub mod terms_acceptance;
 pub mod public_certificates;
+pub mod part_disclosures;
 pub mod react_app;
 pub mod safe;
 
@@ -0,0 +1,16 @@
+use super::*;
+use constata_lib::models::blockchain::PrivateKey;
+use crate::RENDERER;
+
+#[get("/<token>")]
+pub async fn show(site: &State<Site>, token: String, key: &State<PrivateKey>, l: Lang) -> ConstataResult<i18n::LocalizedResponse<'static>> {
+  let response = match site.part_disclosure().select().token_eq(&token).optional().await? {
+    None => RENDERER.i18n("certificates/", l, "not_found.html")?,
+    Some(disclosure) => i18n::LocalizedResponse::new(
+      std::borrow::Cow::Owned(disclosure.html_proof(key, l).await?.into_bytes()),
+      rocket::http::ContentType::HTML,
+      l
+    ),
+  };
+  Ok(response)
+}
@@ -5,7 +5,7 @@ use crate::RENDERER;
 
 #[get("/<token>?<show_content>")]
 pub async fn show(site: &State<Site>, token: String, key: &State<PrivateKey>, l: Lang, show_content: bool) -> ConstataResult<i18n::LocalizedResponse<'static>> {
-  let response = match site.download_proof_link().public_certificate_active(token).one().await {
+  let response = match site.download_proof_link().public_certificate_active(token.clone()).one().await {
     Err(_e) => RENDERER.i18n("certificates/", l, "not_found.html")?,
     Ok(download_proof_link) => {
       if show_content {
@@ -23,6 +23,10 @@ pub async fn show(site: &State<Site>, token: String, key: &State<PrivateKey>, l:
           "public_name": org.attrs.public_name,
           "name_for_on_behalf_of": org.name_for_on_behalf_of().await?,
           "image": download_proof_link.image_url().await?,
+          "token": token,
+          "has_credential": download_proof_link.has_verifiable_credential().await?,
+          "revocation": revocation_status(&download_proof_link).await?,
+          "superseded_by": superseded_by_url(&download_proof_link).await?,
         });
         RENDERER.i18n_and_serialize("certificates/", l, "to_show.html", &context)?
       }
@@ -30,3 +34,37 @@ pub async fn show(site: &State<Site>, token: String, key: &State<PrivateKey>, l:
   };
   Ok(response)
 }
+
+#[get("/<token>/credential.json")]
+pub async fn credential(site: &State<Site>, token: String, key: &State<PrivateKey>) -> ConstataResult<(rocket::http::ContentType, String)> {
+  let download_proof_link = site.download_proof_link().public_certificate_active(token).one().await?;
+  let entry = download_proof_link.entry_optional().await?
+    .ok_or_else(|| Error::validation("certificate", "has_no_verifiable_credential"))?;
+  Ok((rocket::http::ContentType::JSON, entry.verifiable_credential(key).await?.to_json()?))
+}
+
+/* Lets third parties check whether a certificate is still valid without parsing its proof. */
+#[get("/<token>/status.json")]
+pub async fn status(site: &State<Site>, token: String) -> ConstataResult<(rocket::http::ContentType, String)> {
+  let download_proof_link = site.download_proof_link().public_certificate_active(token).one().await?;
+  let revocation = revocation_status(&download_proof_link).await?;
+  let body = json!({
+    "status": if revocation.is_some() { "revoked" } else { "active" },
+    "revocation": revocation,
+  });
+  Ok((rocket::http::ContentType::JSON, serde_json::to_string(&body)?))
+}
+
+async fn revocation_status(link: &DownloadProofLink) -> ConstataResult<Option<RevocationStatus>> {
+  match link.document().await?.revocation().await? {
+    Some(revocation) => Ok(Some(revocation.status().await?)),
+    None => Ok(None),
+  }
+}
+
+async fn superseded_by_url(link: &DownloadProofLink) -> ConstataResult<Option<String>> {
+  match link.entry_optional().await? {
+    Some(entry) => entry.superseded_by_url().await,
+    None => Ok(None),
+  }
+}
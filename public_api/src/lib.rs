@@ -73,7 +73,12 @@ pub fn server(site: Site) -> rocket::Rocket<rocket::Build> {
       terms_acceptance::accept,
     ])
     .mount("/certificate", routes![
-      public_certificates::show
+      public_certificates::show,
+      public_certificates::credential,
+      public_certificates::status,
+    ])
+    .mount("/disclosure", routes![
+      part_disclosures::show,
     ])
     .mount("/graphql", routes![graphiql, get_handler, post_handler, introspect])
     .mount("/", routes![
@@ -250,7 +250,7 @@ pub mod gql {
     AllIssuances,
     Entry,
     AllEntries,
-    UpdateWebCallbacksUrl,
+    CreateWebCallbackEndpoint,
     WebCallback,
     AllWebCallbacks,
     WebCallbackAttempt,
@@ -1,4 +1,4 @@
-use constata_lib::prelude::*;
+use constata_lib::{prelude::*, models::JobKind};
 use email_bot::EmailBot;
 use log::*;
 use std::time::Duration;
@@ -7,6 +7,7 @@ use std::time::Duration;
 async fn main() {
   let site = Site::from_stdin_password().await.unwrap();
   site.audit_log.start();
+  let key = site.keyring().unwrap().expect("keyring is empty. Cannot init.").private_key;
 
   let mut handles = vec![];
 
@@ -31,13 +32,23 @@ async fn main() {
     )
   }
 
-  /*
-  every![500, |s| {
-    run!("workroom_create_received" { s.issuance().create_all_received().await });
-    run!("workroom_complete_all_notified" { s.issuance().try_complete().await });
-    run!("attempting_webhooks" { s.web_callback().attempt_all_pending().await });
+  every![5000, |s| {
+    run!("enqueue_pending_jobs" { s.job().enqueue_pending().await });
+    run!("release_stale_jobs" { s.job().release_stale().await });
+  }];
+
+  for kind in [JobKind::RenderIssuance, JobKind::ApplyBatchSignature, JobKind::CompleteIssuance, JobKind::AttemptWebCallback] {
+    every![500, |s| {
+      run!("jobs" { s.job().work(kind).await });
+    }];
+  }
+
+  every![2000, |s| {
+    match EmailBot::new(s.clone()).await {
+      Ok(email_bot) => { run!("email_callback_jobs" { email_bot.handle_email_callback_jobs().await }); },
+      Err(err) => error!("Error connecting to email bot: {:?}", err),
+    };
   }];
-  */
 
   every![10000, |s| {
     match EmailBot::new(s.clone()).await {
@@ -46,6 +57,13 @@ async fn main() {
     };
   }];
 
+  every![60000, |s| {
+    run!("seal_expired_stories" { s.story().seal_all_expired().await });
+    run!("time_out_cosigners" { s.attestation_cosigner().time_out_all_past_deadline().await });
+    run!("web_captures" { s.web_capture().process_all().await });
+    run!("warm_up_rendered_proofs" { s.rendered_proof().warm_up(&key, 50).await });
+  }];
+
   every![300000, |s| {
     run!("pubkey_domain_endorsement" { s.pubkey_domain_endorsement().process_all().await });
   }];